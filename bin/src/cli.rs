@@ -0,0 +1,236 @@
+//! Command-line arguments of the OpenBook trading tool.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use openbook_v2::state::Side;
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Trade on OpenBook V2 markets")]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Args, Debug)]
+pub struct GlobalArgs {
+    /// Path of the configuration file. Defaults to `$OPENBOOK_CONFIG` or
+    /// `~/.config/openbook/config.toml`.
+    #[arg(long, short = 'c', global = true)]
+    pub config: Option<String>,
+
+    /// Configuration profile, e.g. devnet, mainnet or localnet.
+    #[arg(long, short = 'p', global = true)]
+    pub profile: Option<String>,
+
+    /// URL of the Solana RPC node. Overrides the profile.
+    #[arg(long, short = 'u', global = true)]
+    pub rpc_url: Option<String>,
+
+    /// Path of the keypair of the owner. Overrides the profile.
+    #[arg(long, short = 'k', global = true)]
+    pub keypair: Option<String>,
+
+    /// Commitment level: processed, confirmed or finalized. Overrides the profile.
+    #[arg(long, global = true)]
+    pub commitment: Option<String>,
+
+    /// Address of the market to trade on. Overrides the profile.
+    #[arg(long, short = 'm', global = true)]
+    pub market: Option<Pubkey>,
+
+    /// Open orders account to trade with. Overrides the profile, and is found or created from
+    /// the owner's accounts if neither sets it.
+    #[arg(long, global = true)]
+    pub open_orders_account: Option<Pubkey>,
+
+    /// Wallet receiving the referrer rebates of taker orders when settling. Overrides the
+    /// profile.
+    #[arg(long, global = true)]
+    pub referrer: Option<Pubkey>,
+
+    /// Output format.
+    #[arg(long, short = 'o', global = true, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    /// Simulate transactions instead of sending them.
+    #[arg(long, global = true)]
+    pub simulate: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Show the parameters of the market.
+    MarketInfo,
+
+    /// Show the aggregated levels of the order book.
+    Book {
+        /// Number of price levels to show per side.
+        #[arg(long, default_value_t = 10)]
+        depth: usize,
+    },
+
+    /// Place a limit order, with the order type and time to live of the market's settings
+    /// (post-only, expiring after a day, by default).
+    Place {
+        #[arg(long, value_enum)]
+        side: SideArg,
+
+        /// Limit price, in quote tokens per base token.
+        #[arg(long)]
+        price: f64,
+
+        /// Size of the order, in quote tokens.
+        #[arg(long)]
+        size: u64,
+    },
+
+    /// Cancel an order by its order id.
+    Cancel {
+        #[arg(long)]
+        order_id: u128,
+    },
+
+    /// Cancel all orders of the open orders account.
+    CancelAll,
+
+    /// Deposit native amounts of base and quote tokens into the open orders account.
+    Deposit {
+        #[arg(long, default_value_t = 0)]
+        base: u64,
+
+        #[arg(long, default_value_t = 0)]
+        quote: u64,
+    },
+
+    /// Settle the free balances of the open orders account to the owner's token accounts.
+    Settle,
+
+    /// Show the balances and orders of the open orders account.
+    OpenOrders,
+
+    /// Create a new market administered by the owner.
+    CreateMarket(CreateMarketArgs),
+
+    /// Process pending events of the market's event heap.
+    Crank {
+        /// Maximum number of events to process.
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Cancel the orders of every open orders account of the owner, on all markets, and settle
+    /// their funds, retrying until the accounts are verified flat.
+    KillSwitch {
+        /// Close the accounts once flat, returning their rent to the owner.
+        #[arg(long)]
+        close: bool,
+
+        /// Maximum number of rounds of transactions before giving up.
+        #[arg(long, default_value_t = 10)]
+        max_attempts: usize,
+    },
+
+    /// Record the book and event heap changes of markets to rotating JSONL files until
+    /// interrupted.
+    Record {
+        /// Markets to record. Defaults to the market of the profile.
+        #[arg(long = "markets", value_delimiter = ',')]
+        markets: Vec<Pubkey>,
+
+        /// Directory the recordings are written to.
+        #[arg(long, default_value = "recordings")]
+        dir: String,
+
+        /// Milliseconds between two polls.
+        #[arg(long, default_value_t = 400)]
+        interval_ms: u64,
+
+        /// Size in megabytes after which a file is rotated.
+        #[arg(long, default_value_t = 100)]
+        max_file_mb: u64,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct CreateMarketArgs {
+    #[arg(long)]
+    pub name: String,
+
+    #[arg(long)]
+    pub base_mint: Pubkey,
+
+    #[arg(long)]
+    pub quote_mint: Pubkey,
+
+    #[arg(long)]
+    pub base_lot_size: i64,
+
+    #[arg(long)]
+    pub quote_lot_size: i64,
+
+    /// Maker fee, in millionths. Negative for a rebate.
+    #[arg(long, default_value_t = 0, allow_hyphen_values = true)]
+    pub maker_fee: i64,
+
+    /// Taker fee, in millionths.
+    #[arg(long, default_value_t = 0)]
+    pub taker_fee: i64,
+
+    /// Unix timestamp the market expires at, or 0 for a market that never expires.
+    #[arg(long, default_value_t = 0)]
+    pub time_expiry: i64,
+
+    #[arg(long)]
+    pub oracle_a: Option<Pubkey>,
+
+    #[arg(long)]
+    pub oracle_b: Option<Pubkey>,
+
+    /// Largest oracle confidence interval, relative to the price, the program accepts.
+    #[arg(long, default_value_t = 0.1)]
+    pub conf_filter: f32,
+
+    /// Largest age in slots of an oracle price the program accepts. Unlimited by default.
+    #[arg(long)]
+    pub max_staleness_slots: Option<u32>,
+
+    /// Admin collecting the fees. Defaults to the keypair.
+    #[arg(long)]
+    pub collect_fee_admin: Option<Pubkey>,
+
+    /// Admin co-signing every order, making the market permissioned.
+    #[arg(long)]
+    pub open_orders_admin: Option<Pubkey>,
+
+    /// Admin allowed to consume events, instead of anyone.
+    #[arg(long)]
+    pub consume_events_admin: Option<Pubkey>,
+
+    /// Admin allowed to expire and close the market.
+    #[arg(long)]
+    pub close_market_admin: Option<Pubkey>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Table,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SideArg {
+    Bid,
+    Ask,
+}
+
+impl From<SideArg> for Side {
+    fn from(side: SideArg) -> Self {
+        match side {
+            SideArg::Bid => Side::Bid,
+            SideArg::Ask => Side::Ask,
+        }
+    }
+}
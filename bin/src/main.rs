@@ -1,26 +1,456 @@
-use openbook::ob_client::OBClient;
-use solana_sdk::commitment_config::CommitmentConfig;
-use solana_sdk::pubkey;
-use solana_sdk::signature::Keypair;
-use solana_sdk::signer::EncodableKey;
-use std::sync::Arc;
+mod cli;
+mod output;
+
+use std::mem::size_of;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use openbook::{
+    book,
+    config::{expand_home, ClientConfig, Config},
+    instructions,
+    kill_switch::{KillSwitchConfig, KillSwitchReport},
+    ob_client::{self, OBClient},
+    recorder::{Recorder, RecorderConfig},
+};
+use openbook_v2::state::{BookSide, EventHeap, OracleConfigParams, Side};
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair},
+    signer::Signer,
+    system_instruction,
+    transaction::Transaction,
+};
+
+use cli::{Cli, Command, CreateMarketArgs, GlobalArgs};
+
+#[derive(Serialize)]
+struct MarketInfoView {
+    address: String,
+    name: String,
+    base_mint: String,
+    quote_mint: String,
+    base_decimals: u8,
+    quote_decimals: u8,
+    base_lot_size: i64,
+    quote_lot_size: i64,
+    maker_fee: i64,
+    taker_fee: i64,
+    time_expiry: i64,
+    bids: String,
+    asks: String,
+    event_heap: String,
+    oracle_a: Option<String>,
+    oracle_b: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LevelView {
+    price: f64,
+    size: f64,
+    orders: usize,
+}
+
+#[derive(Serialize)]
+struct BookView {
+    bids: Vec<LevelView>,
+    asks: Vec<LevelView>,
+}
+
+#[derive(Serialize)]
+struct OpenOrderView {
+    order_id: String,
+    client_order_id: u64,
+    side: String,
+    price: f64,
+}
+
+#[derive(Serialize)]
+struct OpenOrdersView {
+    address: String,
+    owner: String,
+    market: String,
+    delegate: Option<String>,
+    base_free: f64,
+    quote_free: f64,
+    referrer_rebates: f64,
+    bids_base_lots: i64,
+    asks_base_lots: i64,
+    orders: Vec<OpenOrderView>,
+}
+
+#[derive(Serialize)]
+struct FlattenedAccountView {
+    address: String,
+    market: String,
+    open_orders: usize,
+    closed: bool,
+    unsettled: bool,
+}
+
+#[derive(Serialize)]
+struct KillSwitchView {
+    attempts: usize,
+    accounts: Vec<FlattenedAccountView>,
+    signatures: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct TransactionView {
+    signature: Option<String>,
+    simulated: bool,
+    error: Option<String>,
+    units_consumed: Option<u64>,
+    logs: Option<Vec<String>>,
+}
 
 #[tokio::main]
-async fn main() {
-    let /*mut*/ _client = OBClient::new(
-        "https://devnet.solana.com".into(),
-        Arc::new(Keypair::read_from_file("keypair.json").unwrap()),
-        None,
-        CommitmentConfig::confirmed(),
-        pubkey!("gQN1TNHiqj5x82ZQd7JZ8rm8WD4xwWtXxd4onReWZNK"),
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let global = &cli.global;
+    let config = resolve_config(global)?;
+    if let Command::CreateMarket(args) = &cli.command {
+        return create_market(global, &config, args).await;
+    }
+    if let Command::Record {
+        markets,
+        dir,
+        interval_ms,
+        max_file_mb,
+    } = &cli.command
+    {
+        let markets = match (markets.is_empty(), config.market) {
+            (false, _) => markets.clone(),
+            (true, Some(market)) => vec![market],
+            (true, None) => bail!("--markets or a profile market is required"),
+        };
+        let recorder_config = RecorderConfig {
+            poll_interval: Duration::from_millis(*interval_ms),
+            max_file_bytes: max_file_mb * 1024 * 1024,
+            ..RecorderConfig::new(markets, dir)
+        };
+        let mut recorder =
+            Recorder::new(config.rpc_url.clone(), config.commitment, recorder_config).await?;
+        return recorder
+            .run(async {
+                let _ = tokio::signal::ctrl_c().await;
+            })
+            .await;
+    }
+
+    let mut client = match &cli.command {
+        Command::MarketInfo
+        | Command::Book { .. }
+        | Command::Crank { .. }
+        | Command::KillSwitch { .. } => {
+            OBClient::from_config_without_open_orders_account(&config).await?
+        }
+        _ if global.simulate && config.open_orders_account.is_none() => {
+            bail!("--open-orders-account is required when simulating")
+        }
+        _ => OBClient::from_config(&config).await?,
+    };
+
+    match cli.command {
+        Command::MarketInfo => output::print(global.output, &market_info(&client)),
+        Command::Book { depth } => {
+            let (bids, asks) = client.load_book().await?;
+            let view = |orders: &[book::BookOrder]| -> Vec<LevelView> {
+                book::levels(orders, depth)
+                    .into_iter()
+                    .map(|level| LevelView {
+                        price: client.context.price_lots_to_ui(level.price_lots),
+                        size: client.context.base_lots_to_ui(level.quantity),
+                        orders: level.orders,
+                    })
+                    .collect()
+            };
+            output::print(
+                global.output,
+                &BookView {
+                    bids: view(&bids),
+                    asks: view(&asks),
+                },
+            )
+        }
+        Command::Place { side, price, size } => {
+            let trx = client.place_limit_order(price, size, side.into()).await?;
+            execute(&client, global, &trx).await
+        }
+        Command::Cancel { order_id } => {
+            let trx = client.cancel_limit_order(order_id).await?;
+            execute(&client, global, &trx).await
+        }
+        Command::CancelAll => {
+            let trx = client.cancel_all().await?;
+            execute(&client, global, &trx).await
+        }
+        Command::Deposit { base, quote } => {
+            let trx = client
+                .deposit(
+                    client.market_id,
+                    base,
+                    quote,
+                    client.base_ata,
+                    client.quote_ata,
+                    client.market_info.market_base_vault,
+                    client.market_info.market_quote_vault,
+                )
+                .await?;
+            execute(&client, global, &trx).await
+        }
+        Command::Settle => {
+            let trx = client.settle_funds().await?;
+            execute(&client, global, &trx).await
+        }
+        Command::OpenOrders => output::print(global.output, &open_orders(&client).await?),
+        Command::Crank { limit } => match client.consume_events(limit).await? {
+            Some(trx) => execute(&client, global, &trx).await,
+            None => output::print(global.output, &serde_json::json!({ "pending_events": 0 })),
+        },
+        Command::KillSwitch {
+            close,
+            max_attempts,
+        } => {
+            if global.simulate {
+                bail!("kill-switch sends several rounds of transactions and cannot be simulated");
+            }
+            let report = client
+                .kill_switch(KillSwitchConfig {
+                    close_accounts: close,
+                    max_attempts,
+                    ..Default::default()
+                })
+                .await?;
+            output::print(global.output, &kill_switch_view(&report))
+        }
+        Command::CreateMarket(_) | Command::Record { .. } => unreachable!(),
+    }
+}
+
+/// Resolves the configuration profile, with the command-line flags taking precedence.
+fn resolve_config(global: &GlobalArgs) -> Result<ClientConfig> {
+    let config = match &global.config {
+        Some(path) => Config::load(path)?,
+        None => Config::load_default()?,
+    };
+    let mut resolved = config.resolve(global.profile.as_deref())?;
+    if let Some(rpc_url) = &global.rpc_url {
+        resolved.rpc_url = rpc_url.clone();
+    }
+    if let Some(keypair) = &global.keypair {
+        resolved.keypair_path = expand_home(keypair);
+    }
+    if let Some(commitment) = &global.commitment {
+        resolved.commitment = CommitmentConfig::from_str(commitment)
+            .map_err(|err| anyhow!("invalid commitment {commitment}: {err}"))?;
+    }
+    if global.market.is_some() {
+        resolved.market = global.market;
+    }
+    if global.open_orders_account.is_some() {
+        resolved.open_orders_account = global.open_orders_account;
+    }
+    if global.referrer.is_some() {
+        resolved.referrer = global.referrer;
+    }
+    Ok(resolved)
+}
+
+/// Sends `trx`, or only simulates it with `--simulate`, and prints the outcome.
+async fn execute(client: &OBClient, global: &GlobalArgs, trx: &Transaction) -> Result<()> {
+    let view = if global.simulate {
+        let result = client.simulate_trx(trx).await?;
+        TransactionView {
+            signature: None,
+            simulated: true,
+            error: result.err.map(|err| err.to_string()),
+            units_consumed: result.units_consumed,
+            logs: result.logs,
+        }
+    } else {
+        let signature = client.send_trx(trx).await?;
+        TransactionView {
+            signature: Some(signature.to_string()),
+            simulated: false,
+            error: None,
+            units_consumed: None,
+            logs: None,
+        }
+    };
+    output::print(global.output, &view)
+}
+
+fn market_info(client: &OBClient) -> MarketInfoView {
+    let market = &client.market_info;
+    MarketInfoView {
+        address: client.market_id.to_string(),
+        name: market.name().to_string(),
+        base_mint: market.base_mint.to_string(),
+        quote_mint: market.quote_mint.to_string(),
+        base_decimals: market.base_decimals,
+        quote_decimals: market.quote_decimals,
+        base_lot_size: market.base_lot_size,
+        quote_lot_size: market.quote_lot_size,
+        maker_fee: market.maker_fee,
+        taker_fee: market.taker_fee,
+        time_expiry: market.time_expiry,
+        bids: market.bids.to_string(),
+        asks: market.asks.to_string(),
+        event_heap: market.event_heap.to_string(),
+        oracle_a: Option::<Pubkey>::from(market.oracle_a).map(|key| key.to_string()),
+        oracle_b: Option::<Pubkey>::from(market.oracle_b).map(|key| key.to_string()),
+    }
+}
+
+async fn open_orders(client: &OBClient) -> Result<OpenOrdersView> {
+    let account = client.load_open_orders_account().await?;
+    let position = &account.position;
+    Ok(OpenOrdersView {
+        address: client.open_orders_account.to_string(),
+        owner: account.owner.to_string(),
+        market: account.market.to_string(),
+        delegate: Option::<Pubkey>::from(account.delegate).map(|key| key.to_string()),
+        base_free: client
+            .context
+            .base_native_to_ui(position.base_free_native as i64),
+        quote_free: client
+            .context
+            .quote_native_to_ui(position.quote_free_native as i64),
+        referrer_rebates: client
+            .context
+            .quote_native_to_ui(position.referrer_rebates_available as i64),
+        bids_base_lots: position.bids_base_lots,
+        asks_base_lots: position.asks_base_lots,
+        orders: account
+            .all_orders_in_use()
+            .map(|order| OpenOrderView {
+                order_id: order.id.to_string(),
+                client_order_id: order.client_id,
+                side: match order.side_and_tree().side() {
+                    Side::Bid => "bid".to_string(),
+                    Side::Ask => "ask".to_string(),
+                },
+                price: client.context.price_lots_to_ui(order.locked_price),
+            })
+            .collect(),
+    })
+}
+
+fn kill_switch_view(report: &KillSwitchReport) -> KillSwitchView {
+    KillSwitchView {
+        attempts: report.attempts,
+        accounts: report
+            .accounts
+            .iter()
+            .map(|account| FlattenedAccountView {
+                address: account.address.to_string(),
+                market: account.market.to_string(),
+                open_orders: account.open_orders,
+                closed: account.closed,
+                unsettled: account.unsettled,
+            })
+            .collect(),
+        signatures: report
+            .signatures
+            .iter()
+            .map(|signature| signature.to_string())
+            .collect(),
+    }
+}
+
+/// Allocates the book sides and event heap of a new market and creates it in one transaction.
+async fn create_market(
+    global: &GlobalArgs,
+    config: &ClientConfig,
+    args: &CreateMarketArgs,
+) -> Result<()> {
+    let owner = read_keypair_file(&config.keypair_path)
+        .map_err(|err| anyhow!("failed to read keypair {}: {err}", config.keypair_path))?;
+    let rpc = RpcClient::new_with_commitment(config.rpc_url.clone(), config.commitment);
+    let market = Keypair::new();
+    let bids = Keypair::new();
+    let asks = Keypair::new();
+    let event_heap = Keypair::new();
+    let token_program = rpc.get_account(&args.base_mint).await?.owner;
+    if rpc.get_account(&args.quote_mint).await?.owner != token_program {
+        bail!("base and quote mints use different token programs");
+    }
+
+    let mut ixs = vec![];
+    for (account, space) in [
+        (&bids, 8 + size_of::<BookSide>()),
+        (&asks, 8 + size_of::<BookSide>()),
+        (&event_heap, 8 + size_of::<EventHeap>()),
+    ] {
+        ixs.push(system_instruction::create_account(
+            &owner.pubkey(),
+            &account.pubkey(),
+            rpc.get_minimum_balance_for_rent_exemption(space).await?,
+            space as u64,
+            &openbook_v2::id(),
+        ));
+    }
+    ixs.push(ob_client::create_market_ix(
+        owner.pubkey(),
+        token_program,
+        market.pubkey(),
+        instructions::market_authority(&market.pubkey()),
+        bids.pubkey(),
+        asks.pubkey(),
+        event_heap.pubkey(),
+        args.base_mint,
+        args.quote_mint,
+        args.oracle_a,
+        args.oracle_b,
+        args.collect_fee_admin.unwrap_or(owner.pubkey()),
+        args.open_orders_admin,
+        args.consume_events_admin,
+        args.close_market_admin,
+        instructions::event_authority(),
+        args.name.clone(),
+        OracleConfigParams {
+            conf_filter: args.conf_filter,
+            max_staleness_slots: args.max_staleness_slots,
+        },
+        args.base_lot_size,
+        args.quote_lot_size,
+        args.maker_fee,
+        args.taker_fee,
+        args.time_expiry,
+    ));
+
+    let trx = Transaction::new_signed_with_payer(
+        &ixs,
+        Some(&owner.pubkey()),
+        &[&owner, &market, &bids, &asks, &event_heap],
+        rpc.get_latest_blockhash().await?,
+    );
+    if global.simulate {
+        let result = rpc.simulate_transaction(&trx).await?.value;
+        return output::print(
+            global.output,
+            &TransactionView {
+                signature: None,
+                simulated: true,
+                error: result.err.map(|err| err.to_string()),
+                units_consumed: result.units_consumed,
+                logs: result.logs,
+            },
+        );
+    }
+
+    let signature = rpc.send_and_confirm_transaction(&trx).await?;
+    output::print(
+        global.output,
+        &serde_json::json!({
+            "market": market.pubkey().to_string(),
+            "signature": signature.to_string(),
+        }),
     )
-    .await
-    .unwrap();
-
-    // client.create_market().await.unwrap();
-    // client.find_or_create_account().await.unwrap()
-    // client
-    //     .place_market_order(1000.0, 1000, Side::Bid)
-    //     .await
-    //     .unwrap();
 }
@@ -0,0 +1,81 @@
+//! Rendering of command results as JSON or as plain text tables.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::cli::OutputFormat;
+
+/// Prints `value` in `format`.
+///
+/// In table format, a list of objects is printed as one row per object, and an object as one
+/// `key: value` line per field.
+pub fn print(format: OutputFormat, value: &impl Serialize) -> Result<()> {
+    let value = serde_json::to_value(value)?;
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&value)?),
+        OutputFormat::Table => print_table(&value),
+    }
+    Ok(())
+}
+
+fn print_table(value: &Value) {
+    match value {
+        Value::Array(rows) => {
+            let Some(Value::Object(first)) = rows.first() else {
+                rows.iter().for_each(|row| println!("{}", cell(row)));
+                return;
+            };
+            let headers: Vec<&String> = first.keys().collect();
+            let cells: Vec<Vec<String>> = rows
+                .iter()
+                .map(|row| headers.iter().map(|key| cell(&row[key.as_str()])).collect())
+                .collect();
+            let widths: Vec<usize> = headers
+                .iter()
+                .enumerate()
+                .map(|(i, header)| {
+                    cells
+                        .iter()
+                        .map(|row| row[i].len())
+                        .chain([header.len()])
+                        .max()
+                        .unwrap_or_default()
+                })
+                .collect();
+
+            let line = |row: Vec<&str>| {
+                row.iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{cell:<width$}"))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+            };
+            println!("{}", line(headers.iter().map(|h| h.as_str()).collect()));
+            for row in &cells {
+                println!("{}", line(row.iter().map(String::as_str).collect()));
+            }
+        }
+        Value::Object(fields) => {
+            let width = fields.keys().map(String::len).max().unwrap_or_default();
+            for (key, value) in fields {
+                match value {
+                    Value::Array(_) | Value::Object(_) => {
+                        println!("{key}:");
+                        print_table(value);
+                    }
+                    _ => println!("{key:<width$}  {}", cell(value)),
+                }
+            }
+        }
+        _ => println!("{}", cell(value)),
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,389 @@
+//! Builders of the admin-only instructions of a market, and of the stub oracles used to test
+//! markets.
+//!
+//! Unlike the builders of `instructions`, each builder checks the signer against the admin key
+//! of the `Market` the program requires, failing before anything is sent if it does not match,
+//! or if the market has no such admin. The market keys the instruction needs are read from the
+//! `MarketContext`.
+//!
+//! Markets are wound down by their close market admin: `SetMarketExpired` expires the market,
+//! `PruneOrders` removes the orders left on the book, `SettleFundsExpired` returns the funds of
+//! the open orders accounts to their owners, and `CloseMarket` closes the empty market, returning
+//! its rent. The fees accrued by a market are swept by its collect fee admin.
+//!
+//! The orders placed on a permissioned market are co-signed by its open orders admin, see
+//! `sign_open_orders`.
+
+use std::fmt;
+
+use anchor_lang::{prelude::System, Id};
+use anchor_spl::token::Token;
+use anyhow::Result;
+use openbook_v2::state::{Market, OpenOrdersAccount, StubOracle};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::Signer,
+    transaction::{Transaction, VersionedTransaction},
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+
+use crate::{context::MarketContext, instructions};
+
+/// An admin of a market, allowed to sign the instructions restricted to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AdminRole {
+    /// Expires, prunes, settles and closes the market.
+    CloseMarket,
+
+    /// Sweeps the fees accrued by the market.
+    CollectFee,
+
+    /// Cranks the event heap, anyone can when the market has none.
+    ConsumeEvents,
+
+    /// Co-signs the orders placed on a permissioned market, anyone can trade when the market
+    /// has none.
+    OpenOrders,
+}
+
+impl AdminRole {
+    /// The key of the admin of `market`, `None` if the market has none.
+    pub fn admin(self, market: &Market) -> Option<Pubkey> {
+        match self {
+            Self::CloseMarket => market.close_market_admin.into(),
+            Self::CollectFee => Some(market.collect_fee_admin),
+            Self::ConsumeEvents => market.consume_events_admin.into(),
+            Self::OpenOrders => market.open_orders_admin.into(),
+        }
+    }
+}
+
+impl fmt::Display for AdminRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::CloseMarket => "close market admin",
+            Self::CollectFee => "collect fee admin",
+            Self::ConsumeEvents => "consume events admin",
+            Self::OpenOrders => "open orders admin",
+        })
+    }
+}
+
+/// Checks that `signer` is the `role` admin of `market`.
+pub fn check_admin(market: &MarketContext, role: AdminRole, signer: &Pubkey) -> Result<()> {
+    let Some(admin) = role.admin(&market.market) else {
+        anyhow::bail!("Market {} has no {role}", market.address);
+    };
+    anyhow::ensure!(
+        admin == *signer,
+        "{signer} is not the {role} {admin} of market {}",
+        market.address
+    );
+    Ok(())
+}
+
+/// Builds `SetMarketExpired`, expiring the market so that it only allows cancelling orders and
+/// settling funds.
+pub fn set_market_expired(market: &MarketContext, admin: &Pubkey) -> Result<Instruction> {
+    check_admin(market, AdminRole::CloseMarket, admin)?;
+    Ok(instructions::set_market_expired(
+        openbook_v2::accounts::SetMarketExpired {
+            close_market_admin: *admin,
+            market: market.address,
+        },
+    ))
+}
+
+/// Builds `PruneOrders`, removing up to `limit` orders of `open_orders_account` from the book of
+/// an expired market.
+pub fn prune_orders(
+    market: &MarketContext,
+    admin: &Pubkey,
+    open_orders_account: Pubkey,
+    limit: u8,
+) -> Result<Instruction> {
+    check_admin(market, AdminRole::CloseMarket, admin)?;
+    Ok(instructions::prune_orders(
+        openbook_v2::accounts::PruneOrders {
+            close_market_admin: *admin,
+            open_orders_account,
+            market: market.address,
+            bids: market.market.bids,
+            asks: market.market.asks,
+        },
+        limit,
+    ))
+}
+
+/// Builds `SettleFundsExpired`, settling the funds of `account` on an expired market to the
+/// associated token accounts of its owner, which must exist.
+pub fn settle_funds_expired(
+    market: &MarketContext,
+    admin: &Pubkey,
+    penalty_payer: Pubkey,
+    open_orders_account: Pubkey,
+    account: &OpenOrdersAccount,
+) -> Result<Instruction> {
+    check_admin(market, AdminRole::CloseMarket, admin)?;
+    anyhow::ensure!(
+        account.market == market.address,
+        "Open orders account {open_orders_account} is not on market {}",
+        market.address
+    );
+    let base_token_program = market.base_mint_info.token_program;
+    let quote_token_program = market.quote_mint_info.token_program;
+    anyhow::ensure!(
+        base_token_program == quote_token_program,
+        "Base and quote mints of market {} use different token programs",
+        market.address
+    );
+    Ok(instructions::settle_funds_expired(
+        openbook_v2::accounts::SettleFundsExpired {
+            close_market_admin: *admin,
+            owner: account.owner,
+            penalty_payer,
+            open_orders_account,
+            market: market.address,
+            market_authority: market.market.market_authority,
+            market_base_vault: market.market.market_base_vault,
+            market_quote_vault: market.market.market_quote_vault,
+            user_base_account: get_associated_token_address_with_program_id(
+                &account.owner,
+                &market.market.base_mint,
+                &base_token_program,
+            ),
+            user_quote_account: get_associated_token_address_with_program_id(
+                &account.owner,
+                &market.market.quote_mint,
+                &quote_token_program,
+            ),
+            referrer_account: None,
+            token_program: base_token_program,
+            system_program: System::id(),
+        },
+    ))
+}
+
+/// Builds `CloseMarket`, closing the market, its book sides and event heap, and returning their
+/// rent to `sol_destination`. The program only closes markets without orders, events or funds.
+pub fn close_market(
+    market: &MarketContext,
+    admin: &Pubkey,
+    sol_destination: Pubkey,
+) -> Result<Instruction> {
+    check_admin(market, AdminRole::CloseMarket, admin)?;
+    Ok(instructions::close_market(
+        openbook_v2::accounts::CloseMarket {
+            close_market_admin: *admin,
+            market: market.address,
+            bids: market.market.bids,
+            asks: market.market.asks,
+            event_heap: market.market.event_heap,
+            sol_destination,
+            token_program: market.quote_mint_info.token_program,
+        },
+    ))
+}
+
+/// Builds `SweepFees`, transferring the fees accrued by the market to `token_receiver_account`,
+/// a quote token account.
+pub fn sweep_fees(
+    market: &MarketContext,
+    admin: &Pubkey,
+    token_receiver_account: Pubkey,
+) -> Result<Instruction> {
+    check_admin(market, AdminRole::CollectFee, admin)?;
+    Ok(instructions::sweep_fees(openbook_v2::accounts::SweepFees {
+        collect_fee_admin: *admin,
+        market: market.address,
+        market_authority: market.market.market_authority,
+        market_quote_vault: market.market.market_quote_vault,
+        token_receiver_account,
+        token_program: market.quote_mint_info.token_program,
+    }))
+}
+
+/// Builds `ConsumeEvents` for a market with a consume events admin, see
+/// [`instructions::consume_events`].
+pub fn consume_events(
+    market: &MarketContext,
+    admin: &Pubkey,
+    open_orders_accounts: &[Pubkey],
+    limit: usize,
+) -> Result<Instruction> {
+    check_admin(market, AdminRole::ConsumeEvents, admin)?;
+    Ok(instructions::consume_events(
+        openbook_v2::accounts::ConsumeEvents {
+            consume_events_admin: Some(*admin),
+            market: market.address,
+            event_heap: market.market.event_heap,
+        },
+        open_orders_accounts,
+        limit,
+    ))
+}
+
+/// Builds `ConsumeGivenEvents` of the event heap `slots` for a market with a consume events
+/// admin, see [`instructions::consume_given_events`].
+pub fn consume_given_events(
+    market: &MarketContext,
+    admin: &Pubkey,
+    open_orders_accounts: &[Pubkey],
+    slots: Vec<usize>,
+) -> Result<Instruction> {
+    check_admin(market, AdminRole::ConsumeEvents, admin)?;
+    Ok(instructions::consume_given_events(
+        openbook_v2::accounts::ConsumeEvents {
+            consume_events_admin: Some(*admin),
+            market: market.address,
+            event_heap: market.market.event_heap,
+        },
+        open_orders_accounts,
+        slots,
+    ))
+}
+
+/// A legacy or versioned transaction, signed by several parties.
+pub trait Cosignable {
+    /// The signers whose signature is still missing.
+    fn missing_signers(&self) -> Vec<Pubkey>;
+
+    /// Adds the signature of `signer`, which must be a required signer.
+    fn cosign(&mut self, signer: &dyn Signer) -> Result<()>;
+}
+
+impl Cosignable for Transaction {
+    fn missing_signers(&self) -> Vec<Pubkey> {
+        let required = self.message.header.num_required_signatures as usize;
+        missing(&self.message.account_keys[..required], &self.signatures)
+    }
+
+    fn cosign(&mut self, signer: &dyn Signer) -> Result<()> {
+        let recent_blockhash = self.message.recent_blockhash;
+        self.try_partial_sign(&[signer], recent_blockhash)?;
+        Ok(())
+    }
+}
+
+impl Cosignable for VersionedTransaction {
+    fn missing_signers(&self) -> Vec<Pubkey> {
+        let required = self.message.header().num_required_signatures as usize;
+        missing(
+            &self.message.static_account_keys()[..required],
+            &self.signatures,
+        )
+    }
+
+    fn cosign(&mut self, signer: &dyn Signer) -> Result<()> {
+        let pubkey = signer.pubkey();
+        let required = self.message.header().num_required_signatures as usize;
+        let Some(position) = self.message.static_account_keys()[..required]
+            .iter()
+            .position(|key| *key == pubkey)
+        else {
+            anyhow::bail!("{pubkey} is not a signer of the transaction");
+        };
+        self.signatures[position] = signer.try_sign_message(&self.message.serialize())?;
+        Ok(())
+    }
+}
+
+fn missing(signers: &[Pubkey], signatures: &[Signature]) -> Vec<Pubkey> {
+    signers
+        .iter()
+        .zip(signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(key, _)| *key)
+        .collect()
+}
+
+/// Signs `trx`, legacy or versioned, built for a permissioned market by a client without the
+/// open orders admin co-signer, as the open orders admin of the market, e.g. in the service
+/// holding its key.
+///
+/// Fails if `signer` is not the open orders admin of the market, or if `trx` does not miss its
+/// signature.
+pub fn sign_open_orders(
+    market: &MarketContext,
+    trx: &mut impl Cosignable,
+    signer: &dyn Signer,
+) -> Result<()> {
+    let admin = signer.pubkey();
+    check_admin(market, AdminRole::OpenOrders, &admin)?;
+    anyhow::ensure!(
+        trx.missing_signers().contains(&admin),
+        "Transaction does not need the signature of the open orders admin {admin}"
+    );
+    trx.cosign(signer)
+}
+
+/// Derives the PDA of the stub oracle of `owner` for `mint`.
+pub fn stub_oracle(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"StubOracle".as_ref(), owner.as_ref(), mint.as_ref()],
+        &openbook_v2::id(),
+    )
+    .0
+}
+
+/// Builds `StubOracleCreate`, creating the stub oracle of `owner` for `mint` at `price`. Only
+/// `owner` can then set or close it.
+pub fn stub_oracle_create(payer: Pubkey, owner: Pubkey, mint: Pubkey, price: f64) -> Instruction {
+    instructions::stub_oracle_create(
+        openbook_v2::accounts::StubOracleCreate {
+            payer,
+            owner,
+            oracle: stub_oracle(&owner, &mint),
+            mint,
+            system_program: System::id(),
+        },
+        price,
+    )
+}
+
+/// Builds `StubOracleSet`, setting the price of the stub `oracle` owned by `owner`.
+pub fn stub_oracle_set(
+    address: Pubkey,
+    oracle: &StubOracle,
+    owner: &Pubkey,
+    price: f64,
+) -> Result<Instruction> {
+    check_stub_oracle_owner(address, oracle, owner)?;
+    Ok(instructions::stub_oracle_set(
+        openbook_v2::accounts::StubOracleSet {
+            owner: *owner,
+            oracle: address,
+        },
+        price,
+    ))
+}
+
+/// Builds `StubOracleClose`, closing the stub `oracle` owned by `owner` and returning its rent
+/// to `sol_destination`.
+pub fn stub_oracle_close(
+    address: Pubkey,
+    oracle: &StubOracle,
+    owner: &Pubkey,
+    sol_destination: Pubkey,
+) -> Result<Instruction> {
+    check_stub_oracle_owner(address, oracle, owner)?;
+    Ok(instructions::stub_oracle_close(
+        openbook_v2::accounts::StubOracleClose {
+            owner: *owner,
+            oracle: address,
+            sol_destination,
+            token_program: Token::id(),
+        },
+    ))
+}
+
+fn check_stub_oracle_owner(address: Pubkey, oracle: &StubOracle, owner: &Pubkey) -> Result<()> {
+    anyhow::ensure!(
+        oracle.owner == *owner,
+        "{owner} is not the owner {} of stub oracle {address}",
+        oracle.owner
+    );
+    Ok(())
+}
@@ -0,0 +1,963 @@
+//! Offline evaluation of quoting logic against data recorded by the `recorder`.
+//!
+//! The backtester replays the recorded book and fills of a market through a `SimulatedExchange`,
+//! which strategies drive with the same order methods as `OBClient`. Simulated orders follow the
+//! program's rules where the recording allows it:
+//!
+//! - sizes and prices are converted to lots exactly like `OBClient` does;
+//! - post-only orders crossing the book are dropped, and post-only-slide orders slide one tick
+//!   behind the best opposite price;
+//! - taking orders match the recorded book from the best price, skipping expired orders, and
+//!   never consume the same recorded liquidity twice;
+//! - crossing one's own resting orders applies the configured `SelfTradeBehavior`;
+//! - maker and taker fees are charged at the market's rates, with the taker fee of a bid
+//!   reserved from its quote lots before matching and rounded up once on the total matched.
+//!
+//! Strategies implement `BacktestStrategy` to use the exchange directly, or are wrapped in
+//! `IntentStrategy` to evaluate a `strategy::Strategy` unchanged.
+//!
+//! Resting orders are filled when a recorded trade or a recorded resting order crosses their
+//! price. Queue position is unknown, so orders are only filled at their own price when
+//! `BacktestConfig::fill_at_touch` is set.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use openbook_v2::state::{PlaceOrderType, SelfTradeBehavior, Side};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    book::{BookOrder, OrderDiff},
+    config::MarketSettings,
+    context::{MarketContext, OrderLots},
+    fees,
+    recorder::{Change, Record},
+    serde_util,
+    snapshot::{EventRecord, OpenOrderState},
+    strategy::{self, Fill, OrderIntent, Quote, Strategy, StrategyContext},
+};
+
+/// Settings of the simulated matching.
+#[derive(Clone, Copy, Debug)]
+pub struct BacktestConfig {
+    /// What happens when an order crosses one's own resting orders. Defaults to aborting the
+    /// order, like the orders placed by `OBClient`.
+    pub self_trade_behavior: SelfTradeBehavior,
+
+    /// Whether resting orders are filled by trades and orders at exactly their price, instead of
+    /// only by those crossing it.
+    pub fill_at_touch: bool,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            self_trade_behavior: SelfTradeBehavior::AbortTransaction,
+            fill_at_touch: false,
+        }
+    }
+}
+
+/// A simulated order resting on the book.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimOrder {
+    pub order_id: u128,
+    pub client_order_id: u64,
+    #[serde(with = "serde_util::side")]
+    pub side: Side,
+    pub price_lots: i64,
+
+    /// The remaining quantity, in base lots.
+    pub quantity: i64,
+
+    /// The unix timestamp the order expires at, or 0 if it never expires.
+    pub expiry_timestamp: u64,
+}
+
+/// A simulated fill of one of the strategy's orders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimFill {
+    pub slot: u64,
+    pub timestamp: u64,
+
+    /// The id of the resting order, or 0 for a taking order.
+    pub order_id: u128,
+    pub client_order_id: u64,
+    #[serde(with = "serde_util::side")]
+    pub side: Side,
+    pub price_lots: i64,
+
+    /// The filled quantity, in base lots.
+    pub quantity: i64,
+
+    /// Whether the order was resting on the book, rather than taking liquidity.
+    pub maker: bool,
+
+    /// The fee paid, in native quote units. Negative for a rebate.
+    pub fee: i64,
+}
+
+/// The result of a backtest.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub fills: Vec<SimFill>,
+    pub pnl: PnlReport,
+
+    /// The number of gaps in the recording, during which fills may have been missed.
+    pub gaps: usize,
+}
+
+/// The positions and profit of a backtest.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PnlReport {
+    /// The base position, in native units.
+    pub base_position: i64,
+
+    /// The quote position, in native units, net of fees.
+    pub quote_position: i64,
+
+    /// The fees paid, in native quote units, net of rebates.
+    pub fees: i64,
+
+    /// The traded volume, in native quote units.
+    pub volume: u64,
+
+    pub maker_fills: usize,
+    pub taker_fills: usize,
+
+    /// The mid price of the recorded book at the end of the backtest, in quote lots per base lot.
+    pub mark_price_lots: Option<i64>,
+
+    /// The quote position plus the base position valued at the mark price, in native quote units.
+    pub pnl_native: f64,
+
+    /// `pnl_native` in quote tokens.
+    pub pnl: f64,
+}
+
+/// Quoting logic evaluated by the `Backtester`.
+pub trait BacktestStrategy {
+    /// Called after every change of the recorded book.
+    fn on_book(&mut self, exchange: &mut SimulatedExchange) -> Result<()>;
+
+    /// Called for every fill of one of the strategy's orders.
+    fn on_fill(&mut self, _fill: &SimFill, _exchange: &mut SimulatedExchange) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Evaluates a `Strategy` by executing its intents on the `SimulatedExchange`.
+///
+/// Intents failing on the exchange, e.g. self-trades aborting, are skipped like failed
+/// transactions are by `StrategyRuntime`.
+pub struct IntentStrategy<S>(pub S);
+
+impl<S: Strategy> IntentStrategy<S> {
+    fn execute(exchange: &mut SimulatedExchange, intents: Vec<OrderIntent>) {
+        for intent in intents {
+            let result = match &intent {
+                OrderIntent::Place {
+                    side,
+                    limit_price,
+                    quote_size,
+                } => exchange
+                    .place_limit_order(*limit_price, *quote_size, *side)
+                    .map(|_| ()),
+                OrderIntent::PlaceLots {
+                    side,
+                    lots,
+                    order_type,
+                } => {
+                    let expiry_timestamp = exchange.timestamp + exchange.settings.ttl_secs();
+                    let client_order_id = exchange.next_client_order_id();
+                    exchange
+                        .place_order(
+                            *side,
+                            *lots,
+                            (*order_type).into(),
+                            client_order_id,
+                            expiry_timestamp,
+                        )
+                        .map(|_| ())
+                }
+                OrderIntent::Cancel { order_id } => exchange.cancel_limit_order(*order_id),
+                OrderIntent::CancelAll => exchange.cancel_all(),
+                OrderIntent::Replace { bids, asks } => {
+                    exchange.cancel_all_and_place_orders(bids, asks)
+                }
+            };
+            if let Err(err) = result {
+                tracing::debug!("Skipping intent {intent:?}: {err:#}");
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        exchange: &mut SimulatedExchange,
+        call: impl FnOnce(&mut S, &StrategyContext) -> Vec<OrderIntent>,
+    ) {
+        let open_orders: Vec<OpenOrderState> = exchange
+            .orders
+            .iter()
+            .map(|order| OpenOrderState {
+                order_id: order.order_id,
+                client_order_id: order.client_order_id,
+                side: order.side as u8,
+                locked_price: order.price_lots,
+            })
+            .collect();
+        let ctx = StrategyContext {
+            market: &exchange.context,
+            slot: exchange.slot,
+            timestamp: exchange.timestamp,
+            bids: &exchange.bids,
+            asks: &exchange.asks,
+            open_orders: &open_orders,
+            base_inventory: exchange.base_position,
+            oracle_price: None,
+        };
+        let intents = call(&mut self.0, &ctx);
+        Self::execute(exchange, intents);
+    }
+}
+
+impl<S: Strategy> BacktestStrategy for IntentStrategy<S> {
+    fn on_book(&mut self, exchange: &mut SimulatedExchange) -> Result<()> {
+        self.call(exchange, |strategy, ctx| strategy.on_book(ctx));
+        Ok(())
+    }
+
+    fn on_fill(&mut self, fill: &SimFill, exchange: &mut SimulatedExchange) -> Result<()> {
+        let fill = Fill {
+            slot: fill.slot,
+            client_order_id: fill.client_order_id,
+            side: fill.side,
+            price_lots: fill.price_lots,
+            quantity: fill.quantity,
+            maker: fill.maker,
+        };
+        self.call(exchange, |strategy, ctx| strategy.on_fill(ctx, &fill));
+        Ok(())
+    }
+}
+
+/// A simulated market, combining the recorded book with the strategy's orders.
+pub struct SimulatedExchange {
+    pub context: MarketContext,
+    pub settings: MarketSettings,
+    config: BacktestConfig,
+    slot: u64,
+    timestamp: u64,
+    seq_num: u64,
+
+    /// Client order id of the last order placed without an explicit one.
+    client_order_id: u64,
+    bids: Vec<BookOrder>,
+    asks: Vec<BookOrder>,
+
+    /// Base lots of recorded orders already taken by simulated orders, by order id.
+    consumed: HashMap<u128, i64>,
+    orders: Vec<SimOrder>,
+    fills: Vec<SimFill>,
+    pending_fills: Vec<SimFill>,
+    base_position: i64,
+    quote_position: i64,
+    fees: i64,
+    volume: u64,
+}
+
+/// A step of matching a taking order.
+enum Match {
+    Fill {
+        order_id: u128,
+        price_lots: i64,
+        quantity: i64,
+    },
+    CancelOwn {
+        order_id: u128,
+    },
+    DecrementOwn {
+        order_id: u128,
+        quantity: i64,
+    },
+}
+
+impl SimulatedExchange {
+    pub fn new(context: MarketContext, settings: MarketSettings, config: BacktestConfig) -> Self {
+        Self {
+            context,
+            settings,
+            config,
+            slot: 0,
+            timestamp: 0,
+            seq_num: 0,
+            client_order_id: 0,
+            bids: vec![],
+            asks: vec![],
+            consumed: HashMap::new(),
+            orders: vec![],
+            fills: vec![],
+            pending_fills: vec![],
+            base_position: 0,
+            quote_position: 0,
+            fees: 0,
+            volume: 0,
+        }
+    }
+
+    /// Places a limit order of `quote_size` at `limit_price`, with the order type and TTL of the
+    /// market settings, like `OBClient::place_limit_order`.
+    ///
+    /// Returns the order id if some of the order rests on the book.
+    pub fn place_limit_order(
+        &mut self,
+        limit_price: f64,
+        quote_size: u64,
+        side: Side,
+    ) -> Result<Option<u128>> {
+        if let Some(max_size) = self.settings.max_size {
+            anyhow::ensure!(
+                quote_size <= max_size,
+                "Order size {quote_size} exceeds the maximum size {max_size}"
+            );
+        }
+        let lots = self.context.order_lots(side, limit_price, quote_size);
+        let expiry_timestamp = self.timestamp + self.settings.ttl_secs();
+        let client_order_id = self.next_client_order_id();
+        self.place_order(
+            side,
+            lots,
+            self.settings.order_type(),
+            client_order_id,
+            expiry_timestamp,
+        )
+    }
+
+    /// A new client order id, counting up from 1 so that runs over the same recording are
+    /// reproducible.
+    pub fn next_client_order_id(&mut self) -> u64 {
+        self.client_order_id += 1;
+        self.client_order_id
+    }
+
+    /// Places an order with explicit lots and order type.
+    ///
+    /// Fails without changes if the order would abort on-chain: a fill-or-kill order that
+    /// cannot be filled entirely, or a self-trade with `SelfTradeBehavior::AbortTransaction`.
+    pub fn place_order(
+        &mut self,
+        side: Side,
+        lots: OrderLots,
+        order_type: PlaceOrderType,
+        client_order_id: u64,
+        expiry_timestamp: u64,
+    ) -> Result<Option<u128>> {
+        let mut price_lots = lots.price_lots;
+        anyhow::ensure!(price_lots > 0, "Order price must be positive");
+        let best_opposite = self.best_price(side.invert_side());
+        let crosses = best_opposite.is_some_and(|best| crosses(side, price_lots, best));
+
+        let max_base_lots = lots.max_base_lots as i64;
+        // Like the program, bids reserve the taker fee out of their quote lots before matching.
+        let max_quote_lots = match side {
+            Side::Bid => {
+                fees::subtract_taker_fees(&self.context.market, lots.max_quote_lots as i64)
+            }
+            Side::Ask => lots.max_quote_lots as i64,
+        };
+        let (mut remaining, spent_quote_lots) = match order_type {
+            PlaceOrderType::PostOnly if crosses => return Ok(None),
+            PlaceOrderType::PostOnly => (max_base_lots, 0),
+            PlaceOrderType::PostOnlySlide => {
+                if crosses {
+                    let best = best_opposite.expect("crossing an order");
+                    price_lots = match side {
+                        Side::Bid => best - 1,
+                        Side::Ask => best + 1,
+                    };
+                    if price_lots <= 0 {
+                        return Ok(None);
+                    }
+                }
+                (max_base_lots, 0)
+            }
+            PlaceOrderType::Limit
+            | PlaceOrderType::ImmediateOrCancel
+            | PlaceOrderType::Market
+            | PlaceOrderType::FillOrKill => self.take(
+                side,
+                price_lots,
+                max_base_lots,
+                max_quote_lots,
+                client_order_id,
+                matches!(order_type, PlaceOrderType::FillOrKill),
+            )?,
+        };
+
+        let rests = matches!(
+            order_type,
+            PlaceOrderType::Limit | PlaceOrderType::PostOnly | PlaceOrderType::PostOnlySlide
+        );
+        if side == Side::Bid {
+            let affordable = (max_quote_lots - spent_quote_lots) / price_lots;
+            remaining = remaining.min(affordable);
+        }
+        if !rests || remaining <= 0 {
+            return Ok(None);
+        }
+
+        self.seq_num += 1;
+        let seq_num = match side {
+            Side::Bid => !self.seq_num,
+            Side::Ask => self.seq_num,
+        };
+        let order_id = ((price_lots as u128) << 64) | seq_num as u128;
+        self.orders.push(SimOrder {
+            order_id,
+            client_order_id,
+            side,
+            price_lots,
+            quantity: remaining,
+            expiry_timestamp,
+        });
+        Ok(Some(order_id))
+    }
+
+    /// Cancels the resting order `order_id`, like `OBClient::cancel_limit_order`.
+    pub fn cancel_limit_order(&mut self, order_id: u128) -> Result<()> {
+        let position = self
+            .orders
+            .iter()
+            .position(|order| order.order_id == order_id)
+            .ok_or_else(|| anyhow!("Order {order_id} not found"))?;
+        self.orders.remove(position);
+        Ok(())
+    }
+
+    /// Cancels all resting orders, like `OBClient::cancel_all`.
+    pub fn cancel_all(&mut self) -> Result<()> {
+        self.orders.clear();
+        Ok(())
+    }
+
+    /// Cancels all resting orders and places `bids` and `asks`, like
+    /// `OBClient::cancel_all_and_place_orders`.
+    ///
+    /// Orders get the client order ids of `strategy::replace_orders`, and orders failing are
+    /// skipped, like the program does with post-only orders crossing the book.
+    pub fn cancel_all_and_place_orders(&mut self, bids: &[Quote], asks: &[Quote]) -> Result<()> {
+        if let Some(max_size) = self.settings.max_size {
+            if let Some(quote) = bids.iter().chain(asks).find(|q| q.quote_size > max_size) {
+                bail!(
+                    "Order size {} exceeds the maximum size {max_size}",
+                    quote.quote_size
+                );
+            }
+        }
+        self.orders.clear();
+        let expiry_timestamp = self.timestamp + self.settings.ttl_secs();
+        for (client_order_id, side, quote) in strategy::replace_orders(bids, asks) {
+            let lots =
+                self.context
+                    .order_lots_from_price_lots(side, quote.price_lots, quote.quote_size);
+            let placed = self.place_order(
+                side,
+                lots,
+                self.settings.order_type(),
+                client_order_id,
+                expiry_timestamp,
+            );
+            if let Err(err) = placed {
+                tracing::debug!("Skipping quote {quote:?}: {err:#}");
+            }
+        }
+        Ok(())
+    }
+
+    /// The strategy's resting orders.
+    pub fn open_orders(&self) -> &[SimOrder] {
+        &self.orders
+    }
+
+    /// The recorded bids, from best to worst price.
+    pub fn bids(&self) -> &[BookOrder] {
+        &self.bids
+    }
+
+    /// The recorded asks, from best to worst price.
+    pub fn asks(&self) -> &[BookOrder] {
+        &self.asks
+    }
+
+    /// The best recorded price of `side` with liquidity left, in quote lots per base lot.
+    pub fn best_recorded_price(&self, side: Side) -> Option<i64> {
+        self.book_side(side)
+            .iter()
+            .find(|order| self.available(order) > 0)
+            .map(|order| order.price_lots)
+    }
+
+    /// The mid price of the recorded book, in quote lots per base lot.
+    pub fn mid_price_lots(&self) -> Option<i64> {
+        match (
+            self.best_recorded_price(Side::Bid),
+            self.best_recorded_price(Side::Ask),
+        ) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / 2),
+            (Some(price), None) | (None, Some(price)) => Some(price),
+            (None, None) => None,
+        }
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.slot
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// All fills so far, in order.
+    pub fn fills(&self) -> &[SimFill] {
+        &self.fills
+    }
+
+    /// The positions and profit so far, marked at the mid price of the recorded book.
+    pub fn pnl(&self) -> PnlReport {
+        let market = &self.context.market;
+        let mark_price_lots = self.mid_price_lots();
+        let base_value = mark_price_lots.map_or(0.0, |price_lots| {
+            self.base_position as f64 * price_lots as f64 * market.quote_lot_size as f64
+                / market.base_lot_size as f64
+        });
+        let pnl_native = self.quote_position as f64 + base_value;
+        PnlReport {
+            base_position: self.base_position,
+            quote_position: self.quote_position,
+            fees: self.fees,
+            volume: self.volume,
+            maker_fills: self.fills.iter().filter(|fill| fill.maker).count(),
+            taker_fills: self.fills.iter().filter(|fill| !fill.maker).count(),
+            mark_price_lots,
+            pnl_native,
+            pnl: pnl_native / 10f64.powi(market.quote_decimals as i32),
+        }
+    }
+
+    /// Moves the clock to `slot` and `timestamp`, expiring resting orders.
+    fn advance(&mut self, slot: u64, timestamp: u64) {
+        self.slot = slot;
+        self.timestamp = timestamp;
+        self.orders
+            .retain(|order| order.expiry_timestamp == 0 || order.expiry_timestamp > timestamp);
+    }
+
+    fn set_book(&mut self, bids: Vec<BookOrder>, asks: Vec<BookOrder>) {
+        self.bids = bids;
+        self.asks = asks;
+        self.consumed.clear();
+        sort_book_side(&mut self.bids, Side::Bid);
+        sort_book_side(&mut self.asks, Side::Ask);
+    }
+
+    fn apply_diff(&mut self, side: Side, diff: &OrderDiff) {
+        let orders = match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+        let removed = diff.removed.iter().chain(&diff.changed);
+        for order in removed {
+            orders.retain(|o| o.order_id != order.order_id);
+            self.consumed.remove(&order.order_id);
+        }
+        orders.extend(diff.added.iter().chain(&diff.changed));
+        sort_book_side(orders, side);
+    }
+
+    fn book_side(&self, side: Side) -> &[BookOrder] {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    fn available(&self, order: &BookOrder) -> i64 {
+        if order.is_expired(self.timestamp) {
+            return 0;
+        }
+        order.quantity - self.consumed.get(&order.order_id).copied().unwrap_or(0)
+    }
+
+    /// The best price of `side` across the recorded book and the strategy's orders.
+    fn best_price(&self, side: Side) -> Option<i64> {
+        let own = self
+            .orders
+            .iter()
+            .filter(|order| order.side == side)
+            .map(|order| order.price_lots);
+        let prices = self.best_recorded_price(side).into_iter().chain(own);
+        match side {
+            Side::Bid => prices.max(),
+            Side::Ask => prices.min(),
+        }
+    }
+
+    /// Matches a taking order against the recorded book and the strategy's resting orders.
+    ///
+    /// Bids spend up to `max_quote_lots`, net of the taker fee. Returns the base lots left after
+    /// filling and decrementing, and the quote lots spent.
+    #[allow(clippy::too_many_arguments)]
+    fn take(
+        &mut self,
+        side: Side,
+        limit_price_lots: i64,
+        max_base_lots: i64,
+        max_quote_lots: i64,
+        client_order_id: u64,
+        fill_or_kill: bool,
+    ) -> Result<(i64, i64)> {
+        // Recorded orders are ahead of the strategy's orders at the same price.
+        let opposite = side.invert_side();
+        let mut makers: Vec<(i64, bool, u128, i64)> = self
+            .book_side(opposite)
+            .iter()
+            .map(|order| {
+                (
+                    order.price_lots,
+                    false,
+                    order.order_id,
+                    self.available(order),
+                )
+            })
+            .chain(
+                self.orders
+                    .iter()
+                    .filter(|order| order.side == opposite)
+                    .map(|order| (order.price_lots, true, order.order_id, order.quantity)),
+            )
+            .filter(|(price_lots, _, _, quantity)| {
+                *quantity > 0 && crosses(side, limit_price_lots, *price_lots)
+            })
+            .collect();
+        makers.sort_by_key(|(price_lots, own, _, _)| match side {
+            Side::Bid => (*price_lots, *own),
+            Side::Ask => (-*price_lots, *own),
+        });
+
+        let mut remaining = max_base_lots;
+        let mut spent_quote_lots = 0;
+        let mut matches = vec![];
+        for (price_lots, own, order_id, quantity) in makers {
+            if remaining == 0 {
+                break;
+            }
+            if own {
+                match self.config.self_trade_behavior {
+                    SelfTradeBehavior::AbortTransaction => bail!("Order would self-trade"),
+                    SelfTradeBehavior::CancelProvide => {
+                        matches.push(Match::CancelOwn { order_id });
+                    }
+                    SelfTradeBehavior::DecrementTake => {
+                        let decrement = remaining.min(quantity);
+                        remaining -= decrement;
+                        matches.push(Match::DecrementOwn {
+                            order_id,
+                            quantity: decrement,
+                        });
+                    }
+                }
+                continue;
+            }
+
+            let mut quantity = remaining.min(quantity);
+            if side == Side::Bid {
+                let affordable = (max_quote_lots - spent_quote_lots) / price_lots;
+                quantity = quantity.min(affordable);
+            }
+            if quantity <= 0 {
+                break;
+            }
+            remaining -= quantity;
+            spent_quote_lots += quantity * price_lots;
+            matches.push(Match::Fill {
+                order_id,
+                price_lots,
+                quantity,
+            });
+        }
+
+        let filled: i64 = matches
+            .iter()
+            .map(|m| match m {
+                Match::Fill { quantity, .. } => *quantity,
+                _ => 0,
+            })
+            .sum();
+        if fill_or_kill && filled < max_base_lots {
+            bail!("Fill-or-kill order cannot be filled entirely");
+        }
+
+        // The taker fee is charged once on the total quote matched, so each fill is charged the
+        // increase of the fee of the quote matched so far.
+        let mut taken_quote_native = 0;
+        let mut charged_fee = 0;
+        for m in matches {
+            match m {
+                Match::Fill {
+                    order_id,
+                    price_lots,
+                    quantity,
+                } => {
+                    *self.consumed.entry(order_id).or_default() += quantity;
+                    taken_quote_native +=
+                        quantity * price_lots * self.context.market.quote_lot_size;
+                    let fee = fees::taker_fee_ceil(&self.context.market, taken_quote_native as u64)
+                        as i64
+                        - charged_fee;
+                    charged_fee += fee;
+                    self.record_fill(0, client_order_id, side, price_lots, quantity, fee);
+                }
+                Match::CancelOwn { order_id } => {
+                    self.orders.retain(|order| order.order_id != order_id);
+                }
+                Match::DecrementOwn { order_id, quantity } => {
+                    for order in self.orders.iter_mut().filter(|o| o.order_id == order_id) {
+                        order.quantity -= quantity;
+                    }
+                    self.orders.retain(|order| order.quantity > 0);
+                }
+            }
+        }
+        Ok((remaining, spent_quote_lots))
+    }
+
+    /// Fills resting orders crossed by recorded resting orders on the other side.
+    fn fill_crossed(&mut self) {
+        for side in [Side::Bid, Side::Ask] {
+            let opposite: Vec<(u128, i64)> = self
+                .book_side(side.invert_side())
+                .iter()
+                .map(|order| (order.order_id, order.price_lots))
+                .collect();
+            for (order_id, price_lots) in opposite {
+                let available = self
+                    .book_side(side.invert_side())
+                    .iter()
+                    .find(|order| order.order_id == order_id)
+                    .map_or(0, |order| self.available(order));
+                let filled = self.fill_resting(side, price_lots, available);
+                if filled > 0 {
+                    *self.consumed.entry(order_id).or_default() += filled;
+                }
+            }
+        }
+    }
+
+    /// Fills resting orders crossed by a recorded trade.
+    fn fill_from_trade(&mut self, taker_side: Side, price_lots: i64, quantity: i64) {
+        self.fill_resting(taker_side.invert_side(), price_lots, quantity);
+    }
+
+    /// Fills up to `quantity` base lots of the resting orders of `side` crossed by `price_lots`,
+    /// from the best price. Returns the filled base lots.
+    fn fill_resting(&mut self, side: Side, price_lots: i64, mut quantity: i64) -> i64 {
+        let fill_at_touch = self.config.fill_at_touch;
+        let mut crossed: Vec<SimOrder> = self
+            .orders
+            .iter()
+            .filter(|order| {
+                let through = match side {
+                    Side::Bid => order.price_lots > price_lots,
+                    Side::Ask => order.price_lots < price_lots,
+                };
+                let touch = fill_at_touch && order.price_lots == price_lots;
+                order.side == side && (through || touch)
+            })
+            .copied()
+            .collect();
+        crossed.sort_by_key(|order| match side {
+            Side::Bid => -order.price_lots,
+            Side::Ask => order.price_lots,
+        });
+
+        let mut filled = 0;
+        for order in crossed {
+            if quantity == 0 {
+                break;
+            }
+            let fill = quantity.min(order.quantity);
+            quantity -= fill;
+            filled += fill;
+            for resting in self
+                .orders
+                .iter_mut()
+                .filter(|o| o.order_id == order.order_id)
+            {
+                resting.quantity -= fill;
+            }
+            let quote_native = fill * order.price_lots * self.context.market.quote_lot_size;
+            let fee = fees::fill_fee(&self.context.market, quote_native as u64, true);
+            self.record_fill(
+                order.order_id,
+                order.client_order_id,
+                side,
+                order.price_lots,
+                fill,
+                fee,
+            );
+        }
+        self.orders.retain(|order| order.quantity > 0);
+        filled
+    }
+
+    /// Records a fill of the resting order `order_id`, or of a taking order if 0.
+    fn record_fill(
+        &mut self,
+        order_id: u128,
+        client_order_id: u64,
+        side: Side,
+        price_lots: i64,
+        quantity: i64,
+        fee: i64,
+    ) {
+        let market = &self.context.market;
+        let base_native = quantity * market.base_lot_size;
+        let quote_native = quantity * price_lots * market.quote_lot_size;
+        match side {
+            Side::Bid => {
+                self.base_position += base_native;
+                self.quote_position -= quote_native + fee;
+            }
+            Side::Ask => {
+                self.base_position -= base_native;
+                self.quote_position += quote_native - fee;
+            }
+        }
+        self.fees += fee;
+        self.volume += quote_native as u64;
+
+        let fill = SimFill {
+            slot: self.slot,
+            timestamp: self.timestamp,
+            order_id,
+            client_order_id,
+            side,
+            price_lots,
+            quantity,
+            maker: order_id != 0,
+            fee,
+        };
+        self.fills.push(fill);
+        self.pending_fills.push(fill);
+    }
+}
+
+/// Replays recorded market data through a `SimulatedExchange`.
+pub struct Backtester {
+    pub exchange: SimulatedExchange,
+    gaps: usize,
+}
+
+impl Backtester {
+    pub fn new(context: MarketContext, settings: MarketSettings, config: BacktestConfig) -> Self {
+        Self {
+            exchange: SimulatedExchange::new(context, settings, config),
+            gaps: 0,
+        }
+    }
+
+    /// Replays the `records` of the exchange's market, in order, driving `strategy`.
+    ///
+    /// Records of other markets are skipped.
+    pub fn run(
+        mut self,
+        records: impl IntoIterator<Item = Record>,
+        strategy: &mut impl BacktestStrategy,
+    ) -> Result<BacktestReport> {
+        for record in records {
+            self.step(record, strategy)?;
+        }
+        Ok(BacktestReport {
+            fills: self.exchange.fills.clone(),
+            pnl: self.exchange.pnl(),
+            gaps: self.gaps,
+        })
+    }
+
+    /// Replays one record.
+    pub fn step(&mut self, record: Record, strategy: &mut impl BacktestStrategy) -> Result<()> {
+        let exchange = &mut self.exchange;
+        if record.market != exchange.context.address {
+            return Ok(());
+        }
+        exchange.advance(record.slot, record.wall_clock_ms / 1000);
+
+        match record.change {
+            Change::Book { bids, asks } => {
+                exchange.set_book(bids, asks);
+                self.on_book(strategy)?;
+            }
+            Change::BookUpdate { bids, asks } => {
+                exchange.apply_diff(Side::Bid, &bids);
+                exchange.apply_diff(Side::Ask, &asks);
+                self.on_book(strategy)?;
+            }
+            Change::Events { events } => {
+                for event in events {
+                    if let EventRecord::Fill {
+                        taker_side,
+                        price,
+                        quantity,
+                        ..
+                    } = event
+                    {
+                        let taker_side = if taker_side == 0 {
+                            Side::Bid
+                        } else {
+                            Side::Ask
+                        };
+                        exchange.fill_from_trade(taker_side, price, quantity);
+                    }
+                }
+                self.deliver_fills(strategy)?;
+            }
+            Change::Gap { .. } => self.gaps += 1,
+        }
+        Ok(())
+    }
+
+    fn on_book(&mut self, strategy: &mut impl BacktestStrategy) -> Result<()> {
+        self.exchange.fill_crossed();
+        self.deliver_fills(strategy)?;
+        strategy.on_book(&mut self.exchange)?;
+        self.deliver_fills(strategy)
+    }
+
+    fn deliver_fills(&mut self, strategy: &mut impl BacktestStrategy) -> Result<()> {
+        while !self.exchange.pending_fills.is_empty() {
+            for fill in std::mem::take(&mut self.exchange.pending_fills) {
+                strategy.on_fill(&fill, &mut self.exchange)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether an order of `side` at `price_lots` matches an opposite order at `other_price_lots`.
+fn crosses(side: Side, price_lots: i64, other_price_lots: i64) -> bool {
+    match side {
+        Side::Bid => price_lots >= other_price_lots,
+        Side::Ask => price_lots <= other_price_lots,
+    }
+}
+
+/// Sorts `orders` from best to worst price, earliest first within a price.
+fn sort_book_side(orders: &mut [BookOrder], side: Side) {
+    match side {
+        Side::Bid => {
+            orders.sort_by(|a, b| (b.price_lots, b.order_id).cmp(&(a.price_lots, a.order_id)))
+        }
+        Side::Ask => orders.sort_by_key(|order| (order.price_lots, order.order_id)),
+    }
+}
@@ -0,0 +1,141 @@
+//! Decoding of the order book sides of an OpenBook V2 market.
+
+use openbook_v2::state::BookSide;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::serde_util;
+
+/// An order resting on a book side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookOrder {
+    /// The order id, combining the price data and the order sequence number.
+    pub order_id: u128,
+
+    /// The open orders account owning the order.
+    #[serde(with = "serde_util::pubkey")]
+    pub owner: Pubkey,
+
+    /// The slot of the order in the owner's open orders account.
+    pub owner_slot: u8,
+
+    pub client_order_id: u64,
+
+    /// The effective price of the order, in quote lots per base lot.
+    pub price_lots: i64,
+
+    /// The remaining quantity of the order, in base lots.
+    pub quantity: i64,
+
+    /// The unix timestamp the order was placed at.
+    pub timestamp: u64,
+
+    /// The number of seconds the order is valid for after `timestamp`, or 0 if it never expires.
+    pub time_in_force: u16,
+}
+
+impl BookOrder {
+    /// Whether the order expired at `now_ts`, like `LeafNode::is_expired`.
+    pub fn is_expired(&self, now_ts: u64) -> bool {
+        self.time_in_force > 0 && now_ts >= self.timestamp + self.time_in_force as u64
+    }
+}
+
+/// A price level of a book side, aggregating the orders at the same price.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookLevel {
+    /// The price of the level, in quote lots per base lot.
+    pub price_lots: i64,
+
+    /// The total quantity of the level, in base lots.
+    pub quantity: i64,
+
+    /// The number of orders at this level.
+    pub orders: usize,
+}
+
+/// Lists the valid orders of `book_side` from best to worst price.
+///
+/// Orders expired at `now_ts` are skipped, as the program does when matching. Pegged orders are
+/// only listed when `oracle_price_lots` is known.
+pub fn book_side_orders(
+    book_side: &BookSide,
+    now_ts: u64,
+    oracle_price_lots: Option<i64>,
+) -> Vec<BookOrder> {
+    book_side
+        .iter_valid(now_ts, oracle_price_lots)
+        .map(|item| BookOrder {
+            order_id: item.node.key,
+            owner: item.node.owner,
+            owner_slot: item.node.owner_slot,
+            client_order_id: item.node.client_order_id,
+            price_lots: item.price_lots,
+            quantity: item.node.quantity,
+            timestamp: item.node.timestamp,
+            time_in_force: item.node.time_in_force,
+        })
+        .collect()
+}
+
+/// Aggregates `orders`, sorted from best to worst price, into at most `depth` price levels.
+pub fn levels(orders: &[BookOrder], depth: usize) -> Vec<BookLevel> {
+    let mut levels: Vec<BookLevel> = vec![];
+    for order in orders {
+        match levels.last_mut() {
+            Some(level) if level.price_lots == order.price_lots => {
+                level.quantity += order.quantity;
+                level.orders += 1;
+            }
+            _ => {
+                if levels.len() == depth {
+                    break;
+                }
+                levels.push(BookLevel {
+                    price_lots: order.price_lots,
+                    quantity: order.quantity,
+                    orders: 1,
+                });
+            }
+        }
+    }
+    levels
+}
+
+/// The differences between two states of a book side.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderDiff {
+    /// Orders only in the new state.
+    pub added: Vec<BookOrder>,
+
+    /// Orders only in the old state.
+    pub removed: Vec<BookOrder>,
+
+    /// The new state of orders whose quantity or price changed, e.g. partially filled orders.
+    pub changed: Vec<BookOrder>,
+}
+
+impl OrderDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compares the orders of two states of a book side by order id.
+pub fn diff_orders(old: &[BookOrder], new: &[BookOrder]) -> OrderDiff {
+    let find = |orders: &[BookOrder], id: u128| orders.iter().find(|o| o.order_id == id).copied();
+    let mut diff = OrderDiff::default();
+    for order in new {
+        match find(old, order.order_id) {
+            None => diff.added.push(*order),
+            Some(previous) if previous != *order => diff.changed.push(*order),
+            Some(_) => {}
+        }
+    }
+    diff.removed = old
+        .iter()
+        .filter(|order| find(new, order.order_id).is_none())
+        .copied()
+        .collect();
+    diff
+}
@@ -0,0 +1,264 @@
+//! Configuration file with named profiles for `OBClient` and the command-line tool.
+//!
+//! The configuration is read from TOML, or JSON when the file has a `.json` extension:
+//!
+//! ```toml
+//! default_profile = "devnet"
+//!
+//! [profiles.devnet]
+//! rpc_url = "https://api.devnet.solana.com"
+//! keypair_path = "~/.config/solana/devnet.json"
+//! commitment = "confirmed"
+//! market = "gQN1TNHiqj5x82ZQd7JZ8rm8WD4xwWtXxd4onReWZNK"
+//!
+//! [profiles.devnet.markets.gQN1TNHiqj5x82ZQd7JZ8rm8WD4xwWtXxd4onReWZNK]
+//! order_type = "post_only"
+//! ttl_secs = 3600
+//! max_size = 1000
+//!
+//! [profiles.devnet.markets.gQN1TNHiqj5x82ZQd7JZ8rm8WD4xwWtXxd4onReWZNK.risk]
+//! max_order_notional = 5000.0
+//! ```
+//!
+//! The `devnet`, `mainnet` and `localnet` profiles are built in, and only need to be declared to
+//! override their settings. Every resolved profile can be overridden with the `OPENBOOK_RPC_URL`,
+//! `OPENBOOK_KEYPAIR`, `OPENBOOK_COMMITMENT`, `OPENBOOK_MARKET`, `OPENBOOK_OPEN_ORDERS_ACCOUNT`
+//! and `OPENBOOK_REFERRER` environment variables.
+
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use anyhow::{anyhow, bail, Context, Result};
+use openbook_v2::state::PlaceOrderType;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    pubkey::Pubkey,
+};
+
+use crate::risk::RiskLimits;
+
+/// Environment variable holding the path of the configuration file.
+pub const CONFIG_PATH_ENV: &str = "OPENBOOK_CONFIG";
+
+/// Environment variable selecting the profile, instead of `default_profile`.
+pub const PROFILE_ENV: &str = "OPENBOOK_PROFILE";
+
+/// The contents of a configuration file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// The profile used when none is requested.
+    pub default_profile: Option<String>,
+
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// A named set of connection and trading settings.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Profile {
+    pub rpc_url: Option<String>,
+
+    /// Path of the owner's keypair. A leading `~` is expanded to the home directory.
+    pub keypair_path: Option<String>,
+
+    pub commitment: Option<CommitmentLevel>,
+
+    /// The market traded by default.
+    pub market: Option<String>,
+
+    pub open_orders_account: Option<String>,
+
+    /// Wallet receiving the referrer rebates of the owner's taker orders when settling.
+    pub referrer: Option<String>,
+
+    /// Trading settings, keyed by market address.
+    #[serde(default)]
+    pub markets: HashMap<String, MarketSettings>,
+}
+
+/// Per-market trading settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarketSettings {
+    /// Order type of limit orders. Defaults to post-only.
+    pub order_type: Option<OrderType>,
+
+    /// Number of seconds limit orders stay valid for. Defaults to a day.
+    pub ttl_secs: Option<u64>,
+
+    /// Maximum size of an order, in quote tokens.
+    pub max_size: Option<u64>,
+
+    /// Pre-trade risk limits of the orders, unchecked if unset.
+    pub risk: Option<RiskLimits>,
+}
+
+impl MarketSettings {
+    pub const DEFAULT_TTL_SECS: u64 = 86_400;
+
+    pub fn order_type(&self) -> PlaceOrderType {
+        self.order_type.unwrap_or(OrderType::PostOnly).into()
+    }
+
+    pub fn ttl_secs(&self) -> u64 {
+        self.ttl_secs.unwrap_or(Self::DEFAULT_TTL_SECS)
+    }
+}
+
+/// Serializable mirror of `PlaceOrderType`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderType {
+    Limit,
+    ImmediateOrCancel,
+    PostOnly,
+    Market,
+    PostOnlySlide,
+    FillOrKill,
+}
+
+impl From<OrderType> for PlaceOrderType {
+    fn from(order_type: OrderType) -> Self {
+        match order_type {
+            OrderType::Limit => PlaceOrderType::Limit,
+            OrderType::ImmediateOrCancel => PlaceOrderType::ImmediateOrCancel,
+            OrderType::PostOnly => PlaceOrderType::PostOnly,
+            OrderType::Market => PlaceOrderType::Market,
+            OrderType::PostOnlySlide => PlaceOrderType::PostOnlySlide,
+            OrderType::FillOrKill => PlaceOrderType::FillOrKill,
+        }
+    }
+}
+
+/// Fully resolved settings used to construct an `OBClient`.
+#[derive(Clone, Debug)]
+pub struct ClientConfig {
+    pub rpc_url: String,
+    pub keypair_path: String,
+    pub commitment: CommitmentConfig,
+    pub market: Option<Pubkey>,
+    pub open_orders_account: Option<Pubkey>,
+    pub referrer: Option<Pubkey>,
+
+    /// Trading settings of the profile, keyed by market address.
+    pub markets: HashMap<String, MarketSettings>,
+}
+
+impl ClientConfig {
+    /// The settings of `market`, default if the profile has none for it.
+    pub fn market_settings(&self) -> MarketSettings {
+        self.market
+            .and_then(|market| self.markets.get(&market.to_string()).copied())
+            .unwrap_or_default()
+    }
+}
+
+impl Config {
+    /// Reads the configuration from `path`, as JSON if it has a `.json` extension, TOML otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        let config = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&contents)?
+        } else {
+            toml::from_str(&contents)?
+        };
+        Ok(config)
+    }
+
+    /// Reads the configuration from `$OPENBOOK_CONFIG` or `~/.config/openbook/config.toml`,
+    /// falling back to the built-in profiles if the file does not exist.
+    pub fn load_default() -> Result<Self> {
+        let path = match std::env::var(CONFIG_PATH_ENV) {
+            Ok(path) => path,
+            Err(_) => expand_home("~/.config/openbook/config.toml"),
+        };
+        if Path::new(&path).exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Resolves the profile `name`, or `$OPENBOOK_PROFILE`, `default_profile` or `devnet`, with
+    /// environment variable overrides applied.
+    pub fn resolve(&self, name: Option<&str>) -> Result<ClientConfig> {
+        let env_name = std::env::var(PROFILE_ENV).ok();
+        let name = name
+            .or(env_name.as_deref())
+            .or(self.default_profile.as_deref())
+            .unwrap_or("devnet");
+
+        let builtin = builtin_profile(name);
+        let profile = match (self.profiles.get(name), &builtin) {
+            (Some(profile), _) => profile,
+            (None, Some(profile)) => profile,
+            (None, None) => bail!("Unknown profile {name}"),
+        };
+        let fallback = builtin.clone().unwrap_or_default();
+
+        let rpc_url = env_or("OPENBOOK_RPC_URL", profile.rpc_url.as_ref())
+            .or(fallback.rpc_url)
+            .ok_or_else(|| anyhow!("Profile {name} has no rpc_url"))?;
+        let keypair_path = env_or("OPENBOOK_KEYPAIR", profile.keypair_path.as_ref())
+            .unwrap_or_else(|| "~/.config/solana/id.json".to_string());
+        let commitment = match std::env::var("OPENBOOK_COMMITMENT") {
+            Ok(level) => CommitmentConfig::from_str(&level)
+                .map_err(|err| anyhow!("Invalid OPENBOOK_COMMITMENT {level}: {err}"))?,
+            Err(_) => CommitmentConfig {
+                commitment: profile.commitment.unwrap_or(CommitmentLevel::Confirmed),
+            },
+        };
+        let market = env_or("OPENBOOK_MARKET", profile.market.as_ref())
+            .map(|market| parse_pubkey("market", &market))
+            .transpose()?;
+        let open_orders_account = env_or(
+            "OPENBOOK_OPEN_ORDERS_ACCOUNT",
+            profile.open_orders_account.as_ref(),
+        )
+        .map(|account| parse_pubkey("open_orders_account", &account))
+        .transpose()?;
+        let referrer = env_or("OPENBOOK_REFERRER", profile.referrer.as_ref())
+            .map(|referrer| parse_pubkey("referrer", &referrer))
+            .transpose()?;
+
+        Ok(ClientConfig {
+            rpc_url,
+            keypair_path: expand_home(&keypair_path),
+            commitment,
+            market,
+            open_orders_account,
+            referrer,
+            markets: profile.markets.clone(),
+        })
+    }
+}
+
+fn builtin_profile(name: &str) -> Option<Profile> {
+    let rpc_url = match name {
+        "devnet" => "https://api.devnet.solana.com",
+        "mainnet" => "https://api.mainnet-beta.solana.com",
+        "localnet" => "http://127.0.0.1:8899",
+        _ => return None,
+    };
+    Some(Profile {
+        rpc_url: Some(rpc_url.to_string()),
+        ..Profile::default()
+    })
+}
+
+fn env_or(var: &str, value: Option<&String>) -> Option<String> {
+    std::env::var(var).ok().or_else(|| value.cloned())
+}
+
+fn parse_pubkey(field: &str, value: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|err| anyhow!("Invalid {field} {value}: {err}"))
+}
+
+/// Expands a leading `~` of `path` to the home directory.
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{home}{rest}"),
+        _ => path.to_string(),
+    }
+}
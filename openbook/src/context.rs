@@ -1,10 +1,22 @@
-use openbook_v2::state::Market;
+use openbook_v2::state::{Market, Side};
 use solana_sdk::pubkey::Pubkey;
 
+use crate::{fees, token::MintInfo};
+
+/// The lots of a limit order, as placed by `OBClient::place_limit_order`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OrderLots {
+    pub price_lots: i64,
+    pub max_base_lots: u64,
+    pub max_quote_lots: u64,
+}
+
 #[derive(Clone)]
 pub struct MarketContext {
     pub address: Pubkey,
     pub market: Market,
+    pub base_mint_info: MintInfo,
+    pub quote_mint_info: MintInfo,
 }
 
 impl MarketContext {
@@ -15,14 +27,118 @@ impl MarketContext {
         self.max_base_lots(base_size * self.market.base_decimals as u64)
     }
 
+    /// Converts a limit price and a quote size to the lots of an order on `side`.
+    ///
+    /// Only the tokens the order transfers to the market vault pay their mint's transfer fee: the
+    /// quote of a bid, and the base of an ask.
+    pub fn order_lots(&self, side: Side, limit_price: f64, quote_size: u64) -> OrderLots {
+        let base_size = self.base_size_from_quote(quote_size, limit_price);
+        let mut base_native = base_size * self.market.base_decimals as u64;
+        let mut quote_native = quote_size * 10u64.pow(6);
+        match side {
+            Side::Bid => quote_native = self.quote_after_transfer_fee(quote_native),
+            Side::Ask => base_native = self.base_after_transfer_fee(base_native),
+        }
+        OrderLots {
+            price_lots: self.native_price_to_lots_price(limit_price),
+            max_base_lots: self.max_base_lots(base_native),
+            max_quote_lots: self.max_quote_lots_including_maker_fees(quote_native),
+        }
+    }
+
+    /// Converts a price in quote lots per base lot and a quote size to the lots of an order, like
+    /// `order_lots` does for a limit price.
+    pub fn order_lots_from_price_lots(
+        &self,
+        side: Side,
+        price_lots: i64,
+        quote_size: u64,
+    ) -> OrderLots {
+        OrderLots {
+            price_lots,
+            ..self.order_lots(side, self.price_lots_to_ui(price_lots), quote_size)
+        }
+    }
+
+    pub fn native_price_to_lots_price(&self, limit_price: f64) -> i64 {
+        let base_decimals = self.market.base_decimals as u32;
+        let quote_decimals = self.market.quote_decimals as u32;
+        let base_factor = 10_u64.pow(base_decimals);
+        let quote_factor = 10_u64.pow(quote_decimals);
+        let price_factor = (base_factor / quote_factor) as f64;
+        (limit_price * price_factor) as i64
+    }
+
+    pub fn base_size_from_quote(&self, quote_size: u64, limit_price: f64) -> u64 {
+        let base_decimals = self.market.base_decimals as u32;
+        let base_factor = 10_u64.pow(base_decimals) as f64;
+        ((quote_size as f64 / limit_price) * base_factor) as u64
+    }
+
     // For PostOnly or PostOnlySlide orders.
     pub fn max_quote_lots_including_maker_fees(&self, quote_size: u64) -> u64 {
-        let quote_lots: u64 = quote_size / (self.market.quote_lot_size as u64);
-        let fees: u64 = self.market.maker_fees_floor(quote_size);
-        quote_lots + fees
+        let quote_lots = quote_size / (self.market.quote_lot_size as u64);
+        fees::quote_lots_including_maker_fees(&self.market, quote_lots as i64) as u64
     }
 
     pub fn max_base_lots(&self, base_size: u64) -> u64 {
         base_size / (self.market.base_lot_size as u64)
     }
+
+    /// The base amount reaching the market vault when `base_size` is transferred to it.
+    pub fn base_after_transfer_fee(&self, base_size: u64) -> u64 {
+        self.base_mint_info
+            .transfer_fee
+            .map_or(base_size, |fee| fee.amount_after_fee(base_size))
+    }
+
+    /// The quote amount reaching the market vault when `quote_size` is transferred to it.
+    pub fn quote_after_transfer_fee(&self, quote_size: u64) -> u64 {
+        self.quote_mint_info
+            .transfer_fee
+            .map_or(quote_size, |fee| fee.amount_after_fee(quote_size))
+    }
+
+    /// Converts a price in quote lots per base lot to a price in quote tokens per base token.
+    pub fn price_lots_to_ui(&self, price_lots: i64) -> f64 {
+        let decimals_factor =
+            10f64.powi(self.market.base_decimals as i32 - self.market.quote_decimals as i32);
+        price_lots as f64 * self.market.quote_lot_size as f64 * decimals_factor
+            / self.market.base_lot_size as f64
+    }
+
+    /// Converts a price in quote tokens per base token to quote lots per base lot, rounded to the
+    /// nearest lot, the inverse of `price_lots_to_ui`.
+    pub fn price_ui_to_lots(&self, price: f64) -> i64 {
+        (price / self.price_lots_to_ui(1)).round() as i64
+    }
+
+    /// Converts a quantity in base tokens to base lots, rounded to the nearest lot.
+    pub fn base_ui_to_lots(&self, base_size: f64) -> i64 {
+        (base_size / self.base_lots_to_ui(1)).round() as i64
+    }
+
+    /// The lots of an order of `base_lots` at `price_lots`, with enough quote for the taker fee.
+    pub fn taker_order_lots(&self, price_lots: i64, base_lots: i64) -> OrderLots {
+        let quote_lots =
+            fees::quote_lots_including_taker_fees(&self.market, price_lots * base_lots);
+        OrderLots {
+            price_lots,
+            max_base_lots: base_lots as u64,
+            max_quote_lots: quote_lots as u64,
+        }
+    }
+
+    /// Converts a quantity in base lots to a quantity in base tokens.
+    pub fn base_lots_to_ui(&self, base_lots: i64) -> f64 {
+        self.base_native_to_ui(base_lots * self.market.base_lot_size)
+    }
+
+    pub fn base_native_to_ui(&self, base_native: i64) -> f64 {
+        base_native as f64 / 10f64.powi(self.market.base_decimals as i32)
+    }
+
+    pub fn quote_native_to_ui(&self, quote_native: i64) -> f64 {
+        quote_native as f64 / 10f64.powi(self.market.quote_decimals as i32)
+    }
 }
@@ -0,0 +1,66 @@
+//! Decoding of the event heap of an OpenBook V2 market.
+
+use openbook_v2::state::{AnyEvent, EventHeap, EventType, FillEvent, OutEvent};
+use solana_sdk::pubkey::Pubkey;
+
+/// A decoded event of the event heap.
+#[derive(Clone, Copy, Debug)]
+pub enum MarketEvent {
+    /// A taker order matched a resting maker order.
+    Fill(FillEvent),
+
+    /// A resting order left the book (cancelled, expired or fully filled) and has funds to unlock.
+    Out(OutEvent),
+}
+
+impl MarketEvent {
+    /// Decodes `event`, returning `None` for unknown event types.
+    pub fn decode(event: &AnyEvent) -> Option<Self> {
+        if event.event_type == EventType::Fill as u8 {
+            Some(Self::Fill(*bytemuck::cast_ref::<AnyEvent, FillEvent>(
+                event,
+            )))
+        } else if event.event_type == EventType::Out as u8 {
+            Some(Self::Out(*bytemuck::cast_ref::<AnyEvent, OutEvent>(event)))
+        } else {
+            None
+        }
+    }
+
+    /// The open orders account that must be passed to `ConsumeEvents` to process this event.
+    pub fn open_orders_account(&self) -> Pubkey {
+        match self {
+            Self::Fill(fill) => fill.maker,
+            Self::Out(out) => out.owner,
+        }
+    }
+}
+
+/// Lists the pending events of `event_heap` in processing order, with their heap slots.
+pub fn pending_events(event_heap: &EventHeap) -> Vec<(usize, MarketEvent)> {
+    event_heap
+        .iter()
+        .filter_map(|(event, slot)| MarketEvent::decode(event).map(|event| (slot, event)))
+        .collect()
+}
+
+/// Lists the distinct open orders accounts referenced by the first `limit` pending events.
+pub fn pending_open_orders_accounts(event_heap: &EventHeap, limit: usize) -> Vec<Pubkey> {
+    let mut accounts: Vec<Pubkey> = vec![];
+    for (_, event) in pending_events(event_heap).into_iter().take(limit) {
+        let account = event.open_orders_account();
+        if !accounts.contains(&account) {
+            accounts.push(account);
+        }
+    }
+    accounts
+}
+
+/// Lists the heap slots of the pending events processed against `open_orders_account`.
+pub fn pending_event_slots(event_heap: &EventHeap, open_orders_account: &Pubkey) -> Vec<usize> {
+    pending_events(event_heap)
+        .into_iter()
+        .filter(|(_, event)| event.open_orders_account() == *open_orders_account)
+        .map(|(slot, _)| slot)
+        .collect()
+}
@@ -0,0 +1,340 @@
+//! Execution algorithms working a large order over time, as strategies run by the
+//! `StrategyRuntime` or evaluated with the backtester.
+//!
+//! - `Twap` takes liquidity with immediate-or-cancel slices spread over a duration, with
+//!   randomized sizes and times. Size a slice could not fill at the limit price is carried over
+//!   to the next one.
+//! - `Iceberg` keeps a clip of its size resting at its limit price, and places the next clip once
+//!   the open orders account shows the previous one filled.
+//!
+//! Progress is measured from the change of the base inventory since the algorithm started, so
+//! fills are counted even when cranked out of the event heap before the runtime polls.
+
+use std::time::Duration;
+
+use openbook_v2::state::Side;
+use rand::Rng;
+
+use crate::{
+    config::OrderType,
+    strategy::{OrderIntent, Strategy, StrategyContext},
+};
+
+/// Progress of an execution algorithm.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Base lots executed so far.
+    pub filled_base_lots: i64,
+
+    /// Base lots to execute, known once the algorithm started.
+    pub target_base_lots: i64,
+
+    /// Number of orders placed.
+    pub orders: usize,
+
+    pub done: bool,
+}
+
+impl Progress {
+    /// The executed fraction of the target, from 0 to 1.
+    pub fn fraction(&self) -> f64 {
+        if self.target_base_lots == 0 {
+            return 0.0;
+        }
+        (self.filled_base_lots as f64 / self.target_base_lots as f64).min(1.0)
+    }
+}
+
+/// Called with the progress of an execution algorithm whenever it changes.
+pub type ProgressCallback = Box<dyn FnMut(&Progress) + Send>;
+
+/// Progress tracking shared by the algorithms.
+struct Tracker {
+    side: Side,
+    base_size: f64,
+    start_inventory: Option<i64>,
+    progress: Progress,
+
+    /// Slot of the state the last order was placed on, to not place twice on the same state.
+    last_order_slot: Option<u64>,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl Tracker {
+    fn new(side: Side, base_size: f64) -> Self {
+        Self {
+            side,
+            base_size,
+            start_inventory: None,
+            progress: Progress::default(),
+            last_order_slot: None,
+            on_progress: None,
+        }
+    }
+
+    /// Updates the executed lots from the base inventory of `ctx`. The first call starts the
+    /// algorithm.
+    fn update(&mut self, ctx: &StrategyContext) {
+        let start = match self.start_inventory {
+            Some(start) => start,
+            None => {
+                self.start_inventory = Some(ctx.base_inventory);
+                self.progress.target_base_lots = ctx.market.base_ui_to_lots(self.base_size);
+                self.report();
+                ctx.base_inventory
+            }
+        };
+        let change = match self.side {
+            Side::Bid => ctx.base_inventory - start,
+            Side::Ask => start - ctx.base_inventory,
+        };
+        let filled = (change / ctx.market.market.base_lot_size).max(0);
+        if filled != self.progress.filled_base_lots {
+            self.progress.filled_base_lots = filled;
+            self.report();
+        }
+        if self.remaining() == 0 {
+            self.finish();
+        }
+    }
+
+    fn remaining(&self) -> i64 {
+        (self.progress.target_base_lots - self.progress.filled_base_lots).max(0)
+    }
+
+    fn can_place(&self, ctx: &StrategyContext) -> bool {
+        self.last_order_slot.map_or(true, |slot| ctx.slot > slot)
+    }
+
+    fn placed(&mut self, ctx: &StrategyContext) {
+        self.last_order_slot = Some(ctx.slot);
+        self.progress.orders += 1;
+        self.report();
+    }
+
+    fn finish(&mut self) {
+        if !self.progress.done {
+            self.progress.done = true;
+            self.report();
+        }
+    }
+
+    fn report(&mut self) {
+        let progress = self.progress;
+        tracing::info!(
+            "Executed {}/{} base lots with {} orders{}",
+            progress.filled_base_lots,
+            progress.target_base_lots,
+            progress.orders,
+            if progress.done { ", done" } else { "" }
+        );
+        if let Some(on_progress) = &mut self.on_progress {
+            on_progress(&progress);
+        }
+    }
+}
+
+/// Settings of a `Twap`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TwapConfig {
+    pub side: Side,
+
+    /// Size to execute, in base tokens.
+    pub base_size: f64,
+
+    /// Worst price to fill at, in quote tokens per base token.
+    pub limit_price: f64,
+
+    pub duration: Duration,
+
+    /// Number of slices, due at regular intervals over the duration.
+    pub slices: usize,
+
+    /// Random variation of the sizes and times of the slices, from 0 for none to 1 for up to
+    /// their whole size and interval.
+    pub jitter: f64,
+}
+
+/// Executes a size with taking slices spread over a duration.
+pub struct Twap {
+    pub config: TwapConfig,
+    tracker: Tracker,
+
+    /// Due timestamps of the slices, with the fraction of the size executed once they are done.
+    schedule: Vec<(u64, f64)>,
+
+    /// Number of slices placed, or skipped as already executed.
+    sent: usize,
+}
+
+impl Twap {
+    pub fn new(config: TwapConfig) -> Self {
+        Self {
+            config,
+            tracker: Tracker::new(config.side, config.base_size),
+            schedule: vec![],
+            sent: 0,
+        }
+    }
+
+    /// Calls `on_progress` whenever the progress changes.
+    pub fn with_progress(mut self, on_progress: impl FnMut(&Progress) + Send + 'static) -> Self {
+        self.tracker.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.tracker.progress
+    }
+
+    fn build_schedule(&self, start: u64) -> Vec<(u64, f64)> {
+        let mut rng = rand::thread_rng();
+        let jitter = self.config.jitter.clamp(0.0, 1.0);
+        let slices = self.config.slices.max(1);
+        let interval = self.config.duration.as_secs_f64() / slices as f64;
+
+        let weights: Vec<f64> = (0..slices)
+            .map(|_| 1.0 + jitter * rng.gen_range(-1.0..=1.0))
+            .collect();
+        let total: f64 = weights.iter().sum();
+        let mut executed = 0.0;
+        weights
+            .iter()
+            .enumerate()
+            .map(|(slice, weight)| {
+                executed += weight / total;
+                let offset = (slice as f64 + jitter * rng.gen::<f64>()) * interval;
+                (start + offset as u64, executed)
+            })
+            .collect()
+    }
+
+    fn step(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        if self.schedule.is_empty() {
+            self.schedule = self.build_schedule(ctx.timestamp);
+        }
+        self.tracker.update(ctx);
+        if self.sent == self.schedule.len() && self.tracker.can_place(ctx) {
+            self.tracker.finish();
+        }
+        if self.tracker.progress.done || !self.tracker.can_place(ctx) {
+            return vec![];
+        }
+
+        let due = self
+            .schedule
+            .iter()
+            .take_while(|(timestamp, _)| *timestamp <= ctx.timestamp)
+            .count();
+        if due == self.sent {
+            return vec![];
+        }
+        self.sent = due;
+        let executed = self.schedule[due - 1].1;
+        let target = (executed * self.tracker.progress.target_base_lots as f64).round() as i64;
+        let base_lots = target.min(self.tracker.progress.target_base_lots)
+            - self.tracker.progress.filled_base_lots;
+        if base_lots <= 0 {
+            return vec![];
+        }
+
+        self.tracker.placed(ctx);
+        let price_lots = ctx.market.price_ui_to_lots(self.config.limit_price);
+        vec![OrderIntent::PlaceLots {
+            side: self.config.side,
+            lots: ctx.market.taker_order_lots(price_lots, base_lots),
+            order_type: OrderType::ImmediateOrCancel,
+        }]
+    }
+}
+
+impl Strategy for Twap {
+    fn on_book(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        self.step(ctx)
+    }
+
+    fn on_timer(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        self.step(ctx)
+    }
+
+    fn is_done(&self) -> bool {
+        self.tracker.progress.done
+    }
+}
+
+/// Settings of an `Iceberg`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IcebergConfig {
+    pub side: Side,
+
+    /// Size to execute, in base tokens.
+    pub base_size: f64,
+
+    /// Size of the resting clip, in base tokens.
+    pub clip_size: f64,
+
+    /// Price of the clips, in quote tokens per base token.
+    pub limit_price: f64,
+}
+
+/// Executes a size by resting one clip of it at a time.
+pub struct Iceberg {
+    pub config: IcebergConfig,
+    tracker: Tracker,
+}
+
+impl Iceberg {
+    pub fn new(config: IcebergConfig) -> Self {
+        Self {
+            config,
+            tracker: Tracker::new(config.side, config.base_size),
+        }
+    }
+
+    /// Calls `on_progress` whenever the progress changes.
+    pub fn with_progress(mut self, on_progress: impl FnMut(&Progress) + Send + 'static) -> Self {
+        self.tracker.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.tracker.progress
+    }
+
+    fn step(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        self.tracker.update(ctx);
+        let resting = !ctx.open_orders.is_empty();
+        if self.tracker.progress.done || resting || !self.tracker.can_place(ctx) {
+            return vec![];
+        }
+
+        let clip = ctx
+            .market
+            .base_ui_to_lots(self.config.clip_size)
+            .min(self.tracker.remaining());
+        if clip <= 0 {
+            return vec![];
+        }
+        self.tracker.placed(ctx);
+        let price_lots = ctx.market.price_ui_to_lots(self.config.limit_price);
+        vec![OrderIntent::PlaceLots {
+            side: self.config.side,
+            lots: ctx.market.taker_order_lots(price_lots, clip),
+            order_type: OrderType::Limit,
+        }]
+    }
+}
+
+impl Strategy for Iceberg {
+    fn on_book(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        self.step(ctx)
+    }
+
+    fn on_timer(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        self.step(ctx)
+    }
+
+    fn is_done(&self) -> bool {
+        self.tracker.progress.done
+    }
+}
@@ -0,0 +1,152 @@
+//! Fee calculations of the OpenBook V2 program.
+//!
+//! Fee rates are in millionths of the quote matched. Taker fees are rounded up, maker rebates,
+//! for a negative maker fee, are rounded down, and positive maker fees are rounded up. The taker
+//! fee of a match pays the maker rebate, and the rest is accrued by the market, or shared with
+//! the referrer of the taker order when one is given.
+//!
+//! Bids lock their fees on top of their quote: the taker fee is reserved from the
+//! `max_quote_lots_including_fees` of an order before matching, and a positive maker fee is
+//! locked with the part of the order resting on the book. Asks pay their fees out of the quote
+//! they receive.
+
+use openbook_v2::state::{Market, Side};
+use serde::{Deserialize, Serialize};
+
+/// Scale of the market's fee rates, which are in millionths.
+pub const FEES_SCALE_FACTOR: i128 = 1_000_000;
+
+/// The taker fee of a match of `quote_native`, rounded up.
+pub fn taker_fee_ceil(market: &Market, quote_native: u64) -> u64 {
+    div_ceil(quote_native as i128 * market.taker_fee as i128) as u64
+}
+
+/// The fee paid by the maker of a match of `quote_native`, rounded up, or 0 for a market with
+/// maker rebates.
+pub fn maker_fee_ceil(market: &Market, quote_native: u64) -> u64 {
+    if market.maker_fee > 0 {
+        div_ceil(quote_native as i128 * market.maker_fee as i128) as u64
+    } else {
+        0
+    }
+}
+
+/// The rebate paid to the maker of a match of `quote_native`, rounded down, or 0 for a market
+/// with maker fees.
+pub fn maker_rebate_floor(market: &Market, quote_native: u64) -> u64 {
+    if market.maker_fee < 0 {
+        (quote_native as i128 * -market.maker_fee as i128 / FEES_SCALE_FACTOR) as u64
+    } else {
+        0
+    }
+}
+
+/// The fee of a maker or taker fill of `quote_native`, in native quote units. Negative for a
+/// maker rebate.
+pub fn fill_fee(market: &Market, quote_native: u64, maker: bool) -> i64 {
+    if !maker {
+        taker_fee_ceil(market, quote_native) as i64
+    } else if market.maker_fee < 0 {
+        -(maker_rebate_floor(market, quote_native) as i64)
+    } else {
+        maker_fee_ceil(market, quote_native) as i64
+    }
+}
+
+/// The share of the taker fee of a match of `quote_native` paid to the referrer of the taker
+/// order: the taker fee net of the maker rebate.
+pub fn referrer_rebate(market: &Market, quote_native: u64) -> u64 {
+    taker_fee_ceil(market, quote_native).saturating_sub(maker_rebate_floor(market, quote_native))
+}
+
+/// The quote lots a bid can match from `quote_lots_including_fees`, after reserving the taker
+/// fee, rounded down like the program.
+pub fn subtract_taker_fees(market: &Market, quote_lots_including_fees: i64) -> i64 {
+    (quote_lots_including_fees as i128 * FEES_SCALE_FACTOR
+        / (FEES_SCALE_FACTOR + market.taker_fee as i128)) as i64
+}
+
+/// The smallest `max_quote_lots_including_fees` of a bid able to match `quote_lots` as a taker,
+/// the inverse of `subtract_taker_fees`.
+pub fn quote_lots_including_taker_fees(market: &Market, quote_lots: i64) -> i64 {
+    let scaled = quote_lots as i128 * (FEES_SCALE_FACTOR + market.taker_fee as i128);
+    (scaled as u128).div_ceil(FEES_SCALE_FACTOR as u128) as i64
+}
+
+/// The quote lots a bid of `quote_lots` resting on the book locks, with its maker fee rounded
+/// up to a whole lot.
+pub fn quote_lots_including_maker_fees(market: &Market, quote_lots: i64) -> i64 {
+    let quote_lot_size = market.quote_lot_size as u64;
+    let fee_native = maker_fee_ceil(market, quote_lots as u64 * quote_lot_size);
+    quote_lots + fee_native.div_ceil(quote_lot_size) as i64
+}
+
+/// The `max_quote_lots_including_fees` of an order matching or resting with `quote_lots` before
+/// fees. Asks pay their fees out of the quote they receive, so their quote lots are unchanged.
+pub fn quote_lots_including_fees(market: &Market, side: Side, quote_lots: i64, maker: bool) -> i64 {
+    match side {
+        Side::Ask => quote_lots,
+        Side::Bid if maker => quote_lots_including_maker_fees(market, quote_lots),
+        Side::Bid => quote_lots_including_taker_fees(market, quote_lots),
+    }
+}
+
+/// The fees of a taker order matching `quote_native`, all in native quote units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeBreakdown {
+    /// The quote matched, before fees.
+    pub quote_native: u64,
+
+    /// The fee paid by the taker.
+    pub taker_fee_native: u64,
+
+    /// The rebates earned by the makers, paid out of the taker fee.
+    pub maker_rebate_native: u64,
+
+    /// The fees paid by the makers, for a market with positive maker fees.
+    pub maker_fee_native: u64,
+
+    /// The share of the taker fee paid to the referrer, 0 without a referrer.
+    pub referrer_rebate_native: u64,
+
+    /// The fees accrued by the market.
+    pub market_fee_native: u64,
+
+    /// The quote paid by a bid including its fee, or received by an ask net of its fee.
+    pub taker_net_quote_native: u64,
+}
+
+impl FeeBreakdown {
+    /// The fees of a `side` taker order matching `quote_native`, with or without a referrer.
+    ///
+    /// Fees are computed on the total, like the program does for the taker fee. The maker fees
+    /// and rebates of several makers are rounded per fill, and can differ by a few native units.
+    pub fn new(market: &Market, side: Side, quote_native: u64, referrer: bool) -> Self {
+        let taker_fee_native = taker_fee_ceil(market, quote_native);
+        let maker_rebate_native = maker_rebate_floor(market, quote_native);
+        let maker_fee_native = maker_fee_ceil(market, quote_native);
+        let referrer_rebate_native = if referrer {
+            referrer_rebate(market, quote_native)
+        } else {
+            0
+        };
+        let taker_net_quote_native = match side {
+            Side::Bid => quote_native + taker_fee_native,
+            Side::Ask => quote_native.saturating_sub(taker_fee_native),
+        };
+        Self {
+            quote_native,
+            taker_fee_native,
+            maker_rebate_native,
+            maker_fee_native,
+            referrer_rebate_native,
+            market_fee_native: (taker_fee_native + maker_fee_native)
+                .saturating_sub(maker_rebate_native + referrer_rebate_native),
+            taker_net_quote_native,
+        }
+    }
+}
+
+fn div_ceil(product: i128) -> i128 {
+    product.div_euclid(FEES_SCALE_FACTOR) + (product.rem_euclid(FEES_SCALE_FACTOR) != 0) as i128
+}
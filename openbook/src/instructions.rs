@@ -0,0 +1,296 @@
+//! Pure, synchronous builders for every OpenBook V2 instruction.
+//!
+//! Each function takes the explicit `openbook_v2::accounts` struct of the instruction together with
+//! its arguments, and never touches the network. This makes it possible to build instructions for
+//! any wallet, delegate or market without an `OBClient`.
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use openbook_v2::{
+    accounts, instruction as ix,
+    state::{OracleConfigParams, PlaceOrderType, Side},
+    PlaceMultipleOrdersArgs, PlaceOrderArgs, PlaceOrderPeggedArgs, PlaceTakeOrderArgs,
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: openbook_v2::id(),
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Derives the PDA owning the vaults of `market`.
+pub fn market_authority(market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"Market".as_ref(), market.as_ref()], &openbook_v2::id()).0
+}
+
+/// Derives the anchor event authority PDA of the OpenBook program.
+pub fn event_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority".as_ref()], &openbook_v2::id()).0
+}
+
+/// Derives the open orders indexer PDA of `owner`.
+pub fn open_orders_indexer(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"OpenOrdersIndexer".as_ref(), owner.as_ref()],
+        &openbook_v2::id(),
+    )
+    .0
+}
+
+/// Derives the PDA of the `account_num`-th open orders account of `owner`.
+pub fn open_orders_account(owner: &Pubkey, account_num: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"OpenOrders".as_ref(),
+            owner.as_ref(),
+            &account_num.to_le_bytes(),
+        ],
+        &openbook_v2::id(),
+    )
+    .0
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_market(
+    accounts: accounts::CreateMarket,
+    name: String,
+    oracle_config: OracleConfigParams,
+    quote_lot_size: i64,
+    base_lot_size: i64,
+    maker_fee: i64,
+    taker_fee: i64,
+    time_expiry: i64,
+) -> Instruction {
+    build(
+        accounts,
+        ix::CreateMarket {
+            name,
+            oracle_config,
+            quote_lot_size,
+            base_lot_size,
+            maker_fee,
+            taker_fee,
+            time_expiry,
+        },
+    )
+}
+
+pub fn close_market(accounts: accounts::CloseMarket) -> Instruction {
+    build(accounts, ix::CloseMarket {})
+}
+
+pub fn create_open_orders_indexer(accounts: accounts::CreateOpenOrdersIndexer) -> Instruction {
+    build(accounts, ix::CreateOpenOrdersIndexer {})
+}
+
+pub fn close_open_orders_indexer(accounts: accounts::CloseOpenOrdersIndexer) -> Instruction {
+    build(accounts, ix::CloseOpenOrdersIndexer {})
+}
+
+pub fn create_open_orders_account(
+    accounts: accounts::CreateOpenOrdersAccount,
+    name: String,
+) -> Instruction {
+    build(accounts, ix::CreateOpenOrdersAccount { name })
+}
+
+pub fn close_open_orders_account(accounts: accounts::CloseOpenOrdersAccount) -> Instruction {
+    build(accounts, ix::CloseOpenOrdersAccount {})
+}
+
+pub fn place_order(accounts: accounts::PlaceOrder, args: PlaceOrderArgs) -> Instruction {
+    build(accounts, ix::PlaceOrder { args })
+}
+
+pub fn edit_order(
+    accounts: accounts::PlaceOrder,
+    client_order_id: u64,
+    expected_cancel_size: i64,
+    place_order: PlaceOrderArgs,
+) -> Instruction {
+    build(
+        accounts,
+        ix::EditOrder {
+            client_order_id,
+            expected_cancel_size,
+            place_order,
+        },
+    )
+}
+
+pub fn edit_order_pegged(
+    accounts: accounts::PlaceOrder,
+    client_order_id: u64,
+    expected_cancel_size: i64,
+    place_order: PlaceOrderPeggedArgs,
+) -> Instruction {
+    build(
+        accounts,
+        ix::EditOrderPegged {
+            client_order_id,
+            expected_cancel_size,
+            place_order,
+        },
+    )
+}
+
+pub fn place_orders(
+    accounts: accounts::CancelAllAndPlaceOrders,
+    orders_type: PlaceOrderType,
+    bids: Vec<PlaceMultipleOrdersArgs>,
+    asks: Vec<PlaceMultipleOrdersArgs>,
+    limit: u8,
+) -> Instruction {
+    build(
+        accounts,
+        ix::PlaceOrders {
+            orders_type,
+            bids,
+            asks,
+            limit,
+        },
+    )
+}
+
+pub fn cancel_all_and_place_orders(
+    accounts: accounts::CancelAllAndPlaceOrders,
+    orders_type: PlaceOrderType,
+    bids: Vec<PlaceMultipleOrdersArgs>,
+    asks: Vec<PlaceMultipleOrdersArgs>,
+    limit: u8,
+) -> Instruction {
+    build(
+        accounts,
+        ix::CancelAllAndPlaceOrders {
+            orders_type,
+            bids,
+            asks,
+            limit,
+        },
+    )
+}
+
+pub fn place_order_pegged(
+    accounts: accounts::PlaceOrder,
+    args: PlaceOrderPeggedArgs,
+) -> Instruction {
+    build(accounts, ix::PlaceOrderPegged { args })
+}
+
+pub fn place_take_order(
+    accounts: accounts::PlaceTakeOrder,
+    args: PlaceTakeOrderArgs,
+) -> Instruction {
+    build(accounts, ix::PlaceTakeOrder { args })
+}
+
+/// Builds `ConsumeEvents`, passing `open_orders_accounts` as the writable remaining accounts
+/// the event heap is processed against.
+pub fn consume_events(
+    accounts: accounts::ConsumeEvents,
+    open_orders_accounts: &[Pubkey],
+    limit: usize,
+) -> Instruction {
+    let mut instruction = build(accounts, ix::ConsumeEvents { limit });
+    instruction.accounts.extend(
+        open_orders_accounts
+            .iter()
+            .map(|key| AccountMeta::new(*key, false)),
+    );
+    instruction
+}
+
+/// Builds `ConsumeGivenEvents` for the event heap `slots`, see [`consume_events`].
+pub fn consume_given_events(
+    accounts: accounts::ConsumeEvents,
+    open_orders_accounts: &[Pubkey],
+    slots: Vec<usize>,
+) -> Instruction {
+    let mut instruction = build(accounts, ix::ConsumeGivenEvents { slots });
+    instruction.accounts.extend(
+        open_orders_accounts
+            .iter()
+            .map(|key| AccountMeta::new(*key, false)),
+    );
+    instruction
+}
+
+pub fn cancel_order(accounts: accounts::CancelOrder, order_id: u128) -> Instruction {
+    build(accounts, ix::CancelOrder { order_id })
+}
+
+pub fn cancel_order_by_client_order_id(
+    accounts: accounts::CancelOrder,
+    client_order_id: u64,
+) -> Instruction {
+    build(accounts, ix::CancelOrderByClientOrderId { client_order_id })
+}
+
+pub fn cancel_all_orders(
+    accounts: accounts::CancelOrder,
+    side_option: Option<Side>,
+    limit: u8,
+) -> Instruction {
+    build(accounts, ix::CancelAllOrders { side_option, limit })
+}
+
+pub fn deposit(accounts: accounts::Deposit, base_amount: u64, quote_amount: u64) -> Instruction {
+    build(
+        accounts,
+        ix::Deposit {
+            base_amount,
+            quote_amount,
+        },
+    )
+}
+
+pub fn refill(accounts: accounts::Deposit, base_amount: u64, quote_amount: u64) -> Instruction {
+    build(
+        accounts,
+        ix::Refill {
+            base_amount,
+            quote_amount,
+        },
+    )
+}
+
+pub fn settle_funds(accounts: accounts::SettleFunds) -> Instruction {
+    build(accounts, ix::SettleFunds {})
+}
+
+pub fn settle_funds_expired(accounts: accounts::SettleFundsExpired) -> Instruction {
+    build(accounts, ix::SettleFundsExpired {})
+}
+
+pub fn sweep_fees(accounts: accounts::SweepFees) -> Instruction {
+    build(accounts, ix::SweepFees {})
+}
+
+pub fn set_delegate(accounts: accounts::SetDelegate) -> Instruction {
+    build(accounts, ix::SetDelegate {})
+}
+
+pub fn set_market_expired(accounts: accounts::SetMarketExpired) -> Instruction {
+    build(accounts, ix::SetMarketExpired {})
+}
+
+pub fn prune_orders(accounts: accounts::PruneOrders, limit: u8) -> Instruction {
+    build(accounts, ix::PruneOrders { limit })
+}
+
+pub fn stub_oracle_create(accounts: accounts::StubOracleCreate, price: f64) -> Instruction {
+    build(accounts, ix::StubOracleCreate { price })
+}
+
+pub fn stub_oracle_close(accounts: accounts::StubOracleClose) -> Instruction {
+    build(accounts, ix::StubOracleClose {})
+}
+
+pub fn stub_oracle_set(accounts: accounts::StubOracleSet, price: f64) -> Instruction {
+    build(accounts, ix::StubOracleSet { price })
+}
@@ -0,0 +1,72 @@
+//! Emergency flattening of all the open orders accounts of an owner, see `OBClient::kill_switch`.
+
+use std::time::Duration;
+
+use openbook_v2::state::OpenOrdersAccount;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+/// Settings of `OBClient::kill_switch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KillSwitchConfig {
+    /// Whether to close the open orders accounts once flat, returning their rent to the owner.
+    pub close_accounts: bool,
+
+    /// Maximum number of rounds of transactions before giving up.
+    pub max_attempts: usize,
+
+    /// Delay after a round, before the state is verified again.
+    pub retry_delay: Duration,
+}
+
+impl Default for KillSwitchConfig {
+    fn default() -> Self {
+        Self {
+            close_accounts: false,
+            max_attempts: 10,
+            retry_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+/// An open orders account flattened by the kill switch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FlattenedAccount {
+    pub address: Pubkey,
+    pub market: Pubkey,
+
+    /// Number of orders open when the kill switch found the account.
+    pub open_orders: usize,
+
+    /// Whether the account no longer exists.
+    pub closed: bool,
+
+    /// Whether the funds of the account were left unsettled, as the base and quote mints of its
+    /// market use different token programs, which `SettleFunds` can not transfer together. Its
+    /// orders are still cancelled.
+    pub unsettled: bool,
+}
+
+/// The outcome of `OBClient::kill_switch`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KillSwitchReport {
+    /// The accounts found, all verified flat, or closed with `KillSwitchConfig::close_accounts`.
+    pub accounts: Vec<FlattenedAccount>,
+
+    /// Number of rounds of transactions sent.
+    pub attempts: usize,
+
+    /// Signatures of the transactions that succeeded.
+    pub signatures: Vec<Signature>,
+}
+
+/// Whether `account` has no orders, funds or rebates left, and can be closed.
+pub fn is_flat(account: &OpenOrdersAccount) -> bool {
+    let position = &account.position;
+    account.all_orders_in_use().next().is_none()
+        && position.bids_base_lots == 0
+        && position.asks_base_lots == 0
+        && position.base_free_native == 0
+        && position.quote_free_native == 0
+        && position.locked_maker_fees == 0
+        && position.referrer_rebates_available == 0
+}
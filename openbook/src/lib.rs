@@ -1,5 +1,25 @@
 /// Library for interacting with the OpenBook V2 program.
 /// The code of this library is based on https://github.com/GigaDAO/openbook
+pub mod admin;
+pub mod backtest;
+pub mod book;
+pub mod config;
 pub mod context;
+pub mod events;
+pub mod execution;
+pub mod fees;
+pub mod instructions;
+pub mod kill_switch;
+pub mod lookup_table;
+pub mod market_maker;
+pub mod matching;
 pub mod ob_client;
+pub mod oracle;
+pub mod portfolio;
+pub mod recorder;
+pub mod risk;
 mod rpc;
+mod serde_util;
+pub mod snapshot;
+pub mod strategy;
+pub mod token;
@@ -0,0 +1,142 @@
+//! Builders for address lookup tables holding the static accounts of a market, and for v0
+//! transactions using them.
+//!
+//! Referencing accounts through a lookup table takes one byte instead of 32, so v0 transactions
+//! fit many more orders, or open orders accounts to consume events for, than legacy ones.
+
+use anyhow::Result;
+use openbook_v2::state::Market;
+use solana_sdk::{
+    address_lookup_table::{
+        instruction::{create_lookup_table, extend_lookup_table},
+        state::AddressLookupTable,
+    },
+    address_lookup_table_account::AddressLookupTableAccount,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    pubkey::Pubkey,
+    signer::Signer,
+    system_program,
+    transaction::VersionedTransaction,
+};
+
+use crate::instructions;
+
+/// Maximum number of addresses added by one extend instruction, keeping it within a transaction.
+pub const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+
+/// The accounts used by most instructions on `market`: the market, its book sides, event heap,
+/// vaults, mints and oracles, and the programs they are passed to.
+pub fn market_addresses(
+    address: &Pubkey,
+    market: &Market,
+    token_programs: &[Pubkey],
+) -> Vec<Pubkey> {
+    let mut addresses = vec![
+        *address,
+        instructions::market_authority(address),
+        market.bids,
+        market.asks,
+        market.event_heap,
+        market.market_base_vault,
+        market.market_quote_vault,
+        market.base_mint,
+        market.quote_mint,
+        instructions::event_authority(),
+        openbook_v2::id(),
+        system_program::id(),
+        spl_associated_token_account::id(),
+    ];
+    addresses.extend(Option::<Pubkey>::from(market.oracle_a));
+    addresses.extend(Option::<Pubkey>::from(market.oracle_b));
+    addresses.extend(token_programs);
+
+    let mut unique = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        if !unique.contains(&address) {
+            unique.push(address);
+        }
+    }
+    unique
+}
+
+/// Creates a lookup table of `authority` and extends it with `addresses`.
+///
+/// Returns the instructions, in chunks small enough to send one chunk per transaction, and the
+/// address of the table. `recent_slot` must be a recent finalized slot. The table can be used
+/// from the slot after the one it was last extended in.
+pub fn create_and_extend(
+    authority: &Pubkey,
+    payer: &Pubkey,
+    recent_slot: u64,
+    addresses: &[Pubkey],
+) -> (Vec<Vec<Instruction>>, Pubkey) {
+    let (create, table) = create_lookup_table(*authority, *payer, recent_slot);
+    let mut chunks: Vec<Vec<Instruction>> = addresses
+        .chunks(MAX_ADDRESSES_PER_EXTEND)
+        .map(|chunk| vec![extend(&table, authority, payer, chunk)])
+        .collect();
+    match chunks.first_mut() {
+        Some(first) => first.insert(0, create),
+        None => chunks.push(vec![create]),
+    }
+    (chunks, table)
+}
+
+/// Adds `addresses` to the lookup `table` of `authority`.
+pub fn extend(
+    table: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    addresses: &[Pubkey],
+) -> Instruction {
+    extend_lookup_table(*table, *authority, Some(*payer), addresses.to_vec())
+}
+
+/// Decodes the lookup table account `data` at `key`.
+pub fn decode(key: Pubkey, data: &[u8]) -> Result<AddressLookupTableAccount> {
+    let table = AddressLookupTable::deserialize(data)?;
+    Ok(AddressLookupTableAccount {
+        key,
+        addresses: table.addresses.to_vec(),
+    })
+}
+
+/// Compiles a v0 message paid for by `payer`, referencing the accounts found in `lookup_tables`
+/// through them.
+pub fn to_message(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Result<VersionedMessage> {
+    Ok(VersionedMessage::V0(v0::Message::try_compile(
+        payer,
+        instructions,
+        lookup_tables,
+        recent_blockhash,
+    )?))
+}
+
+/// Builds a transaction of `message` signed by those of `signers` it requires.
+///
+/// Signatures of the other required signers are left empty, to be added by the caller.
+pub fn partially_signed(
+    message: VersionedMessage,
+    signers: &[&dyn Signer],
+) -> Result<VersionedTransaction> {
+    let required = message.header().num_required_signatures as usize;
+    let data = message.serialize();
+    let mut trx = VersionedTransaction {
+        signatures: vec![Default::default(); required],
+        message,
+    };
+    let keys = trx.message.static_account_keys()[..required].to_vec();
+    for signer in signers {
+        if let Some(position) = keys.iter().position(|key| *key == signer.pubkey()) {
+            trx.signatures[position] = signer.try_sign_message(&data)?;
+        }
+    }
+    Ok(trx)
+}
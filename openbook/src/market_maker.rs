@@ -0,0 +1,228 @@
+//! A reference market-making `Strategy`.
+//!
+//! The market maker quotes a ladder of bids and asks around a reference price, either the mid
+//! price of the book or the oracle price. The ladder is shifted by a constant skew and by an
+//! inventory skew, moving quotes down when holding more base than the target and up when holding
+//! less. No quote is placed closer to the reference price than the market's taker fee plus a
+//! minimum edge.
+//!
+//! Quotes are refreshed with a single `OrderIntent::Replace`, cancelling all orders and placing
+//! the new ladder in one transaction, when the ladder moved by more than `requote_bps` or some of
+//! the orders were filled or expired.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    book::BookOrder,
+    strategy::{OrderIntent, Quote, Strategy, StrategyContext},
+};
+
+/// Scale of basis points.
+const BPS: f64 = 10_000.0;
+
+/// The price the ladder is built around.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    /// The middle of the best bid and ask.
+    #[default]
+    Mid,
+
+    /// The oracle price of the market.
+    Oracle,
+}
+
+/// Settings of a `MarketMaker`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarketMakerConfig {
+    pub price_source: PriceSource,
+
+    /// Number of orders on each side.
+    pub levels: usize,
+
+    /// Size of each order, in quote tokens.
+    pub quote_size: u64,
+
+    /// Distance of the first level from the reference price, in basis points.
+    pub spread_bps: f64,
+
+    /// Distance between two levels, in basis points.
+    pub level_spacing_bps: f64,
+
+    /// Constant shift of the ladder, in basis points. Positive values quote higher.
+    pub skew_bps: f64,
+
+    /// Base inventory aimed for, in base tokens.
+    pub target_base: f64,
+
+    /// Shift of the ladder per base token held above the target, in basis points.
+    pub inventory_skew_bps: f64,
+
+    /// Maximum shift caused by the inventory, in basis points.
+    pub max_inventory_skew_bps: f64,
+
+    /// Edge kept over the taker fee, in basis points, so fills can be hedged by taking orders.
+    pub min_edge_bps: f64,
+
+    /// Move of the ladder, in basis points, above which the orders are refreshed.
+    pub requote_bps: f64,
+}
+
+impl Default for MarketMakerConfig {
+    fn default() -> Self {
+        Self {
+            price_source: PriceSource::Mid,
+            levels: 3,
+            quote_size: 10,
+            spread_bps: 20.0,
+            level_spacing_bps: 10.0,
+            skew_bps: 0.0,
+            target_base: 0.0,
+            inventory_skew_bps: 0.0,
+            max_inventory_skew_bps: 50.0,
+            min_edge_bps: 1.0,
+            requote_bps: 5.0,
+        }
+    }
+}
+
+/// The bids and asks quoted at once, from the closest to the reference price.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Ladder {
+    pub bids: Vec<Quote>,
+    pub asks: Vec<Quote>,
+}
+
+impl Ladder {
+    /// Whether `other` has the same levels, at prices within `bps` basis points of these.
+    fn is_close_to(&self, other: &Ladder, bps: f64) -> bool {
+        let close = |a: &[Quote], b: &[Quote]| {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|(a, b)| {
+                    let moved = (a.price_lots - b.price_lots).abs() as f64;
+                    moved * BPS <= bps * a.price_lots as f64 && a.quote_size == b.quote_size
+                })
+        };
+        close(&self.bids, &other.bids) && close(&self.asks, &other.asks)
+    }
+}
+
+/// Quotes a `Ladder` around the reference price of `MarketMakerConfig::price_source`.
+pub struct MarketMaker {
+    pub config: MarketMakerConfig,
+
+    /// The ladder of the last refresh.
+    quoted: Option<Ladder>,
+
+    /// Number of orders open after the last refresh, once observed.
+    resting: Option<usize>,
+}
+
+impl MarketMaker {
+    pub fn new(config: MarketMakerConfig) -> Self {
+        Self {
+            config,
+            quoted: None,
+            resting: None,
+        }
+    }
+
+    /// The reference price, in quote lots per base lot. The mid price ignores the market maker's
+    /// own orders.
+    pub fn reference_price_lots(&self, ctx: &StrategyContext) -> Option<f64> {
+        let others = |order: &&BookOrder| {
+            !ctx.open_orders
+                .iter()
+                .any(|open| open.order_id == order.order_id)
+        };
+        match self.config.price_source {
+            PriceSource::Mid => {
+                let bid = ctx.bids.iter().find(others)?.price_lots;
+                let ask = ctx.asks.iter().find(others)?.price_lots;
+                Some((bid + ask) as f64 / 2.0)
+            }
+            PriceSource::Oracle => Some(ctx.oracle_price? / ctx.market.price_lots_to_ui(1)),
+        }
+    }
+
+    /// The shift of the ladder caused by the inventory, in basis points.
+    pub fn inventory_skew_bps(&self, ctx: &StrategyContext) -> f64 {
+        let excess = ctx.market.base_native_to_ui(ctx.base_inventory) - self.config.target_base;
+        let max = self.config.max_inventory_skew_bps;
+        (-excess * self.config.inventory_skew_bps).clamp(-max, max)
+    }
+
+    /// The ladder to quote in `ctx`, or `None` without a reference price.
+    pub fn ladder(&self, ctx: &StrategyContext) -> Option<Ladder> {
+        let reference = self.reference_price_lots(ctx)?;
+        let config = &self.config;
+        let center = reference * (1.0 + (config.skew_bps + self.inventory_skew_bps(ctx)) / BPS);
+        let taker_fee_bps = ctx.market.market.taker_fee as f64 / 100.0;
+        let min_distance = (taker_fee_bps + config.min_edge_bps) / BPS;
+
+        let mut ladder = Ladder::default();
+        for level in 0..config.levels {
+            let distance = (config.spread_bps + level as f64 * config.level_spacing_bps) / BPS;
+            let bid = (center * (1.0 - distance)).min(reference * (1.0 - min_distance));
+            let ask = (center * (1.0 + distance)).max(reference * (1.0 + min_distance));
+            if bid >= 1.0 {
+                ladder.bids.push(Quote {
+                    price_lots: floor_tick(bid),
+                    quote_size: config.quote_size,
+                });
+            }
+            ladder.asks.push(Quote {
+                price_lots: ceil_tick(ask),
+                quote_size: config.quote_size,
+            });
+        }
+        Some(ladder)
+    }
+
+    fn refresh(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        let Some(ladder) = self.ladder(ctx) else {
+            self.quoted = None;
+            if ctx.open_orders.is_empty() {
+                return vec![];
+            }
+            return vec![OrderIntent::CancelAll];
+        };
+
+        let resting = *self.resting.get_or_insert(ctx.open_orders.len());
+        let filled = ctx.open_orders.len() < resting;
+        let moved = self.quoted.as_ref().map_or(true, |quoted| {
+            !quoted.is_close_to(&ladder, self.config.requote_bps)
+        });
+        if !filled && !moved {
+            return vec![];
+        }
+
+        self.resting = None;
+        self.quoted = Some(ladder.clone());
+        vec![OrderIntent::Replace {
+            bids: ladder.bids,
+            asks: ladder.asks,
+        }]
+    }
+}
+
+impl Strategy for MarketMaker {
+    fn on_book(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        self.refresh(ctx)
+    }
+
+    fn on_timer(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        self.refresh(ctx)
+    }
+}
+
+/// Rounds a price in lots down, ignoring floating point errors.
+fn floor_tick(price_lots: f64) -> i64 {
+    (price_lots + 1e-9).floor() as i64
+}
+
+/// Rounds a price in lots up, ignoring floating point errors.
+fn ceil_tick(price_lots: f64) -> i64 {
+    (price_lots - 1e-9).ceil() as i64
+}
@@ -0,0 +1,194 @@
+//! Local preview of what a taking order will do, matching it against decoded book sides like the
+//! OpenBook V2 program does.
+//!
+//! The preview walks the opposing side from best to worst price, skipping the orders expired at
+//! the given time, and stops at the limit price, when the base or quote lots of the order are
+//! used up, or after `TakerOrder::limit` matches. The taker fee of a bid is reserved from its
+//! quote lots before matching, and charged on the total quote matched.
+//!
+//! Self-trades are not detected, and pegged orders are only matched if the book sides were
+//! decoded with an oracle price.
+
+use openbook_v2::state::Side;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    book::BookOrder,
+    context::{MarketContext, OrderLots},
+    fees::{self, FeeBreakdown},
+    serde_util,
+};
+
+/// A taking order to preview.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TakerOrder {
+    #[serde(with = "serde_util::side")]
+    pub side: Side,
+
+    /// The limit price, in quote lots per base lot.
+    pub price_lots: i64,
+
+    pub max_base_lots: i64,
+
+    /// The quote lots the order can spend, including the taker fee of a bid.
+    pub max_quote_lots_including_fees: i64,
+
+    /// Maximum number of resting orders to match, like `PlaceOrderArgs::limit`.
+    pub limit: u8,
+}
+
+impl TakerOrder {
+    /// An order of `lots`, as placed by `OBClient::place_order`.
+    pub fn new(side: Side, lots: OrderLots, limit: u8) -> Self {
+        Self {
+            side,
+            price_lots: lots.price_lots,
+            max_base_lots: lots.max_base_lots as i64,
+            max_quote_lots_including_fees: lots.max_quote_lots as i64,
+            limit,
+        }
+    }
+}
+
+/// An expected match against a resting order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreviewFill {
+    pub order_id: u128,
+
+    /// The open orders account owning the resting order.
+    #[serde(with = "serde_util::pubkey")]
+    pub maker: Pubkey,
+    pub maker_slot: u8,
+
+    pub price_lots: i64,
+
+    /// The matched quantity, in base lots.
+    pub quantity: i64,
+}
+
+/// The expected outcome of a taking order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchPreview {
+    pub fills: Vec<PreviewFill>,
+
+    /// Base lots matched.
+    pub base_lots: i64,
+
+    /// Quote lots matched, excluding the taker fee.
+    pub quote_lots: i64,
+
+    /// The taker fee of the matched quote, in native quote units.
+    pub taker_fee_native: i64,
+
+    /// Base lots left unmatched, which rest on the book for a limit order.
+    pub remaining_base_lots: i64,
+
+    /// Quote lots left unmatched, excluding the taker fee reserved for a bid.
+    pub remaining_quote_lots: i64,
+
+    /// Number of expired orders skipped.
+    pub expired_orders: usize,
+
+    /// Whether matching stopped at `TakerOrder::limit` with crossing orders left on the book.
+    pub limit_reached: bool,
+}
+
+impl MatchPreview {
+    /// The average price of the matches, in quote lots per base lot, `None` without a match.
+    pub fn average_price_lots(&self) -> Option<f64> {
+        (self.base_lots > 0).then(|| self.quote_lots as f64 / self.base_lots as f64)
+    }
+
+    /// The average price of the matches, in quote tokens per base token.
+    pub fn average_price(&self, market: &MarketContext) -> Option<f64> {
+        self.average_price_lots()
+            .map(|price_lots| price_lots * market.price_lots_to_ui(1))
+    }
+
+    /// The quote matched, in native quote units, excluding the taker fee.
+    pub fn quote_native(&self, market: &MarketContext) -> u64 {
+        (self.quote_lots * market.market.quote_lot_size) as u64
+    }
+
+    /// The fees of the matches of a `side` order, with or without a referrer.
+    pub fn fees(&self, market: &MarketContext, side: Side, referrer: bool) -> FeeBreakdown {
+        FeeBreakdown::new(&market.market, side, self.quote_native(market), referrer)
+    }
+
+    /// Whether the whole base size of the order was matched.
+    pub fn is_filled(&self) -> bool {
+        self.remaining_base_lots == 0
+    }
+}
+
+/// Matches `order` against `bids` or `asks`, both sorted from best to worst price, at the unix
+/// timestamp `now_ts`.
+pub fn preview_match(
+    market: &MarketContext,
+    bids: &[BookOrder],
+    asks: &[BookOrder],
+    order: &TakerOrder,
+    now_ts: u64,
+) -> MatchPreview {
+    let opposing = match order.side {
+        Side::Bid => asks,
+        Side::Ask => bids,
+    };
+    let mut preview = MatchPreview {
+        remaining_base_lots: order.max_base_lots,
+        remaining_quote_lots: match order.side {
+            Side::Bid => {
+                fees::subtract_taker_fees(&market.market, order.max_quote_lots_including_fees)
+            }
+            Side::Ask => order.max_quote_lots_including_fees,
+        },
+        ..MatchPreview::default()
+    };
+    let mut limit = order.limit;
+    for resting in opposing {
+        if preview.remaining_base_lots == 0 || preview.remaining_quote_lots == 0 {
+            break;
+        }
+        if resting.is_expired(now_ts) {
+            preview.expired_orders += 1;
+            continue;
+        }
+        let within_limit = match order.side {
+            Side::Bid => resting.price_lots <= order.price_lots,
+            Side::Ask => resting.price_lots >= order.price_lots,
+        };
+        if !within_limit {
+            break;
+        }
+        if limit == 0 {
+            preview.limit_reached = true;
+            break;
+        }
+
+        let max_match_by_quote = preview.remaining_quote_lots / resting.price_lots;
+        if max_match_by_quote == 0 {
+            break;
+        }
+        let quantity = preview
+            .remaining_base_lots
+            .min(resting.quantity)
+            .min(max_match_by_quote);
+        let quote_lots = quantity * resting.price_lots;
+        preview.remaining_base_lots -= quantity;
+        preview.remaining_quote_lots -= quote_lots;
+        preview.base_lots += quantity;
+        preview.quote_lots += quote_lots;
+        preview.fills.push(PreviewFill {
+            order_id: resting.order_id,
+            maker: resting.owner,
+            maker_slot: resting.owner_slot,
+            price_lots: resting.price_lots,
+            quantity,
+        });
+        limit -= 1;
+    }
+    preview.taker_fee_native =
+        fees::taker_fee_ceil(&market.market, preview.quote_native(market)) as i64;
+    preview
+}
@@ -1,31 +1,75 @@
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use anchor_lang::{prelude::System, Id};
+use anchor_lang::{prelude::System, AccountDeserialize, Id};
 use anchor_spl::{associated_token::AssociatedToken, token::Token};
 use anyhow::{Context, Error, Result};
 use rand::random;
-use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
 
 use openbook_v2::{
-    state::{Market, OracleConfigParams, PlaceOrderType, SelfTradeBehavior, Side},
-    PlaceOrderArgs,
+    state::{
+        BookSide, EventHeap, Market, OpenOrdersAccount, OpenOrdersIndexer, OracleConfigParams,
+        PlaceOrderType, SelfTradeBehavior, Side, StubOracle,
+    },
+    PlaceMultipleOrdersArgs, PlaceOrderArgs,
 };
 use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::response::RpcSimulateTransactionResult;
 
-use solana_sdk::transaction::Transaction;
+use solana_client::rpc_client::SerializableTransaction;
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
 use solana_sdk::{
-    commitment_config::CommitmentConfig, instruction::Instruction, pubkey::Pubkey,
-    signature::Keypair, signer::Signer,
+    address_lookup_table_account::AddressLookupTableAccount,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    message::{Message, VersionedMessage},
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Signature},
+    signer::{null_signer::NullSigner, Signer},
 };
 
-use crate::{context::MarketContext, rpc::Rpc};
+use crate::{
+    admin::{self, AdminRole, Cosignable},
+    book::{self, BookOrder},
+    config::{ClientConfig, MarketSettings},
+    context::{MarketContext, OrderLots},
+    events, instructions,
+    kill_switch::{self, FlattenedAccount, KillSwitchConfig, KillSwitchReport},
+    lookup_table,
+    matching::{self, MatchPreview, TakerOrder},
+    oracle::{self, OraclePrice},
+    portfolio::{Balances, MarkPrice, MarketPnl, MarketPortfolio},
+    risk::{self, OrderRateLimiter, RiskState},
+    rpc::Rpc,
+    snapshot::{self, MarketSnapshot},
+    strategy::{self, Quote},
+    token,
+};
+
+/// A thread safe signer, e.g. an `Arc<Keypair>` or a client of a remote signing service.
+pub type SharedSigner = Arc<dyn Signer + Send + Sync>;
+
+/// Maximum number of resting orders matched by an order placed by the client.
+const MATCH_LIMIT: u8 = 12;
 
 /// OpenBook v2 Client to interact with the OpenBook market and perform actions.
 #[derive(Clone)]
 pub struct OBClient {
-    /// The keypair of the owner used for signing transactions related to the market.
-    pub owner: Arc<Keypair>,
+    /// The signer of the owner used for signing transactions related to the market.
+    pub owner: SharedSigner,
+
+    /// The signer paying transaction fees and rent of created accounts. Defaults to the owner.
+    pub payer: SharedSigner,
+
+    /// The delegate signing orders and cancels on behalf of the owner, when in delegate mode.
+    pub delegate: Option<SharedSigner>,
+
+    /// The open orders admin of a permissioned market, co-signing the orders of the client.
+    /// Without it, orders on a permissioned market are built partially signed, for the admin
+    /// service to sign separately with `admin::sign_open_orders`.
+    pub open_orders_admin: Option<SharedSigner>,
 
     /// The RPC client for interacting with the Solana blockchain.
     pub rpc_client: Rpc,
@@ -47,6 +91,20 @@ pub struct OBClient {
 
     /// Context information for the market.
     pub context: MarketContext,
+
+    /// Trading settings of the market, e.g. the order type and TTL of limit orders.
+    pub settings: MarketSettings,
+
+    /// Address lookup tables used to compile versioned transactions.
+    pub lookup_tables: Vec<AddressLookupTableAccount>,
+
+    /// The orders placed recently, for the rate limit of the market's `RiskLimits`. Shared by the
+    /// clones of the client.
+    pub rate_limiter: OrderRateLimiter,
+
+    /// The wallet receiving the referrer rebates accrued by the owner's taker orders, paid to its
+    /// quote token account when settling. Without a referrer, the rebates go to the market.
+    pub referrer: Option<Pubkey>,
 }
 
 impl OBClient {
@@ -103,43 +161,175 @@ impl OBClient {
     ///
     pub async fn new(
         rpc_url: String,
-        owner: Arc<Keypair>,
+        owner: SharedSigner,
         open_orders_account: Option<Pubkey>,
         commitment: CommitmentConfig,
         market_id: Pubkey,
+    ) -> Result<Self, Error> {
+        let mut ob_client =
+            Self::new_without_open_orders_account(rpc_url, owner, commitment, market_id).await?;
+        ob_client.open_orders_account = match open_orders_account {
+            Some(open_orders_account) => open_orders_account,
+            None => ob_client.find_or_create_account().await?,
+        };
+        Ok(ob_client)
+    }
+
+    /// Initializes an `OBClient` reading and cranking the market, and flattening the owner's
+    /// accounts with `kill_switch`, without looking up or creating an open orders account.
+    ///
+    /// `open_orders_account` is left as the default pubkey, so the operations of the client's
+    /// open orders account, like placing orders, can not be used.
+    pub async fn new_without_open_orders_account(
+        rpc_url: String,
+        owner: SharedSigner,
+        commitment: CommitmentConfig,
+        market_id: Pubkey,
     ) -> Result<Self, Error> {
         let pub_owner_key = owner.pubkey();
         let rpc_client = Rpc::new(RpcClient::new_with_commitment(rpc_url.clone(), commitment));
         let market_info = rpc_client
             .fetch_anchor_account::<Market>(&market_id)
             .await?;
-        let base_ata = get_associated_token_address(&pub_owner_key.clone(), &market_info.base_mint);
-        let quote_ata =
-            get_associated_token_address(&pub_owner_key.clone(), &market_info.quote_mint);
+        let [base_mint_info, quote_mint_info] = rpc_client
+            .fetch_mint_infos(&[market_info.base_mint, market_info.quote_mint])
+            .await?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Expected the base and quote mint infos"))?;
+        let base_ata = get_associated_token_address_with_program_id(
+            &pub_owner_key,
+            &market_info.base_mint,
+            &base_mint_info.token_program,
+        );
+        let quote_ata = get_associated_token_address_with_program_id(
+            &pub_owner_key,
+            &market_info.quote_mint,
+            &quote_mint_info.token_program,
+        );
 
         let context = MarketContext {
             market: market_info,
             address: market_id,
+            base_mint_info,
+            quote_mint_info,
         };
 
-        let mut ob_client = Self {
+        Ok(Self {
             rpc_client,
             market_info,
+            payer: owner.clone(),
             owner,
+            delegate: None,
+            open_orders_admin: None,
             quote_ata,
             base_ata,
             market_id,
-            open_orders_account: open_orders_account.unwrap_or_default(),
+            open_orders_account: Pubkey::default(),
             context,
+            settings: MarketSettings::default(),
+            lookup_tables: vec![],
+            rate_limiter: OrderRateLimiter::default(),
+            referrer: None,
+        })
+    }
+
+    /// Initializes an `OBClient` from a resolved configuration profile.
+    ///
+    /// Reads the owner's keypair from `config.keypair_path`, and applies the market settings of
+    /// the profile. Fails if the profile has no market.
+    ///
+    /// # Example
+    ///
+    /// ```rust , ignore
+    /// use openbook::config::Config;
+    /// use openbook::ob_client::OBClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let config = Config::load_default()?.resolve(Some("mainnet"))?;
+    ///
+    ///     let ob_client = OBClient::from_config(&config).await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn from_config(config: &ClientConfig) -> Result<Self, Error> {
+        let mut ob_client = Self::from_config_without_open_orders_account(config).await?;
+        ob_client.open_orders_account = match config.open_orders_account {
+            Some(open_orders_account) => open_orders_account,
+            None => ob_client.find_or_create_account().await?,
         };
+        Ok(ob_client)
+    }
 
-        if open_orders_account.is_none() {
-            ob_client.open_orders_account = ob_client.find_or_create_account().await?;
-        }
+    /// Initializes an `OBClient` from a resolved configuration profile, ignoring its open orders
+    /// account, like `new_without_open_orders_account`.
+    pub async fn from_config_without_open_orders_account(
+        config: &ClientConfig,
+    ) -> Result<Self, Error> {
+        let owner = read_keypair_file(&config.keypair_path).map_err(|err| {
+            anyhow::anyhow!("Failed to read keypair {}: {err}", config.keypair_path)
+        })?;
+        let market_id = config
+            .market
+            .ok_or_else(|| anyhow::anyhow!("No market configured"))?;
+
+        let mut ob_client = Self::new_without_open_orders_account(
+            config.rpc_url.clone(),
+            Arc::new(owner),
+            config.commitment,
+            market_id,
+        )
+        .await?;
+        ob_client.settings = config.market_settings();
+        ob_client.referrer = config.referrer;
 
         Ok(ob_client)
     }
 
+    /// Initializes an `OBClient` in delegate mode, trading the open orders account of a cold `owner`.
+    ///
+    /// Orders and cancels are signed by the hot `delegate`, which also pays for transactions, while
+    /// owner-only operations (e.g. deposits or `set_delegate`) are built with the owner's signature
+    /// missing, to be completed offline with `Transaction::partial_sign`.
+    ///
+    /// Fails if `delegate` is not the delegate of `open_orders_account`.
+    pub async fn new_delegate(
+        rpc_url: String,
+        owner: Pubkey,
+        delegate: SharedSigner,
+        open_orders_account: Pubkey,
+        commitment: CommitmentConfig,
+        market_id: Pubkey,
+    ) -> Result<Self, Error> {
+        let ob_client = Self::new(
+            rpc_url,
+            Arc::new(NullSigner::new(&owner)),
+            Some(open_orders_account),
+            commitment,
+            market_id,
+        )
+        .await?;
+
+        let account = ob_client
+            .rpc_client
+            .fetch_anchor_account::<OpenOrdersAccount>(&open_orders_account)
+            .await?;
+        anyhow::ensure!(
+            account.owner == owner,
+            "open orders account {open_orders_account} is not owned by {owner}"
+        );
+        anyhow::ensure!(
+            Option::<Pubkey>::from(account.delegate) == Some(delegate.pubkey()),
+            "{} is not the delegate of open orders account {open_orders_account}",
+            delegate.pubkey()
+        );
+
+        Ok(ob_client
+            .with_payer(delegate.clone())
+            .with_delegate(delegate))
+    }
+
     /// # Example
     ///
     /// ```rust , ignore
@@ -168,59 +358,119 @@ impl OBClient {
         quote_size: u64,
         side: Side,
     ) -> Result<Transaction> {
-        let current_time = get_unix_secs();
-        let price_lots = self.native_price_to_lots_price(limit_price);
-        let max_quote_lots = self
-            .context
-            .max_quote_lots_including_maker_fees_from_usd(quote_size);
-        let base_size = self.get_base_size_from_quote(quote_size, limit_price);
-        let max_base_lots = self.context.max_base_lots_from_usd(base_size);
-        let ata = match side {
-            Side::Bid => self.quote_ata,
-            Side::Ask => self.base_ata,
-        };
-        let vault = self.market_info.get_vault_by_side(side);
+        self.place_limit_order_with_client_id(limit_price, quote_size, side, random::<u64>())
+            .await
+    }
+
+    /// Places a limit order like `place_limit_order`, identified by `client_order_id` instead of
+    /// a random one, e.g. to find it in the open orders account later.
+    pub async fn place_limit_order_with_client_id(
+        &mut self,
+        limit_price: f64,
+        quote_size: u64,
+        side: Side,
+        client_order_id: u64,
+    ) -> Result<Transaction> {
+        self.check_max_size(quote_size)?;
+        let lots = self.context.order_lots(side, limit_price, quote_size);
+        self.place_order(side, lots, self.settings.order_type(), client_order_id)
+            .await
+    }
 
+    /// Places an order with explicit lots and order type, expiring after the TTL of the market
+    /// settings.
+    ///
+    /// Taking orders fill against the book immediately, crediting the open orders account, and
+    /// orders which can rest are placed on the book.
+    pub async fn place_order(
+        &self,
+        side: Side,
+        lots: OrderLots,
+        order_type: PlaceOrderType,
+        client_order_id: u64,
+    ) -> Result<Transaction> {
+        self.check_risk(&[(side, lots)], false).await?;
+        let OrderLots {
+            max_base_lots,
+            max_quote_lots,
+            ..
+        } = lots;
         tracing::debug!("base: {max_base_lots}, quote: {max_quote_lots}");
-        let oid = random::<u64>();
 
-        let ix = Instruction {
-            program_id: openbook_v2::id(),
-            accounts: {
-                anchor_lang::ToAccountMetas::to_account_metas(
-                    &openbook_v2::accounts::PlaceOrder {
-                        open_orders_account: self.open_orders_account,
-                        open_orders_admin: None,
-                        signer: self.owner(),
-                        market: self.market_id,
-                        bids: self.market_info.bids,
-                        asks: self.market_info.asks,
-                        event_heap: self.market_info.event_heap,
-                        oracle_a: self.market_info.oracle_a.into(),
-                        oracle_b: self.market_info.oracle_b.into(),
-                        user_token_account: ata,
-                        market_vault: vault,
-                        token_program: Token::id(),
-                    },
-                    None,
-                )
-            },
-            data: anchor_lang::InstructionData::data(&openbook_v2::instruction::PlaceOrder {
-                args: PlaceOrderArgs {
-                    side,
-                    price_lots,
-                    max_base_lots: max_base_lots as i64,
-                    max_quote_lots_including_fees: max_quote_lots as i64,
-                    client_order_id: oid,
-                    order_type: PlaceOrderType::PostOnly,
-                    expiry_timestamp: current_time + 86_400,
-                    self_trade_behavior: SelfTradeBehavior::AbortTransaction,
-                    limit: 12,
-                },
-            }),
+        let expiry_timestamp = get_unix_secs() + self.settings.ttl_secs();
+        let ix = self.place_order_ix(side, lots, order_type, client_order_id, expiry_timestamp);
+
+        let funding = match side {
+            Side::Bid => max_quote_lots * self.market_info.quote_lot_size as u64,
+            Side::Ask => max_base_lots * self.market_info.base_lot_size as u64,
         };
+        let mut ixs = self.prepare_order_funding(side, funding).await?;
+        ixs.push(ix);
+        self.to_order_trx(ixs, 1).await
+    }
 
-        self.to_trx(vec![ix]).await
+    /// Builds the `PlaceOrder` instruction of `place_order`, transferring with the token program
+    /// of the side's mint.
+    fn place_order_ix(
+        &self,
+        side: Side,
+        lots: OrderLots,
+        order_type: PlaceOrderType,
+        client_order_id: u64,
+        expiry_timestamp: u64,
+    ) -> Instruction {
+        instructions::place_order(
+            openbook_v2::accounts::PlaceOrder {
+                open_orders_account: self.open_orders_account,
+                open_orders_admin: self.open_orders_admin_key(),
+                signer: self.signer(),
+                market: self.market_id,
+                bids: self.market_info.bids,
+                asks: self.market_info.asks,
+                event_heap: self.market_info.event_heap,
+                oracle_a: self.market_info.oracle_a.into(),
+                oracle_b: self.market_info.oracle_b.into(),
+                user_token_account: self.signer_token_account(side),
+                market_vault: self.market_info.get_vault_by_side(side),
+                token_program: self.side_token_program(side),
+            },
+            PlaceOrderArgs {
+                side,
+                price_lots: lots.price_lots,
+                max_base_lots: lots.max_base_lots as i64,
+                max_quote_lots_including_fees: lots.max_quote_lots as i64,
+                client_order_id,
+                order_type,
+                expiry_timestamp,
+                self_trade_behavior: SelfTradeBehavior::AbortTransaction,
+                limit: MATCH_LIMIT,
+            },
+        )
+    }
+
+    /// Checks `quote_size` against the maximum order size of the market settings.
+    fn check_max_size(&self, quote_size: u64) -> Result<()> {
+        if let Some(max_size) = self.settings.max_size {
+            anyhow::ensure!(
+                quote_size <= max_size,
+                "Order size {quote_size} exceeds the maximum size {max_size} of market {}",
+                self.market_id
+            );
+        }
+        Ok(())
+    }
+
+    /// Previews what an order of `lots` placed by `place_order` would match against the current
+    /// book, without sending anything.
+    pub async fn preview_order(&self, side: Side, lots: OrderLots) -> Result<MatchPreview> {
+        let snapshot = self.snapshot().await?;
+        Ok(matching::preview_match(
+            &self.context,
+            &snapshot.bids.state,
+            &snapshot.asks.state,
+            &TakerOrder::new(side, lots, MATCH_LIMIT),
+            get_unix_secs(),
+        ))
     }
 
     pub async fn place_market_order(
@@ -229,60 +479,175 @@ impl OBClient {
         quote_size: u64,
         side: Side,
     ) -> Result<Transaction> {
+        self.check_max_size(quote_size)?;
         let current_time = get_unix_secs();
-        let price_lots = self.native_price_to_lots_price(limit_price);
-        let max_quote_lots = self
-            .context
-            .max_quote_lots_including_maker_fees_from_usd(quote_size);
-        let base_size = self.get_base_size_from_quote(quote_size, limit_price);
-        let max_base_lots = self.context.max_base_lots_from_usd(base_size);
-        let ata = match side {
-            Side::Bid => self.quote_ata,
-            Side::Ask => self.base_ata,
-        };
+        let lots = self.context.order_lots(side, limit_price, quote_size);
+        self.check_risk(&[(side, lots)], false).await?;
+        let OrderLots {
+            price_lots,
+            max_base_lots,
+            max_quote_lots,
+        } = lots;
+        let ata = self.signer_token_account(side);
         let vault = self.market_info.get_vault_by_side(side);
 
         tracing::debug!("base: {max_base_lots}, quote: {max_quote_lots}");
         let oid = random::<u64>();
 
         // TODO: update to market order inst
-        let ix = Instruction {
-            program_id: openbook_v2::id(),
-            accounts: {
-                anchor_lang::ToAccountMetas::to_account_metas(
-                    &openbook_v2::accounts::PlaceOrder {
-                        open_orders_account: self.open_orders_account,
-                        open_orders_admin: None,
-                        signer: self.owner(),
-                        market: self.market_id,
-                        bids: self.market_info.bids,
-                        asks: self.market_info.asks,
-                        event_heap: self.market_info.event_heap,
-                        oracle_a: self.market_info.oracle_a.into(),
-                        oracle_b: self.market_info.oracle_b.into(),
-                        user_token_account: ata,
-                        market_vault: vault,
-                        token_program: Token::id(),
-                    },
-                    None,
-                )
+        let ix = instructions::place_order(
+            openbook_v2::accounts::PlaceOrder {
+                open_orders_account: self.open_orders_account,
+                open_orders_admin: self.open_orders_admin_key(),
+                signer: self.signer(),
+                market: self.market_id,
+                bids: self.market_info.bids,
+                asks: self.market_info.asks,
+                event_heap: self.market_info.event_heap,
+                oracle_a: self.market_info.oracle_a.into(),
+                oracle_b: self.market_info.oracle_b.into(),
+                user_token_account: ata,
+                market_vault: vault,
+                token_program: self.side_token_program(side),
+            },
+            PlaceOrderArgs {
+                side,
+                price_lots,
+                max_base_lots: max_base_lots as i64,
+                max_quote_lots_including_fees: max_quote_lots as i64,
+                client_order_id: oid,
+                order_type: PlaceOrderType::PostOnly,
+                expiry_timestamp: current_time + 86_400,
+                self_trade_behavior: SelfTradeBehavior::AbortTransaction,
+                limit: MATCH_LIMIT,
             },
-            data: anchor_lang::InstructionData::data(&openbook_v2::instruction::PlaceOrder {
-                args: PlaceOrderArgs {
-                    side,
-                    price_lots,
-                    max_base_lots: max_base_lots as i64,
-                    max_quote_lots_including_fees: max_quote_lots as i64,
-                    client_order_id: oid,
-                    order_type: PlaceOrderType::PostOnly,
-                    expiry_timestamp: current_time + 86_400,
-                    self_trade_behavior: SelfTradeBehavior::AbortTransaction,
-                    limit: 12,
+        );
+
+        let funding = match side {
+            Side::Bid => max_quote_lots * self.market_info.quote_lot_size as u64,
+            Side::Ask => max_base_lots * self.market_info.base_lot_size as u64,
+        };
+        let mut ixs = self.prepare_order_funding(side, funding).await?;
+        ixs.push(ix);
+        self.to_order_trx(ixs, 1).await
+    }
+
+    /// Cancels all orders and places `bids` and `asks` in a single transaction, with the order
+    /// type and TTL of the market settings.
+    ///
+    /// The program assigns the orders client order ids by position, as `strategy::replace_orders`
+    /// lists them. Post-only quotes crossing the book are dropped without failing the
+    /// transaction.
+    ///
+    /// On a market whose base and quote mints use different token programs, the orders are
+    /// cancelled and the quotes placed with one `PlaceOrder` each, with the same client order ids.
+    pub async fn cancel_all_and_place_orders(
+        &self,
+        bids: &[Quote],
+        asks: &[Quote],
+    ) -> Result<Transaction> {
+        for quote in bids.iter().chain(asks) {
+            self.check_max_size(quote.quote_size)?;
+        }
+        let expiry_timestamp = get_unix_secs() + self.settings.ttl_secs();
+        let mut quote_funding = 0;
+        let mut base_funding = 0;
+        let mut bid_args = vec![];
+        let mut ask_args = vec![];
+        let mut orders = vec![];
+        let quotes = bids
+            .iter()
+            .map(|quote| (Side::Bid, quote))
+            .chain(asks.iter().map(|quote| (Side::Ask, quote)));
+        for (side, quote) in quotes {
+            let lots =
+                self.context
+                    .order_lots_from_price_lots(side, quote.price_lots, quote.quote_size);
+            let args = PlaceMultipleOrdersArgs {
+                price_lots: lots.price_lots,
+                max_quote_lots_including_fees: lots.max_quote_lots as i64,
+                expiry_timestamp,
+            };
+            match side {
+                Side::Bid => {
+                    quote_funding += lots.max_quote_lots * self.market_info.quote_lot_size as u64;
+                    bid_args.push(args);
+                }
+                Side::Ask => {
+                    base_funding += lots.max_base_lots * self.market_info.base_lot_size as u64;
+                    ask_args.push(args);
+                }
+            }
+            orders.push((side, lots));
+        }
+
+        self.check_risk(&orders, true).await?;
+
+        let base_token_program = self.context.base_mint_info.token_program;
+        let quote_token_program = self.context.quote_mint_info.token_program;
+        let place_ixs = if base_token_program == quote_token_program {
+            vec![instructions::cancel_all_and_place_orders(
+                openbook_v2::accounts::CancelAllAndPlaceOrders {
+                    signer: self.signer(),
+                    open_orders_account: self.open_orders_account,
+                    open_orders_admin: self.open_orders_admin_key(),
+                    user_quote_account: self.signer_token_account(Side::Bid),
+                    user_base_account: self.signer_token_account(Side::Ask),
+                    market: self.market_id,
+                    bids: self.market_info.bids,
+                    asks: self.market_info.asks,
+                    event_heap: self.market_info.event_heap,
+                    market_quote_vault: self.market_info.market_quote_vault,
+                    market_base_vault: self.market_info.market_base_vault,
+                    oracle_a: self.market_info.oracle_a.into(),
+                    oracle_b: self.market_info.oracle_b.into(),
+                    token_program: base_token_program,
                 },
-            }),
+                self.settings.order_type(),
+                bid_args,
+                ask_args,
+                MATCH_LIMIT,
+            )]
+        } else {
+            // `CancelAllAndPlaceOrders` transfers both tokens with a single token program, so the
+            // quotes of a market mixing Token and Token-2022 mints are placed one by one with the
+            // program of their side, with the client order ids the program would assign.
+            let mut ixs = vec![instructions::cancel_all_orders(
+                openbook_v2::accounts::CancelOrder {
+                    open_orders_account: self.open_orders_account,
+                    signer: self.signer(),
+                    market: self.market_id,
+                    bids: self.market_info.bids,
+                    asks: self.market_info.asks,
+                },
+                None,
+                255,
+            )];
+            ixs.extend(strategy::replace_orders(bids, asks).zip(&orders).map(
+                |((client_order_id, ..), (side, lots))| {
+                    self.place_order_ix(
+                        *side,
+                        *lots,
+                        self.settings.order_type(),
+                        client_order_id,
+                        expiry_timestamp,
+                    )
+                },
+            ));
+            ixs
         };
 
-        self.to_trx(vec![ix]).await
+        let mut ixs = self
+            .prepare_token_accounts(
+                &self.signer(),
+                &[
+                    (self.market_info.quote_mint, quote_funding),
+                    (self.market_info.base_mint, base_funding),
+                ],
+            )
+            .await?;
+        ixs.extend(place_ixs);
+        self.to_order_trx(ixs, orders.len()).await
     }
 
     /// # Example
@@ -307,24 +672,16 @@ impl OBClient {
     /// }
     /// ```
     pub async fn cancel_limit_order(&self, order_id: u128) -> Result<Transaction> {
-        let ix = Instruction {
-            program_id: openbook_v2::id(),
-            accounts: {
-                anchor_lang::ToAccountMetas::to_account_metas(
-                    &openbook_v2::accounts::CancelOrder {
-                        open_orders_account: self.open_orders_account,
-                        signer: self.owner(),
-                        market: self.market_id,
-                        bids: self.market_info.bids,
-                        asks: self.market_info.asks,
-                    },
-                    None,
-                )
+        let ix = instructions::cancel_order(
+            openbook_v2::accounts::CancelOrder {
+                open_orders_account: self.open_orders_account,
+                signer: self.signer(),
+                market: self.market_id,
+                bids: self.market_info.bids,
+                asks: self.market_info.asks,
             },
-            data: anchor_lang::InstructionData::data(&openbook_v2::instruction::CancelOrder {
-                order_id,
-            }),
-        };
+            order_id,
+        );
 
         self.to_trx(vec![ix]).await
     }
@@ -351,27 +708,365 @@ impl OBClient {
     /// }
     /// ```
     pub async fn cancel_all(&self) -> Result<Transaction> {
-        let ix = Instruction {
-            program_id: openbook_v2::id(),
-            accounts: {
-                anchor_lang::ToAccountMetas::to_account_metas(
-                    &openbook_v2::accounts::CancelOrder {
-                        open_orders_account: self.open_orders_account,
-                        signer: self.owner(),
-                        market: self.market_id,
-                        bids: self.market_info.bids,
-                        asks: self.market_info.asks,
+        let ix = instructions::cancel_all_orders(
+            openbook_v2::accounts::CancelOrder {
+                open_orders_account: self.open_orders_account,
+                signer: self.signer(),
+                market: self.market_id,
+                bids: self.market_info.bids,
+                asks: self.market_info.asks,
+            },
+            None,
+            255,
+        );
+
+        self.to_trx(vec![ix]).await
+    }
+
+    /// Settles the free balances of the open orders account to the owner's token accounts.
+    ///
+    /// With a `referrer`, its accrued rebates are paid to the referrer's quote token account,
+    /// created if missing.
+    pub async fn settle_funds(&self) -> Result<Transaction> {
+        let (referrer_account, create_referrer_account) = self
+            .referrer_token_account(
+                &self.market_info.quote_mint,
+                &self.context.quote_mint_info.token_program,
+            )
+            .unzip();
+        let ix = instructions::settle_funds(openbook_v2::accounts::SettleFunds {
+            owner: self.signer(),
+            penalty_payer: self.payer.pubkey(),
+            open_orders_account: self.open_orders_account,
+            market: self.market_id,
+            market_authority: self.market_info.market_authority,
+            market_base_vault: self.market_info.market_base_vault,
+            market_quote_vault: self.market_info.market_quote_vault,
+            user_base_account: self.base_ata,
+            user_quote_account: self.quote_ata,
+            referrer_account,
+            token_program: self.common_token_program()?,
+            system_program: System::id(),
+        });
+
+        let owner = self.owner();
+        let mut ixs = self
+            .prepare_token_accounts(
+                &owner,
+                &[
+                    (self.market_info.base_mint, 0),
+                    (self.market_info.quote_mint, 0),
+                ],
+            )
+            .await?;
+        ixs.extend(create_referrer_account);
+        ixs.push(ix);
+        if self.delegate.is_none() {
+            ixs.extend(self.unwrap_sol_instructions()?);
+        }
+        self.to_trx(ixs).await
+    }
+
+    /// Cancels all orders of every open orders account of the owner, on any market, and settles
+    /// their funds to the owner's token accounts, for use during incidents. With
+    /// `config.close_accounts`, the flat accounts are then closed.
+    ///
+    /// Each round sends one transaction per account: accounts not flat yet get their pending
+    /// events cranked, their orders cancelled and their balances settled, and flat accounts are
+    /// closed. Failed transactions are logged, and rounds are repeated until the accounts are
+    /// verified flat, or closed, failing after `config.max_attempts` rounds.
+    ///
+    /// Accounts on markets whose base and quote mints use different token programs can not be
+    /// settled: their orders are cancelled, and they are reported as `unsettled` rather than
+    /// flat.
+    ///
+    /// In delegate mode, only the accounts delegated to the client are flattened, and they can
+    /// not be closed.
+    pub async fn kill_switch(&self, config: KillSwitchConfig) -> Result<KillSwitchReport> {
+        anyhow::ensure!(
+            !config.close_accounts || self.delegate.is_none(),
+            "Open orders accounts can only be closed by their owner"
+        );
+        let mut report = KillSwitchReport::default();
+        loop {
+            let mut accounts = self.load_open_orders_accounts().await?;
+            if let Some(delegate) = &self.delegate {
+                accounts.retain(|(_, account)| {
+                    Option::<Pubkey>::from(account.delegate) == Some(delegate.pubkey())
+                });
+            }
+            for (address, account) in &accounts {
+                if report
+                    .accounts
+                    .iter()
+                    .all(|found| found.address != *address)
+                {
+                    report.accounts.push(FlattenedAccount {
+                        address: *address,
+                        market: account.market,
+                        open_orders: account.all_orders_in_use().count(),
+                        closed: false,
+                        unsettled: false,
+                    });
+                }
+            }
+            for found in &mut report.accounts {
+                found.closed = accounts
+                    .iter()
+                    .all(|(address, _)| *address != found.address);
+            }
+
+            accounts.retain(|(address, account)| {
+                let unsettled = report
+                    .accounts
+                    .iter()
+                    .any(|found| found.address == *address && found.unsettled);
+                if unsettled {
+                    account.all_orders_in_use().next().is_some()
+                } else {
+                    config.close_accounts || !kill_switch::is_flat(account)
+                }
+            });
+            if accounts.is_empty() {
+                return Ok(report);
+            }
+            anyhow::ensure!(
+                report.attempts < config.max_attempts,
+                "{} open orders accounts are still not flattened after {} attempts",
+                accounts.len(),
+                report.attempts
+            );
+            report.attempts += 1;
+
+            for (address, account) in &accounts {
+                let sent = match self.kill_switch_trx(address, account).await {
+                    Ok((trx, unsettled)) => {
+                        if unsettled {
+                            tracing::warn!(
+                                "Open orders account {address} can not be settled, its market \
+                                 mixes token programs"
+                            );
+                            for found in &mut report.accounts {
+                                found.unsettled |= found.address == *address;
+                            }
+                        }
+                        self.send_trx(&trx).await
+                    }
+                    Err(err) => Err(err),
+                };
+                match sent {
+                    Ok(signature) => report.signatures.push(signature),
+                    Err(err) => {
+                        tracing::warn!("Failed to flatten open orders account {address}: {err:#}")
+                    }
+                }
+            }
+            tokio::time::sleep(config.retry_delay).await;
+        }
+    }
+
+    /// Builds the kill switch transaction of the open orders account at `address`: closing it
+    /// if flat, and cranking, cancelling and settling it otherwise. Also returns whether the
+    /// settlement was left out, because the mints of the market use different token programs.
+    async fn kill_switch_trx(
+        &self,
+        address: &Pubkey,
+        account: &OpenOrdersAccount,
+    ) -> Result<(Transaction, bool)> {
+        let owner = self.owner();
+        if kill_switch::is_flat(account) {
+            let ix = instructions::close_open_orders_account(
+                openbook_v2::accounts::CloseOpenOrdersAccount {
+                    payer: self.payer.pubkey(),
+                    owner,
+                    open_orders_indexer: instructions::open_orders_indexer(&owner),
+                    open_orders_account: *address,
+                    sol_destination: owner,
+                    system_program: System::id(),
+                },
+            );
+            return Ok((self.to_trx(vec![ix]).await?, false));
+        }
+
+        let market = self
+            .rpc_client
+            .fetch_anchor_account::<Market>(&account.market)
+            .await?;
+        let [base_mint_info, quote_mint_info] = self
+            .rpc_client
+            .fetch_mint_infos(&[market.base_mint, market.quote_mint])
+            .await?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Expected the base and quote mint infos"))?;
+        let token_program = base_mint_info.token_program;
+        let unsettled = token_program != quote_mint_info.token_program;
+
+        let mut ixs = vec![];
+        if Option::<Pubkey>::from(market.consume_events_admin).is_none() {
+            let event_heap = self
+                .rpc_client
+                .fetch_anchor_account::<EventHeap>(&market.event_heap)
+                .await?;
+            let slots = events::pending_event_slots(&event_heap, address);
+            if !slots.is_empty() {
+                ixs.push(instructions::consume_given_events(
+                    openbook_v2::accounts::ConsumeEvents {
+                        consume_events_admin: None,
+                        market: account.market,
+                        event_heap: market.event_heap,
                     },
-                    None,
-                )
+                    &[*address],
+                    slots,
+                ));
+            }
+        }
+        // Unsettled accounts are only cancelled, even without orders, so the transaction is not
+        // empty.
+        if unsettled || account.all_orders_in_use().next().is_some() {
+            ixs.push(instructions::cancel_all_orders(
+                openbook_v2::accounts::CancelOrder {
+                    open_orders_account: *address,
+                    signer: self.signer(),
+                    market: account.market,
+                    bids: market.bids,
+                    asks: market.asks,
+                },
+                None,
+                255,
+            ));
+        }
+        if unsettled {
+            return Ok((self.to_trx(ixs).await?, true));
+        }
+
+        let [user_base_account, user_quote_account] =
+            [market.base_mint, market.quote_mint].map(|mint| {
+                ixs.push(token::create_ata_idempotent(
+                    &self.payer.pubkey(),
+                    &owner,
+                    &mint,
+                    &token_program,
+                ));
+                get_associated_token_address_with_program_id(&owner, &mint, &token_program)
+            });
+        let (referrer_account, create_referrer_account) = self
+            .referrer_token_account(&market.quote_mint, &token_program)
+            .unzip();
+        ixs.extend(create_referrer_account);
+        ixs.push(instructions::settle_funds(
+            openbook_v2::accounts::SettleFunds {
+                owner: self.signer(),
+                penalty_payer: self.payer.pubkey(),
+                open_orders_account: *address,
+                market: account.market,
+                market_authority: market.market_authority,
+                market_base_vault: market.market_base_vault,
+                market_quote_vault: market.market_quote_vault,
+                user_base_account,
+                user_quote_account,
+                referrer_account,
+                token_program,
+                system_program: System::id(),
             },
-            data: anchor_lang::InstructionData::data(&openbook_v2::instruction::CancelAllOrders {
-                side_option: None,
-                limit: 255,
-            }),
+        ));
+        if self.delegate.is_none()
+            && (token::is_native_mint(&market.base_mint)
+                || token::is_native_mint(&market.quote_mint))
+        {
+            ixs.push(token::unwrap_sol(&owner, &Token::id())?);
+        }
+        Ok((self.to_trx(ixs).await?, false))
+    }
+
+    /// Builds the instructions creating the missing associated token accounts of `owner` for the
+    /// given `(mint, native_amount)` pairs, and wrapping SOL so the wrapped SOL account holds at
+    /// least its amount.
+    ///
+    /// Missing accounts are paid for by the payer and created idempotently, so concurrent
+    /// transactions creating the same account do not fail.
+    pub async fn prepare_token_accounts(
+        &self,
+        owner: &Pubkey,
+        requirements: &[(Pubkey, u64)],
+    ) -> Result<Vec<Instruction>> {
+        let atas: Vec<Pubkey> = requirements
+            .iter()
+            .map(|(mint, _)| {
+                get_associated_token_address_with_program_id(owner, mint, &self.token_program(mint))
+            })
+            .collect();
+        let accounts = self.rpc_client.inner().get_multiple_accounts(&atas).await?;
+
+        let mut ixs = vec![];
+        for ((mint, amount), account) in requirements.iter().zip(accounts) {
+            if account.is_none() {
+                ixs.push(token::create_ata_idempotent(
+                    &self.payer.pubkey(),
+                    owner,
+                    mint,
+                    &self.token_program(mint),
+                ));
+            }
+            if token::is_native_mint(mint) {
+                let balance = account
+                    .and_then(|account| token::token_account_amount(&account.data))
+                    .unwrap_or_default();
+                if *amount > balance {
+                    ixs.extend(token::wrap_sol(owner, amount - balance, &Token::id())?);
+                }
+            }
+        }
+        Ok(ixs)
+    }
+
+    /// Builds the instructions ensuring the order signer's token account funding orders on `side`
+    /// exists, and holds `amount` if it is wrapped SOL.
+    async fn prepare_order_funding(&self, side: Side, amount: u64) -> Result<Vec<Instruction>> {
+        let mint = match side {
+            Side::Bid => self.market_info.quote_mint,
+            Side::Ask => self.market_info.base_mint,
         };
+        self.prepare_token_accounts(&self.signer(), &[(mint, amount)])
+            .await
+    }
 
-        self.to_trx(vec![ix]).await
+    /// Builds the instructions unwrapping the owner's wrapped SOL, if the market trades it.
+    pub fn unwrap_sol_instructions(&self) -> Result<Vec<Instruction>> {
+        if token::is_native_mint(&self.market_info.base_mint)
+            || token::is_native_mint(&self.market_info.quote_mint)
+        {
+            Ok(vec![token::unwrap_sol(&self.owner(), &Token::id())?])
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    /// Cranks the market, processing up to `limit` pending events of the event heap.
+    ///
+    /// The open orders accounts referenced by the events are read from the event heap and passed
+    /// as remaining accounts. Returns `None` if there are no pending events.
+    pub async fn consume_events(&self, limit: usize) -> Result<Option<Transaction>> {
+        let event_heap = self.load_event_heap().await?;
+        let open_orders_accounts = events::pending_open_orders_accounts(&event_heap, limit);
+        if open_orders_accounts.is_empty() {
+            return Ok(None);
+        }
+
+        let ix = if Option::<Pubkey>::from(self.market_info.consume_events_admin).is_some() {
+            admin::consume_events(&self.context, &self.owner(), &open_orders_accounts, limit)?
+        } else {
+            instructions::consume_events(
+                openbook_v2::accounts::ConsumeEvents {
+                    consume_events_admin: None,
+                    market: self.market_id,
+                    event_heap: self.market_info.event_heap,
+                },
+                &open_orders_accounts,
+                limit,
+            )
+        };
+
+        self.to_trx(vec![ix]).await.map(Some)
     }
 
     /// # Example
@@ -396,40 +1091,30 @@ impl OBClient {
     /// }
     /// ```
     pub async fn find_or_create_account(&self) -> Result<Pubkey> {
-        let program = openbook_v2::id();
-
         let openbook_account_name = "random";
 
-        let mut openbook_account_tuples = self
-            .rpc_client
-            .fetch_openbook_accounts(program, self.owner())
-            .await?;
-        let openbook_account_opt = openbook_account_tuples
-            .iter()
-            .find(|(_, account)| account.name() == openbook_account_name);
-        if openbook_account_opt.is_none() {
-            openbook_account_tuples
-                .sort_by(|a, b| a.1.account_num.partial_cmp(&b.1.account_num).unwrap());
-            let account_num = match openbook_account_tuples.last() {
-                Some(tuple) => tuple.1.account_num + 1,
-                None => 0u32,
-            };
-            self.create_open_orders_account(account_num, openbook_account_name)
-                .await
-                .context("Failed to create account...")?;
+        let accounts = self.load_open_orders_accounts().await?;
+        let found = accounts.iter().find(|(_, account)| {
+            account.name() == openbook_account_name && account.market == self.market_id
+        });
+        if let Some((address, _)) = found {
+            return Ok(*address);
         }
-        let openbook_account_tuples = self
-            .rpc_client
-            .fetch_openbook_accounts(program, self.owner())
-            .await?;
-        let index = openbook_account_tuples
-            .iter()
-            .position(|tuple| tuple.1.name() == openbook_account_name)
-            .unwrap();
 
-        Ok(openbook_account_tuples[index].0)
+        let (address, trx) = self
+            .create_open_orders_account(openbook_account_name, None)
+            .await?;
+        self.send_trx(&trx)
+            .await
+            .context("Failed to create account...")?;
+        Ok(address)
     }
 
+    /// Builds the creation of the owner's next open orders account, returning its address. The
+    /// program numbers the accounts of an owner with the counter of its open orders indexer, so
+    /// the address is derived from `next_account_num`, and the indexer is created first if the
+    /// owner has none.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -444,7 +1129,7 @@ impl OBClient {
     ///
     ///     let ob_client = OBClient::new(commitment, market_id, false, true).await?;
     ///
-    ///     let (confirmed, sig, account) = ob_client.create_open_orders_account(2, "Sol-USDC-OO-Account").await?;
+    ///     let (account, trx) = ob_client.create_open_orders_account("Sol-USDC-OO-Account", None).await?;
     ///
     ///     println!("Got New OO Account: {:?}", account);
     ///
@@ -453,51 +1138,75 @@ impl OBClient {
     /// ```
     pub async fn create_open_orders_account(
         &self,
-        account_num: u32,
         name: &str,
-    ) -> Result<Transaction> {
-        let owner = &self.owner;
-        let payer = &self.owner;
-        let market = self.market_id;
-
-        let delegate = None;
-
-        let open_orders_indexer = Pubkey::find_program_address(
-            &[b"OpenOrdersIndexer".as_ref(), owner.pubkey().as_ref()],
-            &openbook_v2::id(),
-        )
-        .0;
+        delegate: Option<Pubkey>,
+    ) -> Result<(Pubkey, Transaction)> {
+        let owner = self.owner();
+        let payer = self.payer.pubkey();
+        let open_orders_indexer = instructions::open_orders_indexer(&owner);
+        let indexer = self.load_open_orders_indexer().await?;
+        let open_orders_account =
+            instructions::open_orders_account(&owner, next_account_num(indexer.as_ref()));
 
-        let account = Pubkey::find_program_address(
-            &[
-                b"OpenOrders".as_ref(),
-                owner.pubkey().as_ref(),
-                &account_num.to_le_bytes(),
-            ],
-            &openbook_v2::id(),
-        )
-        .0;
-
-        let ix = Instruction {
-            program_id: openbook_v2::id(),
-            accounts: anchor_lang::ToAccountMetas::to_account_metas(
-                &openbook_v2::accounts::CreateOpenOrdersAccount {
-                    owner: owner.pubkey(),
+        let mut ixs = vec![];
+        if indexer.is_none() {
+            ixs.push(instructions::create_open_orders_indexer(
+                openbook_v2::accounts::CreateOpenOrdersIndexer {
+                    payer,
+                    owner,
                     open_orders_indexer,
-                    open_orders_account: account,
-                    payer: payer.pubkey(),
-                    delegate_account: delegate,
-                    market,
                     system_program: System::id(),
                 },
-                None,
-            ),
-            data: anchor_lang::InstructionData::data(
-                &openbook_v2::instruction::CreateOpenOrdersAccount {
-                    name: name.to_string(),
-                },
-            ),
+            ));
+        }
+        ixs.push(instructions::create_open_orders_account(
+            openbook_v2::accounts::CreateOpenOrdersAccount {
+                owner,
+                open_orders_indexer,
+                open_orders_account,
+                payer,
+                delegate_account: delegate,
+                market: self.market_id,
+                system_program: System::id(),
+            },
+            name.to_string(),
+        ));
+
+        Ok((open_orders_account, self.to_trx(ixs).await?))
+    }
+
+    /// Fetches the open orders indexer of the owner, listing its open orders accounts, `None` if
+    /// the owner never created one.
+    pub async fn load_open_orders_indexer(&self) -> Result<Option<OpenOrdersIndexer>> {
+        self.rpc_client
+            .fetch_open_orders_indexer(&self.owner())
+            .await
+    }
+
+    /// Fetches all the open orders accounts of the owner, on any market, from the addresses
+    /// listed by its open orders indexer.
+    pub async fn load_open_orders_accounts(&self) -> Result<Vec<(Pubkey, OpenOrdersAccount)>> {
+        let Some(indexer) = self.load_open_orders_indexer().await? else {
+            return Ok(vec![]);
         };
+        self.rpc_client
+            .fetch_open_orders_accounts(&indexer.addresses)
+            .await
+    }
+
+    /// The number of the next open orders account created by the owner.
+    pub async fn next_account_num(&self) -> Result<u32> {
+        let indexer = self.load_open_orders_indexer().await?;
+        Ok(next_account_num(indexer.as_ref()))
+    }
+
+    /// Sets or, with `None`, removes the delegate allowed to trade the open orders account.
+    pub async fn set_delegate(&self, delegate: Option<Pubkey>) -> Result<Transaction> {
+        let ix = instructions::set_delegate(openbook_v2::accounts::SetDelegate {
+            owner: self.owner(),
+            open_orders_account: self.open_orders_account,
+            delegate_account: delegate,
+        });
 
         self.to_trx(vec![ix]).await
     }
@@ -506,6 +1215,68 @@ impl OBClient {
         self.owner.pubkey()
     }
 
+    /// The key signing orders and cancels: the delegate in delegate mode, the owner otherwise.
+    pub fn signer(&self) -> Pubkey {
+        self.delegate
+            .as_ref()
+            .map_or_else(|| self.owner(), |delegate| delegate.pubkey())
+    }
+
+    /// The token account of the order signer funding orders on `side`.
+    pub fn signer_token_account(&self, side: Side) -> Pubkey {
+        match (&self.delegate, side) {
+            (None, Side::Bid) => self.quote_ata,
+            (None, Side::Ask) => self.base_ata,
+            (Some(delegate), side) => {
+                let mint = self.side_mint(side);
+                get_associated_token_address_with_program_id(
+                    &delegate.pubkey(),
+                    &mint,
+                    &self.token_program(&mint),
+                )
+            }
+        }
+    }
+
+    /// The mint of the tokens funding orders on `side`: quote for bids, base for asks.
+    pub fn side_mint(&self, side: Side) -> Pubkey {
+        match side {
+            Side::Bid => self.market_info.quote_mint,
+            Side::Ask => self.market_info.base_mint,
+        }
+    }
+
+    /// The token program of `mint`, if it is the base or quote mint, the Token program otherwise.
+    pub fn token_program(&self, mint: &Pubkey) -> Pubkey {
+        if *mint == self.market_info.base_mint {
+            self.context.base_mint_info.token_program
+        } else if *mint == self.market_info.quote_mint {
+            self.context.quote_mint_info.token_program
+        } else {
+            Token::id()
+        }
+    }
+
+    /// The token program transferring the tokens funding orders on `side`.
+    pub fn side_token_program(&self, side: Side) -> Pubkey {
+        self.token_program(&self.side_mint(side))
+    }
+
+    /// The token program of instructions transferring both base and quote tokens.
+    ///
+    /// Fails if the base and quote mints are owned by different token programs.
+    pub fn common_token_program(&self) -> Result<Pubkey> {
+        let base = self.context.base_mint_info.token_program;
+        let quote = self.context.quote_mint_info.token_program;
+        anyhow::ensure!(
+            base == quote,
+            "Base and quote mints of market {} use different token programs",
+            self.market_id
+        );
+        Ok(base)
+    }
+
+    /// Builds the creation of a market paid for by the payer, see `create_market_ix`.
     #[allow(clippy::too_many_arguments)]
     pub async fn create_market(
         &self,
@@ -531,47 +1302,124 @@ impl OBClient {
         taker_fee: i64,
         time_expiry: i64,
     ) -> Result<Transaction> {
-        let ix = Instruction {
-            program_id: openbook_v2::id(),
-            accounts: {
-                anchor_lang::ToAccountMetas::to_account_metas(
-                    &openbook_v2::accounts::CreateMarket {
-                        market,
-                        market_authority,
-                        bids,
-                        asks,
-                        event_heap,
-                        payer: self.owner(),
-                        market_base_vault: self.base_ata,
-                        market_quote_vault: self.quote_ata,
-                        base_mint,
-                        quote_mint,
-                        system_program: solana_sdk::system_program::id(),
-                        oracle_a,
-                        oracle_b,
-                        collect_fee_admin,
-                        open_orders_admin,
-                        consume_events_admin,
-                        close_market_admin,
-                        event_authority,
-                        program: openbook_v2::id(),
-                        token_program: Token::id(),
-                        associated_token_program: AssociatedToken::id(),
-                    },
-                    None,
-                )
-            },
-            data: anchor_lang::InstructionData::data(&openbook_v2::instruction::CreateMarket {
-                name,
-                oracle_config,
-                base_lot_size,
-                quote_lot_size,
-                maker_fee,
-                taker_fee,
-                time_expiry,
-            }),
-        };
+        let [base_mint_info, quote_mint_info] = self
+            .rpc_client
+            .fetch_mint_infos(&[base_mint, quote_mint])
+            .await?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Expected the base and quote mint infos"))?;
+        anyhow::ensure!(
+            base_mint_info.token_program == quote_mint_info.token_program,
+            "Base and quote mints use different token programs"
+        );
 
+        let ix = create_market_ix(
+            self.payer.pubkey(),
+            base_mint_info.token_program,
+            market,
+            market_authority,
+            bids,
+            asks,
+            event_heap,
+            base_mint,
+            quote_mint,
+            oracle_a,
+            oracle_b,
+            collect_fee_admin,
+            open_orders_admin,
+            consume_events_admin,
+            close_market_admin,
+            event_authority,
+            name,
+            oracle_config,
+            base_lot_size,
+            quote_lot_size,
+            maker_fee,
+            taker_fee,
+            time_expiry,
+        );
+        self.to_trx(vec![ix]).await
+    }
+
+    /// Expires the market, signed by the owner as its close market admin. Orders can then only
+    /// be cancelled or pruned, and funds settled.
+    pub async fn set_market_expired(&self) -> Result<Transaction> {
+        let ix = admin::set_market_expired(&self.context, &self.owner())?;
+        self.to_trx(vec![ix]).await
+    }
+
+    /// Removes up to `limit` orders of `open_orders_account` from the book of the expired market,
+    /// signed by the owner as its close market admin.
+    pub async fn prune_orders(
+        &self,
+        open_orders_account: Pubkey,
+        limit: u8,
+    ) -> Result<Transaction> {
+        let ix = admin::prune_orders(&self.context, &self.owner(), open_orders_account, limit)?;
+        self.to_trx(vec![ix]).await
+    }
+
+    /// Settles the funds of `open_orders_account` on the expired market to its owner's associated
+    /// token accounts, signed by the owner as the close market admin.
+    pub async fn settle_funds_expired(&self, open_orders_account: Pubkey) -> Result<Transaction> {
+        let account = self
+            .rpc_client
+            .fetch_anchor_account::<OpenOrdersAccount>(&open_orders_account)
+            .await?;
+        let ix = admin::settle_funds_expired(
+            &self.context,
+            &self.owner(),
+            self.payer.pubkey(),
+            open_orders_account,
+            &account,
+        )?;
+        self.to_trx(vec![ix]).await
+    }
+
+    /// Closes the empty market, signed by the owner as its close market admin, returning the rent
+    /// to the owner.
+    pub async fn close_market(&self) -> Result<Transaction> {
+        let owner = self.owner();
+        let ix = admin::close_market(&self.context, &owner, owner)?;
+        self.to_trx(vec![ix]).await
+    }
+
+    /// Sweeps the fees accrued by the market to the owner's quote token account, signed by the
+    /// owner as its collect fee admin.
+    pub async fn sweep_fees(&self) -> Result<Transaction> {
+        let owner = self.owner();
+        let ix = admin::sweep_fees(&self.context, &owner, self.quote_ata)?;
+        let mut ixs = self
+            .prepare_token_accounts(&owner, &[(self.market_info.quote_mint, 0)])
+            .await?;
+        ixs.push(ix);
+        self.to_trx(ixs).await
+    }
+
+    /// Creates the owner's stub oracle for `mint` at `price`, returning its address, to use as a
+    /// market oracle in tests.
+    pub async fn create_stub_oracle(
+        &self,
+        mint: Pubkey,
+        price: f64,
+    ) -> Result<(Pubkey, Transaction)> {
+        let owner = self.owner();
+        let ix = admin::stub_oracle_create(self.payer.pubkey(), owner, mint, price);
+        Ok((
+            admin::stub_oracle(&owner, &mint),
+            self.to_trx(vec![ix]).await?,
+        ))
+    }
+
+    /// Sets the price of the owner's stub oracle for `mint`.
+    pub async fn set_stub_oracle(&self, mint: Pubkey, price: f64) -> Result<Transaction> {
+        let owner = self.owner();
+        let address = admin::stub_oracle(&owner, &mint);
+        let oracle = self
+            .rpc_client
+            .fetch_anchor_account::<StubOracle>(&address)
+            .await?;
+        let ix = admin::stub_oracle_set(address, &oracle, &owner, price)?;
         self.to_trx(vec![ix]).await
     }
 
@@ -586,45 +1434,320 @@ impl OBClient {
         market_base_vault: Pubkey,
         market_quote_vault: Pubkey,
     ) -> Result<Transaction> {
-        let ix = Instruction {
-            program_id: openbook_v2::id(),
-            accounts: {
-                anchor_lang::ToAccountMetas::to_account_metas(
-                    &openbook_v2::accounts::Deposit {
-                        open_orders_account: self.open_orders_account,
-                        owner: self.owner(),
-                        market: market_address,
-                        user_base_account,
-                        user_quote_account,
-                        market_base_vault,
-                        market_quote_vault,
-                        token_program: Token::id(),
-                    },
-                    None,
-                )
+        let ix = instructions::deposit(
+            openbook_v2::accounts::Deposit {
+                open_orders_account: self.open_orders_account,
+                owner: self.owner(),
+                market: market_address,
+                user_base_account,
+                user_quote_account,
+                market_base_vault,
+                market_quote_vault,
+                token_program: match (base_amount, quote_amount) {
+                    (0, _) => self.context.quote_mint_info.token_program,
+                    (_, 0) => self.context.base_mint_info.token_program,
+                    _ => self.common_token_program()?,
+                },
             },
-            data: anchor_lang::InstructionData::data(&openbook_v2::instruction::Deposit {
-                base_amount,
-                quote_amount,
-            }),
-        };
+            base_amount,
+            quote_amount,
+        );
 
-        self.to_trx(vec![ix]).await
+        let mut ixs = vec![];
+        for (mint, user_account, amount) in [
+            (self.market_info.base_mint, user_base_account, base_amount),
+            (
+                self.market_info.quote_mint,
+                user_quote_account,
+                quote_amount,
+            ),
+        ] {
+            let owner_ata = get_associated_token_address_with_program_id(
+                &self.owner(),
+                &mint,
+                &self.token_program(&mint),
+            );
+            if amount > 0 && user_account == owner_ata {
+                ixs.extend(
+                    self.prepare_token_accounts(&self.owner(), &[(mint, amount)])
+                        .await?,
+                );
+            }
+        }
+        ixs.push(ix);
+        self.to_trx(ixs).await
     }
 
     pub fn native_price_to_lots_price(&self, limit_price: f64) -> i64 {
-        let base_decimals = self.market_info.base_decimals as u32;
-        let quote_decimals = self.market_info.quote_decimals as u32;
-        let base_factor = 10_u64.pow(base_decimals);
-        let quote_factor = 10_u64.pow(quote_decimals);
-        let price_factor = (base_factor / quote_factor) as f64;
-        (limit_price * price_factor) as i64
+        self.context.native_price_to_lots_price(limit_price)
     }
 
     pub fn get_base_size_from_quote(&self, quote_size: u64, limit_price: f64) -> u64 {
-        let base_decimals = self.market_info.base_decimals as u32;
-        let base_factor = 10_u64.pow(base_decimals) as f64;
-        ((quote_size as f64 / limit_price) * base_factor) as u64
+        self.context.base_size_from_quote(quote_size, limit_price)
+    }
+
+    pub async fn load_open_orders_account(&self) -> Result<OpenOrdersAccount> {
+        self.rpc_client
+            .fetch_anchor_account::<OpenOrdersAccount>(&self.open_orders_account)
+            .await
+    }
+
+    /// Fetches the referrer rebates accrued by the taker orders of the open orders account, in
+    /// native quote units, to be paid to the referrer on the next settlement.
+    pub async fn referrer_rebates_available(&self) -> Result<u64> {
+        let account = self.load_open_orders_account().await?;
+        Ok(account.position.referrer_rebates_available)
+    }
+
+    /// The quote token account of the `referrer` for `quote_mint`, and the instruction creating
+    /// it idempotently, paid by the payer.
+    fn referrer_token_account(
+        &self,
+        quote_mint: &Pubkey,
+        token_program: &Pubkey,
+    ) -> Option<(Pubkey, Instruction)> {
+        let referrer = self.referrer?;
+        let account =
+            get_associated_token_address_with_program_id(&referrer, quote_mint, token_program);
+        let create = token::create_ata_idempotent(
+            &self.payer.pubkey(),
+            &referrer,
+            quote_mint,
+            token_program,
+        );
+        Some((account, create))
+    }
+
+    /// Fetches the bids and asks of the market, each from best to worst price.
+    pub async fn load_book(&self) -> Result<(Vec<BookOrder>, Vec<BookOrder>)> {
+        let bids = self
+            .rpc_client
+            .fetch_anchor_account::<BookSide>(&self.market_info.bids)
+            .await?;
+        let asks = self
+            .rpc_client
+            .fetch_anchor_account::<BookSide>(&self.market_info.asks)
+            .await?;
+        let now_ts = get_unix_secs();
+        Ok((
+            book::book_side_orders(&bids, now_ts, None),
+            book::book_side_orders(&asks, now_ts, None),
+        ))
+    }
+
+    pub async fn load_event_heap(&self) -> Result<EventHeap> {
+        self.rpc_client
+            .fetch_anchor_account::<EventHeap>(&self.market_info.event_heap)
+            .await
+    }
+
+    /// Checks `orders`, placed together, against the risk limits of the market settings, with the
+    /// orders placed or reserved in the last minute for the rate limit. `replace` tells that all
+    /// open orders are cancelled first.
+    ///
+    /// Fails with a `RiskViolation` if an order breaks a limit.
+    pub async fn check_risk(&self, orders: &[(Side, OrderLots)], replace: bool) -> Result<()> {
+        let Some(limits) = self.settings.risk else {
+            return Ok(());
+        };
+        let state = if limits.needs_state() {
+            self.risk_state(replace).await?
+        } else {
+            RiskState::default()
+        };
+        risk::check_orders(&limits, &self.context, &state, orders)?;
+        if let Some(max) = limits.max_orders_per_minute {
+            self.rate_limiter.check(max, orders.len())?;
+        }
+        Ok(())
+    }
+
+    /// Reads the state orders are checked against. With `replace`, the open orders are left out
+    /// as if cancelled.
+    pub async fn risk_state(&self, replace: bool) -> Result<RiskState> {
+        let snapshot = self.snapshot().await?;
+        let (balances, oracle_price) = self.balances_and_oracle_price(&snapshot).await?;
+        let open_orders = snapshot.open_orders.first().map(|account| &account.state);
+        let mut state = RiskState {
+            best_bid: snapshot.bids.state.first().map(|order| order.price_lots),
+            best_ask: snapshot.asks.state.first().map(|order| order.price_lots),
+            oracle_price,
+            base_inventory: balances.base() as i64,
+            resting_bid_lots: open_orders.map_or(0, |account| account.bids_base_lots),
+            open_orders: open_orders.map_or(0, |account| account.orders.len()),
+        };
+        if replace {
+            state.resting_bid_lots = 0;
+            state.open_orders = 0;
+        }
+        Ok(state)
+    }
+
+    /// Reads the owner's `Balances`, with the open orders account in `snapshot`, and the oracle
+    /// price of the market, if it has an oracle and the price is fresh and confident enough for
+    /// the program to use it.
+    pub async fn balances_and_oracle_price(
+        &self,
+        snapshot: &MarketSnapshot,
+    ) -> Result<(Balances, Option<f64>)> {
+        let mut addresses = vec![self.base_ata, self.quote_ata];
+        addresses.extend(oracle::market_oracles(&self.market_info));
+        let rpc = self.rpc_client.inner();
+        let response = rpc
+            .get_multiple_accounts_with_commitment(&addresses, rpc.commitment())
+            .await?;
+        let accounts = response.value;
+
+        let [wallet_base, wallet_quote] = [&accounts[0], &accounts[1]].map(|account| {
+            account
+                .as_ref()
+                .and_then(|account| token::token_account_amount(&account.data))
+                .unwrap_or_default()
+        });
+        let balances = Balances::new(
+            &self.context,
+            snapshot.open_orders.first().map(|account| &account.state),
+            wallet_base,
+            wallet_quote,
+        );
+
+        let oracle_price =
+            oracle::market_oracle_price(&self.market_info, &accounts[2..], response.context.slot)?;
+        Ok((balances, oracle_price.and_then(|price| price.usable())))
+    }
+
+    /// Reads the oracle price of the market, with its staleness and confidence checked against
+    /// the market's `oracle_config`, or `None` if the market has no oracle.
+    pub async fn oracle_price(&self) -> Result<Option<OraclePrice>> {
+        let addresses = oracle::market_oracles(&self.market_info);
+        if addresses.is_empty() {
+            return Ok(None);
+        }
+        let rpc = self.rpc_client.inner();
+        let response = rpc
+            .get_multiple_accounts_with_commitment(&addresses, rpc.commitment())
+            .await?;
+        oracle::market_oracle_price(&self.market_info, &response.value, response.context.slot)
+    }
+
+    /// Accounts the fills of the client's open orders account in the event heap into
+    /// `portfolio`, and reads its inventory and PnL valued at `mark`.
+    pub async fn portfolio_pnl(
+        &self,
+        portfolio: &mut MarketPortfolio,
+        mark: MarkPrice,
+    ) -> Result<MarketPnl> {
+        let snapshot = self.snapshot().await?;
+        portfolio.apply_snapshot(&snapshot)?;
+        let (balances, oracle_price) = self.balances_and_oracle_price(&snapshot).await?;
+        let mark_price = match mark {
+            MarkPrice::Mid => snapshot
+                .bids
+                .state
+                .first()
+                .zip(snapshot.asks.state.first())
+                .map(|(bid, ask)| {
+                    self.context
+                        .price_lots_to_ui(bid.price_lots + ask.price_lots)
+                        / 2.0
+                }),
+            MarkPrice::Oracle => oracle_price,
+        };
+        Ok(portfolio.pnl(&balances, mark_price))
+    }
+
+    /// Takes a snapshot of the market, its book sides, event heap and the client's open orders
+    /// account, all read at the same slot.
+    ///
+    /// The open orders account is skipped if the client has none.
+    pub async fn snapshot(&self) -> Result<MarketSnapshot> {
+        let mut addresses = vec![
+            self.market_id,
+            self.market_info.bids,
+            self.market_info.asks,
+            self.market_info.event_heap,
+        ];
+        if self.open_orders_account != Pubkey::default() {
+            addresses.push(self.open_orders_account);
+        }
+        let rpc = self.rpc_client.inner();
+        let response = rpc
+            .get_multiple_accounts_with_commitment(&addresses, rpc.commitment())
+            .await?;
+        let slot = response.context.slot;
+        let mut data = response
+            .value
+            .into_iter()
+            .zip(&addresses)
+            .map(|(account, address)| {
+                account
+                    .map(|account| account.data)
+                    .ok_or_else(|| anyhow::anyhow!("Account {address} not found"))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter();
+        let mut next = || data.next().unwrap_or_default();
+
+        let market = next();
+        let context = MarketContext {
+            market: Market::try_deserialize(&mut market.as_slice())?,
+            ..self.context.clone()
+        };
+        let now_ts = get_unix_secs();
+        Ok(MarketSnapshot {
+            slot,
+            timestamp: now_ts,
+            market: snapshot::market(&context, slot, market),
+            bids: snapshot::book_side(self.market_info.bids, slot, next(), now_ts)?,
+            asks: snapshot::book_side(self.market_info.asks, slot, next(), now_ts)?,
+            event_heap: snapshot::event_heap(self.market_info.event_heap, slot, next())?,
+            open_orders: addresses
+                .get(4)
+                .map(|address| snapshot::open_orders(*address, slot, next()))
+                .transpose()?
+                .into_iter()
+                .collect(),
+        })
+    }
+
+    /// Sends `trx` and waits for its confirmation, counting its orders towards the rate limit
+    /// of the market once sent.
+    ///
+    /// On a permissioned market, fails without sending if `trx` misses the signature of the open
+    /// orders admin.
+    pub async fn send_trx(
+        &self,
+        trx: &(impl SerializableTransaction + Cosignable),
+    ) -> Result<Signature> {
+        if let Some(admin) = self.open_orders_admin_key() {
+            anyhow::ensure!(
+                !trx.missing_signers().contains(&admin),
+                "Transaction misses the signature of the open orders admin {admin} of market {}: \
+                 sign it with `admin::sign_open_orders`, or co-sign with `with_open_orders_admin`",
+                self.market_id
+            );
+        }
+        let result = self
+            .rpc_client
+            .inner()
+            .send_and_confirm_transaction(trx)
+            .await;
+        self.rate_limiter
+            .settle(trx.get_signature(), result.is_ok());
+        Ok(result?)
+    }
+
+    /// Simulates `trx` without sending it, e.g. to check it succeeds and inspect its logs.
+    pub async fn simulate_trx(
+        &self,
+        trx: &impl SerializableTransaction,
+    ) -> Result<RpcSimulateTransactionResult> {
+        Ok(self
+            .rpc_client
+            .inner()
+            .simulate_transaction(trx)
+            .await?
+            .value)
     }
 
     pub async fn get_token_balance(&self, ata: &Pubkey) -> Result<f64> {
@@ -636,21 +1759,275 @@ impl OBClient {
         Ok(r.ui_amount.unwrap())
     }
 
+    /// Replaces the signer paying transaction fees and rent, which defaults to the owner.
+    pub fn with_payer(mut self, payer: SharedSigner) -> Self {
+        self.payer = payer;
+        self
+    }
+
+    /// Switches the client to delegate mode, signing orders and cancels with `delegate`.
+    pub fn with_delegate(mut self, delegate: SharedSigner) -> Self {
+        self.delegate = Some(delegate);
+        self
+    }
+
+    /// Pays the referrer rebates of the owner's taker orders to `referrer` when settling.
+    pub fn with_referrer(mut self, referrer: Pubkey) -> Self {
+        self.referrer = Some(referrer);
+        self
+    }
+
+    /// Co-signs the orders of the client with `open_orders_admin`, which must be the open orders
+    /// admin of the market.
+    pub fn with_open_orders_admin(mut self, open_orders_admin: SharedSigner) -> Result<Self> {
+        admin::check_admin(
+            &self.context,
+            AdminRole::OpenOrders,
+            &open_orders_admin.pubkey(),
+        )?;
+        self.open_orders_admin = Some(open_orders_admin);
+        Ok(self)
+    }
+
+    /// The open orders admin of the market, which must co-sign orders, `None` if the market is
+    /// permissionless.
+    pub fn open_orders_admin_key(&self) -> Option<Pubkey> {
+        self.market_info.open_orders_admin.into()
+    }
+
+    /// Replaces the address lookup tables used to compile versioned transactions.
+    pub fn with_lookup_tables(mut self, lookup_tables: Vec<AddressLookupTableAccount>) -> Self {
+        self.lookup_tables = lookup_tables;
+        self
+    }
+
+    /// Fetches the lookup tables at `addresses` and uses them for versioned transactions.
+    pub async fn load_lookup_tables(&mut self, addresses: &[Pubkey]) -> Result<()> {
+        self.lookup_tables = self.rpc_client.fetch_lookup_tables(addresses).await?;
+        Ok(())
+    }
+
+    /// Creates a lookup table of the payer holding the static accounts of the market, and uses it
+    /// for versioned transactions.
+    ///
+    /// Sends one transaction per chunk of addresses, and waits for the table to become usable in
+    /// the slot after the last one.
+    pub async fn create_market_lookup_table(&mut self) -> Result<Pubkey> {
+        let rpc = self.rpc_client.inner();
+        let addresses = lookup_table::market_addresses(
+            &self.market_id,
+            &self.market_info,
+            &[
+                self.context.base_mint_info.token_program,
+                self.context.quote_mint_info.token_program,
+            ],
+        );
+        let recent_slot = rpc
+            .get_slot_with_commitment(CommitmentConfig::finalized())
+            .await?;
+        let payer = self.payer.pubkey();
+        let (chunks, table) =
+            lookup_table::create_and_extend(&payer, &payer, recent_slot, &addresses);
+        for ixs in chunks {
+            let trx = self.to_trx(ixs).await?;
+            self.send_trx(&trx).await?;
+        }
+
+        let extended_slot = rpc.get_slot().await?;
+        while rpc.get_slot().await? <= extended_slot {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        }
+        self.lookup_tables = self.rpc_client.fetch_lookup_tables(&[table]).await?;
+        Ok(table)
+    }
+
+    /// Builds the transaction of `orders` orders passing `check_risk`, reserving them for the rate
+    /// limit of the market until `send_trx` settles the transaction, or for a minute.
+    async fn to_order_trx(
+        &self,
+        instructions: Vec<Instruction>,
+        orders: usize,
+    ) -> Result<Transaction> {
+        let trx = self.to_trx(instructions).await?;
+        let rate_limited = self
+            .settings
+            .risk
+            .is_some_and(|limits| limits.max_orders_per_minute.is_some());
+        if rate_limited {
+            self.rate_limiter.reserve(trx.signatures[0], orders);
+        }
+        Ok(trx)
+    }
+
+    /// Builds a message paid for by the payer, without signing it.
+    ///
+    /// Useful for offline signing workflows, where the message is serialized and signed
+    /// elsewhere before being submitted.
+    pub fn to_message(&self, instructions: &[Instruction], recent_blockhash: Hash) -> Message {
+        Message::new_with_blockhash(instructions, Some(&self.payer.pubkey()), &recent_blockhash)
+    }
+
+    /// Builds an unsigned transaction paid for by the payer, using the latest blockhash.
+    pub async fn to_unsigned_trx(&self, instructions: Vec<Instruction>) -> Result<Transaction> {
+        let recent_hash = self.latest_blockhash().await?;
+        Ok(Transaction::new_unsigned(
+            self.to_message(&instructions, recent_hash),
+        ))
+    }
+
+    /// Builds a transaction paid for by the payer and signed by the payer, and by the owner,
+    /// delegate and open orders admin where the instructions require them.
+    ///
+    /// The transaction is only partially signed, so instructions requiring additional
+    /// signers (e.g. the market keypair in `create_market`, or the open orders admin of a
+    /// permissioned market without a co-signer) can be completed by the caller with
+    /// `Transaction::partial_sign`.
     pub async fn to_trx(&self, instructions: Vec<Instruction>) -> anyhow::Result<Transaction> {
+        let mut trx = self.to_unsigned_trx(instructions).await?;
+        let recent_hash = trx.message.recent_blockhash;
+        let required: Vec<Pubkey> = trx.message.signer_keys().into_iter().copied().collect();
+
+        let mut signers: Vec<&(dyn Signer + Send + Sync)> = vec![];
+        for signer in self.signers() {
+            let pubkey = signer.pubkey();
+            if required.contains(&pubkey) && signers.iter().all(|s| s.pubkey() != pubkey) {
+                signers.push(signer.as_ref());
+            }
+        }
+        trx.try_partial_sign(&signers, recent_hash)?;
+        Ok(trx)
+    }
+
+    /// Compiles a v0 message paid for by the payer, referencing accounts through the client's
+    /// lookup tables, without signing it.
+    pub fn to_versioned_message(
+        &self,
+        instructions: &[Instruction],
+        recent_blockhash: Hash,
+    ) -> Result<VersionedMessage> {
+        lookup_table::to_message(
+            &self.payer.pubkey(),
+            instructions,
+            &self.lookup_tables,
+            recent_blockhash,
+        )
+    }
+
+    /// Builds a v0 transaction using the client's lookup tables, signed like `to_trx`.
+    ///
+    /// The signatures of additional signers are left empty, to be filled in by the caller.
+    pub async fn to_versioned_trx(
+        &self,
+        instructions: Vec<Instruction>,
+    ) -> Result<VersionedTransaction> {
+        let recent_hash = self.latest_blockhash().await?;
+        let message = self.to_versioned_message(&instructions, recent_hash)?;
+        let signers: Vec<&dyn Signer> = self
+            .signers()
+            .map(|signer| signer.as_ref() as &dyn Signer)
+            .collect();
+        lookup_table::partially_signed(message, &signers)
+    }
+
+    /// The signers of the client: the payer, owner, delegate and open orders admin.
+    fn signers(&self) -> impl Iterator<Item = &SharedSigner> {
+        [
+            Some(&self.payer),
+            Some(&self.owner),
+            self.delegate.as_ref(),
+            self.open_orders_admin.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    pub async fn latest_blockhash(&self) -> Result<Hash> {
         let (recent_hash, _) = self
             .rpc_client
             .inner()
             .get_latest_blockhash_with_commitment(self.rpc_client.inner().commitment())
             .await?;
-        Ok(Transaction::new_signed_with_payer(
-            &instructions,
-            Some(&self.owner.pubkey()),
-            &[&self.owner],
-            recent_hash,
-        ))
+        Ok(recent_hash)
     }
 }
 
+/// Builds the `CreateMarket` instruction of `OBClient::create_market`, paid for by `payer`, for
+/// mints of `token_program`. Needs no client, so it can create the first market a client is then
+/// opened on.
+#[allow(clippy::too_many_arguments)]
+pub fn create_market_ix(
+    payer: Pubkey,
+    token_program: Pubkey,
+    market: Pubkey,
+    market_authority: Pubkey,
+    bids: Pubkey,
+    asks: Pubkey,
+    event_heap: Pubkey,
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+    oracle_a: Option<Pubkey>,
+    oracle_b: Option<Pubkey>,
+    collect_fee_admin: Pubkey,
+    open_orders_admin: Option<Pubkey>,
+    consume_events_admin: Option<Pubkey>,
+    close_market_admin: Option<Pubkey>,
+    event_authority: Pubkey,
+    name: String,
+    oracle_config: OracleConfigParams,
+    base_lot_size: i64,
+    quote_lot_size: i64,
+    maker_fee: i64,
+    taker_fee: i64,
+    time_expiry: i64,
+) -> Instruction {
+    instructions::create_market(
+        openbook_v2::accounts::CreateMarket {
+            market,
+            market_authority,
+            bids,
+            asks,
+            event_heap,
+            payer,
+            market_base_vault: get_associated_token_address_with_program_id(
+                &market_authority,
+                &base_mint,
+                &token_program,
+            ),
+            market_quote_vault: get_associated_token_address_with_program_id(
+                &market_authority,
+                &quote_mint,
+                &token_program,
+            ),
+            base_mint,
+            quote_mint,
+            system_program: solana_sdk::system_program::id(),
+            oracle_a,
+            oracle_b,
+            collect_fee_admin,
+            open_orders_admin,
+            consume_events_admin,
+            close_market_admin,
+            event_authority,
+            program: openbook_v2::id(),
+            token_program,
+            associated_token_program: AssociatedToken::id(),
+        },
+        name,
+        oracle_config,
+        quote_lot_size,
+        base_lot_size,
+        maker_fee,
+        taker_fee,
+        time_expiry,
+    )
+}
+
+/// The number of the next open orders account of the owner of `indexer`: the program numbers
+/// the accounts from 1 with the indexer's counter, which never decreases when accounts are closed.
+pub fn next_account_num(indexer: Option<&OpenOrdersIndexer>) -> u32 {
+    indexer.map_or(1, |indexer| indexer.created_counter + 1)
+}
+
 /// Gets the current UNIX timestamp in seconds.
 fn get_unix_secs() -> u64 {
     SystemTime::now()
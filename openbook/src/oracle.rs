@@ -0,0 +1,285 @@
+//! Decoding of the oracle accounts supported by the OpenBook V2 program, and the effective oracle
+//! price of a market.
+//!
+//! A market is priced by `oracle_a` alone, quoting the base token in the quote token, or by
+//! `oracle_a` divided by `oracle_b` when both are set, e.g. SOL/USD over USDC/USD. Like the
+//! program, the price is not used when an oracle was last updated more than
+//! `OracleConfig::max_staleness_slots` slots ago, or when its confidence interval is wider than
+//! `OracleConfig::conf_filter` times the price. The confidence of a combined price compounds the
+//! relative confidences of both oracles.
+//!
+//! Pyth and Switchboard accounts are decoded from their documented layouts, as their crates are
+//! only dependencies of the program.
+
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{anyhow, bail, Result};
+use openbook_v2::state::{Market, OracleConfig, StubOracle};
+use solana_sdk::{account::Account, pubkey, pubkey::Pubkey};
+
+/// Magic number starting Pyth accounts.
+const PYTH_MAGIC: u32 = 0xa1b2_c3d4;
+
+/// Discriminator of Switchboard V2 `AggregatorAccountData` accounts.
+const SWITCHBOARD_V2_DISCRIMINATOR: [u8; 8] = [217, 230, 65, 101, 201, 162, 27, 125];
+
+/// Switchboard V1 programs, whose result accounts have no discriminator.
+const SWITCHBOARD_V1_PROGRAMS: [Pubkey; 2] = [
+    pubkey!("DtmE9D2CSB4L5D6A15mraeEjrGMm6auWVzgaD8hK2tZM"),
+    pubkey!("7azgmy1pFXHikv36q1zZASvFq5vFa39TT9NweVugKKTU"),
+];
+
+/// Offsets in a Pyth price account.
+mod pyth {
+    pub const EXPONENT: usize = 20;
+    pub const LAST_SLOT: usize = 32;
+    pub const AGGREGATE_PRICE: usize = 208;
+    pub const AGGREGATE_CONFIDENCE: usize = 216;
+    pub const LEN: usize = 240;
+}
+
+/// Offsets in a packed Switchboard V2 aggregator account, in its latest confirmed round.
+mod switchboard_v2 {
+    pub const ROUND_OPEN_SLOT: usize = 350;
+    pub const RESULT: usize = 366;
+    pub const STD_DEVIATION: usize = 386;
+    pub const LEN: usize = 406;
+}
+
+/// Offsets in a Switchboard V1 fast round result account.
+mod switchboard_v1 {
+    pub const ACCOUNT_TYPE: u8 = 13;
+    pub const RESULT: usize = 41;
+    pub const ROUND_OPEN_SLOT: usize = 49;
+    pub const MIN_RESPONSE: usize = 65;
+    pub const MAX_RESPONSE: usize = 73;
+    pub const LEN: usize = 81;
+}
+
+/// The kinds of oracle accounts supported by the program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OracleType {
+    Pyth,
+    Stub,
+    SwitchboardV1,
+    SwitchboardV2,
+}
+
+impl OracleType {
+    /// Identifies the oracle account owned by `owner` holding `data`, like the program does.
+    pub fn determine(owner: &Pubkey, data: &[u8]) -> Result<Self> {
+        if data.get(..4) == Some(&PYTH_MAGIC.to_le_bytes()[..]) {
+            Ok(Self::Pyth)
+        } else if data.get(..8) == Some(&StubOracle::discriminator()[..]) {
+            Ok(Self::Stub)
+        } else if data.get(..8) == Some(&SWITCHBOARD_V2_DISCRIMINATOR[..]) {
+            Ok(Self::SwitchboardV2)
+        } else if SWITCHBOARD_V1_PROGRAMS.contains(owner) {
+            Ok(Self::SwitchboardV1)
+        } else {
+            bail!("Unknown oracle type of an account owned by {owner}")
+        }
+    }
+}
+
+/// The state of an oracle account, with prices in the units of its feed, e.g. USD per token.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OracleState {
+    pub oracle_type: OracleType,
+    pub price: f64,
+
+    /// The confidence interval of the price.
+    pub deviation: f64,
+    pub last_update_slot: u64,
+}
+
+impl OracleState {
+    /// Decodes the oracle account owned by `owner` holding `data`.
+    pub fn decode(owner: &Pubkey, data: &[u8]) -> Result<Self> {
+        let oracle_type = OracleType::determine(owner, data)?;
+        let state = match oracle_type {
+            OracleType::Pyth => {
+                check_len(data, pyth::LEN)?;
+                let scale = 10f64.powi(read_i32(data, pyth::EXPONENT));
+                Self {
+                    oracle_type,
+                    price: read_i64(data, pyth::AGGREGATE_PRICE) as f64 * scale,
+                    deviation: read_u64(data, pyth::AGGREGATE_CONFIDENCE) as f64 * scale,
+                    last_update_slot: read_u64(data, pyth::LAST_SLOT),
+                }
+            }
+            OracleType::Stub => {
+                let stub = StubOracle::try_deserialize(&mut &data[..])?;
+                Self {
+                    oracle_type,
+                    price: stub.price,
+                    deviation: stub.deviation,
+                    last_update_slot: stub.last_update_slot,
+                }
+            }
+            OracleType::SwitchboardV1 => {
+                check_len(data, switchboard_v1::LEN)?;
+                if data[0] != switchboard_v1::ACCOUNT_TYPE {
+                    bail!("Switchboard V1 account is not a fast round result");
+                }
+                Self {
+                    oracle_type,
+                    price: read_f64(data, switchboard_v1::RESULT),
+                    deviation: read_f64(data, switchboard_v1::MAX_RESPONSE)
+                        - read_f64(data, switchboard_v1::MIN_RESPONSE),
+                    last_update_slot: read_u64(data, switchboard_v1::ROUND_OPEN_SLOT),
+                }
+            }
+            OracleType::SwitchboardV2 => {
+                check_len(data, switchboard_v2::LEN)?;
+                Self {
+                    oracle_type,
+                    price: read_switchboard_decimal(data, switchboard_v2::RESULT),
+                    deviation: read_switchboard_decimal(data, switchboard_v2::STD_DEVIATION),
+                    last_update_slot: read_u64(data, switchboard_v2::ROUND_OPEN_SLOT),
+                }
+            }
+        };
+        Ok(state)
+    }
+
+    /// Whether the oracle was last updated more than `config.max_staleness_slots` slots before
+    /// `now_slot`. A negative `max_staleness_slots` disables the check.
+    pub fn is_stale(&self, config: &OracleConfig, now_slot: u64) -> bool {
+        config.max_staleness_slots >= 0
+            && self
+                .last_update_slot
+                .saturating_add(config.max_staleness_slots as u64)
+                < now_slot
+    }
+
+    /// Whether the confidence interval is within `config.conf_filter` times the price.
+    pub fn has_valid_confidence(&self, config: &OracleConfig) -> bool {
+        self.deviation <= config.conf_filter * self.price
+    }
+}
+
+/// The effective oracle price of a market.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OraclePrice {
+    /// In quote tokens per base token.
+    pub price: f64,
+
+    /// The confidence interval of the price, in quote tokens per base token.
+    pub deviation: f64,
+
+    /// The oldest update slot of the oracles.
+    pub last_update_slot: u64,
+
+    /// Whether an oracle is older than `OracleConfig::max_staleness_slots`.
+    pub stale: bool,
+
+    /// Whether the confidence interval is within `OracleConfig::conf_filter` times the price.
+    pub confident: bool,
+}
+
+impl OraclePrice {
+    /// Combines `oracle_a` and, if set, `oracle_b` into the price of a market configured with
+    /// `config`, at `now_slot`.
+    pub fn new(
+        config: &OracleConfig,
+        oracle_a: &OracleState,
+        oracle_b: Option<&OracleState>,
+        now_slot: u64,
+    ) -> Self {
+        let Some(oracle_b) = oracle_b else {
+            return Self {
+                price: oracle_a.price,
+                deviation: oracle_a.deviation,
+                last_update_slot: oracle_a.last_update_slot,
+                stale: oracle_a.is_stale(config, now_slot),
+                confident: oracle_a.has_valid_confidence(config),
+            };
+        };
+        let price = oracle_a.price / oracle_b.price;
+        let relative_deviation = ((oracle_a.deviation / oracle_a.price).powi(2)
+            + (oracle_b.deviation / oracle_b.price).powi(2))
+        .sqrt();
+        Self {
+            price,
+            deviation: price * relative_deviation,
+            last_update_slot: oracle_a.last_update_slot.min(oracle_b.last_update_slot),
+            stale: oracle_a.is_stale(config, now_slot) || oracle_b.is_stale(config, now_slot),
+            confident: relative_deviation <= config.conf_filter,
+        }
+    }
+
+    /// The price, if it is fresh and confident enough for the program to use it.
+    pub fn usable(&self) -> Option<f64> {
+        (!self.stale && self.confident).then_some(self.price)
+    }
+}
+
+/// The oracles of `market`: `oracle_a`, and `oracle_b` if both are set.
+pub fn market_oracles(market: &Market) -> Vec<Pubkey> {
+    let oracle_a = Option::<Pubkey>::from(market.oracle_a);
+    let oracle_b = Option::<Pubkey>::from(market.oracle_b);
+    oracle_a
+        .into_iter()
+        .chain(oracle_b.filter(|_| oracle_a.is_some()))
+        .collect()
+}
+
+/// The price of `market` from the accounts of its `market_oracles`, read at `now_slot`, or `None`
+/// if it has no oracle.
+pub fn market_oracle_price(
+    market: &Market,
+    accounts: &[Option<Account>],
+    now_slot: u64,
+) -> Result<Option<OraclePrice>> {
+    let oracles = market_oracles(market);
+    if accounts.len() != oracles.len() {
+        bail!("Expected {} oracle accounts", oracles.len());
+    }
+    let states = oracles
+        .iter()
+        .zip(accounts)
+        .map(|(address, account)| {
+            let account = account
+                .as_ref()
+                .ok_or_else(|| anyhow!("Oracle {address} not found"))?;
+            OracleState::decode(&account.owner, &account.data)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(states
+        .first()
+        .map(|oracle_a| OraclePrice::new(&market.oracle_config, oracle_a, states.get(1), now_slot)))
+}
+
+fn check_len(data: &[u8], len: usize) -> Result<()> {
+    if data.len() < len {
+        bail!("Oracle account of {} bytes is too short", data.len());
+    }
+    Ok(())
+}
+
+fn read<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    data[offset..offset + N].try_into().unwrap()
+}
+
+fn read_i32(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(read(data, offset))
+}
+
+fn read_i64(data: &[u8], offset: usize) -> i64 {
+    i64::from_le_bytes(read(data, offset))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(read(data, offset))
+}
+
+fn read_f64(data: &[u8], offset: usize) -> f64 {
+    f64::from_le_bytes(read(data, offset))
+}
+
+/// Reads a Switchboard decimal: an `i128` mantissa followed by a `u32` scale.
+fn read_switchboard_decimal(data: &[u8], offset: usize) -> f64 {
+    let mantissa = i128::from_le_bytes(read(data, offset));
+    let scale = u32::from_le_bytes(read(data, offset + 16));
+    mantissa as f64 / 10f64.powi(scale as i32)
+}
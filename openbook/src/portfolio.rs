@@ -0,0 +1,356 @@
+//! Position and PnL accounting of an owner's trading.
+//!
+//! A `MarketPortfolio` accounts the fills of an open orders account, decoded from the event heap
+//! of snapshots or from recorded event heap changes, with the average cost method: fills adding
+//! to the position move its average entry price, and fills reducing it realize the difference
+//! between their price and the average entry price.
+//!
+//! The `Balances` of the owner's wallet and open orders account give the actual inventory, which
+//! also reflects transfers and fills that happened before the accounting started. Unrealized PnL
+//! and equity are valued at the mid price of the book or at the oracle price.
+//!
+//! Maker fills only reach the balances of the open orders account once their event is consumed,
+//! while they are accounted as soon as they are seen in the event heap. Fills consumed before
+//! being seen are missed, and counted as gaps.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use openbook_v2::state::Side;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    context::MarketContext,
+    fees,
+    recorder::{Change, Record},
+    serde_util,
+    snapshot::{EventHeapTracker, EventRecord, MarketSnapshot, OpenOrdersState},
+    strategy::Fill,
+};
+
+/// The price inventory is valued at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkPrice {
+    /// The middle of the best bid and ask.
+    #[default]
+    Mid,
+
+    /// The oracle price of the market.
+    Oracle,
+}
+
+/// The tokens of an owner on a market, in native units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Balances {
+    /// Tokens in the owner's associated token accounts.
+    pub wallet_base: u64,
+    pub wallet_quote: u64,
+
+    /// Free tokens of the open orders account, to be settled.
+    pub free_base: u64,
+    pub free_quote: u64,
+
+    /// Tokens locked in the open orders: the base of the asks, and the quote and maker fees of
+    /// the bids.
+    pub locked_base: u64,
+    pub locked_quote: u64,
+}
+
+impl Balances {
+    /// The balances of the open orders `account`, as tracked by its position, and the wallet
+    /// balances.
+    pub fn new(
+        market: &MarketContext,
+        account: Option<&OpenOrdersState>,
+        wallet_base: u64,
+        wallet_quote: u64,
+    ) -> Self {
+        let mut balances = Self {
+            wallet_base,
+            wallet_quote,
+            ..Self::default()
+        };
+        let Some(account) = account else {
+            return balances;
+        };
+        let bids_quote_native = account.bids_quote_lots * market.market.quote_lot_size;
+        balances.free_base = account.base_free_native;
+        balances.free_quote = account.quote_free_native;
+        balances.locked_base = (account.asks_base_lots * market.market.base_lot_size) as u64;
+        balances.locked_quote = bids_quote_native as u64 + account.locked_maker_fees;
+        balances
+    }
+
+    /// All base tokens of the owner.
+    pub fn base(&self) -> u64 {
+        self.wallet_base + self.free_base + self.locked_base
+    }
+
+    /// All quote tokens of the owner.
+    pub fn quote(&self) -> u64 {
+        self.wallet_quote + self.free_quote + self.locked_quote
+    }
+}
+
+/// The position built by the accounted fills of a market.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    /// Base lots bought net of base lots sold, negative for a short position.
+    pub base_lots: i64,
+
+    /// The average price the position was entered at, in quote lots per base lot, 0 when flat.
+    pub average_entry_price_lots: f64,
+
+    /// Profit of the closed part of the position, in native quote units, before fees.
+    pub realized_pnl_native: f64,
+
+    /// Fees paid, in native quote units.
+    pub fees_paid_native: i64,
+
+    /// Maker rebates earned, in native quote units.
+    pub maker_rebates_native: i64,
+
+    /// Traded volume, in native quote units.
+    pub volume_native: u64,
+
+    pub fills: usize,
+}
+
+impl Position {
+    fn apply(&mut self, market: &MarketContext, fill: &Fill) {
+        let quote_lot_size = market.market.quote_lot_size as f64;
+        let signed_lots = match fill.side {
+            Side::Bid => fill.quantity,
+            Side::Ask => -fill.quantity,
+        };
+        let size = self.base_lots.abs();
+        if self.base_lots == 0 || self.base_lots.signum() == signed_lots.signum() {
+            self.average_entry_price_lots = (size as f64 * self.average_entry_price_lots
+                + fill.quantity as f64 * fill.price_lots as f64)
+                / (size + fill.quantity) as f64;
+        } else {
+            let closed = fill.quantity.min(size);
+            let gain_lots = (fill.price_lots as f64 - self.average_entry_price_lots)
+                * self.base_lots.signum() as f64;
+            self.realized_pnl_native += closed as f64 * gain_lots * quote_lot_size;
+            if fill.quantity > size {
+                self.average_entry_price_lots = fill.price_lots as f64;
+            }
+        }
+        self.base_lots += signed_lots;
+        if self.base_lots == 0 {
+            self.average_entry_price_lots = 0.0;
+        }
+
+        let quote_native = fill.quantity * fill.price_lots * market.market.quote_lot_size;
+        let fee = fees::fill_fee(&market.market, quote_native as u64, fill.maker);
+        if fee >= 0 {
+            self.fees_paid_native += fee;
+        } else {
+            self.maker_rebates_native -= fee;
+        }
+        self.volume_native += quote_native as u64;
+        self.fills += 1;
+    }
+}
+
+/// The inventory and PnL of a market, in tokens.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MarketPnl {
+    #[serde(with = "serde_util::pubkey")]
+    pub market: Pubkey,
+
+    /// All base tokens held, see `Balances::base`.
+    pub base_inventory: f64,
+
+    /// All quote tokens held, see `Balances::quote`.
+    pub quote_inventory: f64,
+
+    /// The position built by the accounted fills, in base tokens.
+    pub position: f64,
+
+    /// In quote tokens per base token, `None` when flat.
+    pub average_entry_price: Option<f64>,
+
+    /// In quote tokens per base token, `None` if unknown.
+    pub mark_price: Option<f64>,
+
+    pub realized_pnl: f64,
+
+    /// The position valued at the mark price, minus its entry cost. 0 without a mark price.
+    pub unrealized_pnl: f64,
+
+    pub fees_paid: f64,
+    pub maker_rebates: f64,
+
+    /// Realized and unrealized PnL, net of fees and rebates.
+    pub net_pnl: f64,
+
+    /// The quote inventory plus the base inventory valued at the mark price.
+    pub equity: Option<f64>,
+}
+
+/// Accounts the fills of an open orders account on a market.
+#[derive(Clone)]
+pub struct MarketPortfolio {
+    pub context: MarketContext,
+    pub open_orders_account: Pubkey,
+    pub position: Position,
+
+    /// Number of gaps in the accounted events, during which fills may have been missed:
+    /// recorded gaps, and snapshots finding events consumed since the previous one.
+    pub gaps: usize,
+
+    /// The sequence number of the taker order of the last accounted fill, and the maker orders
+    /// it filled, to account every fill once.
+    last_seq_num: Option<u64>,
+    last_makers: Vec<(Pubkey, u8)>,
+    events: EventHeapTracker,
+}
+
+impl MarketPortfolio {
+    pub fn new(context: MarketContext, open_orders_account: Pubkey) -> Self {
+        Self {
+            context,
+            open_orders_account,
+            position: Position::default(),
+            gaps: 0,
+            last_seq_num: None,
+            last_makers: vec![],
+            events: EventHeapTracker::default(),
+        }
+    }
+
+    /// Accounts `fill`.
+    pub fn apply_fill(&mut self, fill: &Fill) {
+        self.position.apply(&self.context, fill);
+    }
+
+    /// Accounts the fill of the open orders account in `event`, if any, unless the event was
+    /// already accounted. Events must be applied in the order of the event heap.
+    pub fn apply_event(&mut self, slot: u64, event: &EventRecord) -> Option<Fill> {
+        let EventRecord::Fill {
+            market_seq_num,
+            maker,
+            maker_slot,
+            ..
+        } = *event
+        else {
+            return None;
+        };
+        let fill = Fill::from_event(slot, event, &self.open_orders_account)?;
+        match self.last_seq_num {
+            Some(last) if market_seq_num < last => return None,
+            Some(last) if market_seq_num == last => {
+                if self.last_makers.contains(&(maker, maker_slot)) {
+                    return None;
+                }
+            }
+            _ => {
+                self.last_seq_num = Some(market_seq_num);
+                self.last_makers.clear();
+            }
+        }
+        self.last_makers.push((maker, maker_slot));
+        self.apply_fill(&fill);
+        Some(fill)
+    }
+
+    /// Accounts the fills of the open orders account in the event heap of `snapshot`, counting a
+    /// gap if events were consumed since the previous snapshot before being seen.
+    pub fn apply_snapshot(&mut self, snapshot: &MarketSnapshot) -> Result<Vec<Fill>> {
+        let new_events = self.events.update(&snapshot.event_heap)?;
+        if new_events.missed > 0 {
+            self.gaps += 1;
+        }
+        Ok(new_events
+            .events
+            .iter()
+            .filter_map(|event| self.apply_event(snapshot.slot, event))
+            .collect())
+    }
+
+    /// Accounts the fills in the recorded event heap changes of the market.
+    pub fn apply_records(&mut self, records: &[Record]) {
+        for record in records {
+            if record.market != self.context.address {
+                continue;
+            }
+            match &record.change {
+                Change::Events { events } => {
+                    for event in events {
+                        self.apply_event(record.slot, event);
+                    }
+                }
+                Change::Gap { .. } => self.gaps += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// The inventory and PnL with `balances`, valued at `mark_price` in quote tokens per base
+    /// token.
+    pub fn pnl(&self, balances: &Balances, mark_price: Option<f64>) -> MarketPnl {
+        let context = &self.context;
+        let position = &self.position;
+        let quote_lot_size = context.market.quote_lot_size as f64;
+        let mark_price_lots = mark_price.map(|price| price / context.price_lots_to_ui(1));
+        let unrealized_pnl_native = mark_price_lots.map_or(0.0, |mark| {
+            position.base_lots as f64 * (mark - position.average_entry_price_lots) * quote_lot_size
+        });
+        let quote_factor = 10f64.powi(context.market.quote_decimals as i32);
+
+        let realized_pnl = position.realized_pnl_native / quote_factor;
+        let unrealized_pnl = unrealized_pnl_native / quote_factor;
+        let fees_paid = context.quote_native_to_ui(position.fees_paid_native);
+        let maker_rebates = context.quote_native_to_ui(position.maker_rebates_native);
+        let base_inventory = context.base_native_to_ui(balances.base() as i64);
+        let quote_inventory = context.quote_native_to_ui(balances.quote() as i64);
+        MarketPnl {
+            market: context.address,
+            base_inventory,
+            quote_inventory,
+            position: context.base_lots_to_ui(position.base_lots),
+            average_entry_price: (position.base_lots != 0)
+                .then(|| position.average_entry_price_lots * context.price_lots_to_ui(1)),
+            mark_price,
+            realized_pnl,
+            unrealized_pnl,
+            fees_paid,
+            maker_rebates,
+            net_pnl: realized_pnl + unrealized_pnl - fees_paid + maker_rebates,
+            equity: mark_price.map(|price| quote_inventory + base_inventory * price),
+        }
+    }
+}
+
+/// The `MarketPortfolio`s of an owner, by market.
+#[derive(Clone, Default)]
+pub struct Portfolio {
+    pub markets: BTreeMap<Pubkey, MarketPortfolio>,
+}
+
+impl Portfolio {
+    /// Accounts the fills of `open_orders_account` on the market of `context`.
+    pub fn add_market(&mut self, context: MarketContext, open_orders_account: Pubkey) {
+        self.markets.insert(
+            context.address,
+            MarketPortfolio::new(context, open_orders_account),
+        );
+    }
+
+    pub fn market(&self, market: &Pubkey) -> Option<&MarketPortfolio> {
+        self.markets.get(market)
+    }
+
+    /// Accounts the fills in recorded event heap changes of any of the markets.
+    pub fn apply_records(&mut self, records: &[Record]) {
+        for record in records {
+            if let Some(portfolio) = self.markets.get_mut(&record.market) {
+                portfolio.apply_records(std::slice::from_ref(record));
+            }
+        }
+    }
+}
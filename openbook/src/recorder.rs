@@ -0,0 +1,332 @@
+//! Long-running recorder of the book and event heap changes of a list of markets.
+//!
+//! The recorder polls the book sides and event heap of each market at one slot, and appends one
+//! JSON line per change to a file per market, rotated by size and age. Each file starts with the
+//! full book, so it can be replayed on its own:
+//!
+//! ```text
+//! {"market":"...","slot":1,"wall_clock_ms":1700000000000,"change":{"book":{"bids":[...],"asks":[...]}}}
+//! {"market":"...","slot":9,"wall_clock_ms":1700000003200,"change":{"book_update":{"bids":{...},"asks":{...}}}}
+//! {"market":"...","slot":9,"wall_clock_ms":1700000003200,"change":{"events":{"events":[...]}}}
+//! {"market":"...","slot":120,"wall_clock_ms":1700000048000,"change":{"gap":{"last_slot":9}}}
+//! ```
+
+use std::{
+    fs::{self, File},
+    future::Future,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use openbook_v2::state::Market;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+
+use crate::{
+    book::{self, BookOrder, OrderDiff},
+    rpc::Rpc,
+    serde_util,
+    snapshot::{self, EventHeapSnapshot, EventHeapTracker, EventRecord},
+};
+
+/// Maximum number of accounts fetched by one `getMultipleAccounts` request.
+const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+
+/// Maximum number of markets whose book sides and event heap are fetched by one request, so
+/// that the three accounts of a market are always read at the same slot.
+const MARKETS_PER_REQUEST: usize = MAX_ACCOUNTS_PER_REQUEST / 3;
+
+/// Settings of a `Recorder`.
+#[derive(Clone, Debug)]
+pub struct RecorderConfig {
+    pub markets: Vec<Pubkey>,
+
+    /// Directory the files are written to, created if missing.
+    pub dir: PathBuf,
+
+    pub poll_interval: Duration,
+
+    /// Size after which a file is rotated.
+    pub max_file_bytes: u64,
+
+    /// Age after which a file is rotated.
+    pub max_file_age: Duration,
+
+    /// Largest slot advance between two polls not reported as a gap.
+    pub max_slot_gap: u64,
+}
+
+impl RecorderConfig {
+    pub fn new(markets: Vec<Pubkey>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            markets,
+            dir: dir.into(),
+            poll_interval: Duration::from_millis(400),
+            max_file_bytes: 100 * 1024 * 1024,
+            max_file_age: Duration::from_secs(3600),
+            max_slot_gap: 10,
+        }
+    }
+}
+
+/// A line of a recording.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    #[serde(with = "serde_util::pubkey")]
+    pub market: Pubkey,
+
+    /// The slot the accounts were read at.
+    pub slot: u64,
+
+    /// The unix time the accounts were read at, in milliseconds.
+    pub wall_clock_ms: u64,
+
+    pub change: Change,
+}
+
+/// A change of a market.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    /// The full book, written at the start of every file.
+    Book {
+        bids: Vec<BookOrder>,
+        asks: Vec<BookOrder>,
+    },
+
+    /// The changes of the book since the previous record.
+    BookUpdate { bids: OrderDiff, asks: OrderDiff },
+
+    /// Events added to the event heap since the previous record.
+    Events { events: Vec<EventRecord> },
+
+    /// More than `max_slot_gap` slots passed since `last_slot`, e.g. because polls failed, or
+    /// events were pushed to the event heap and consumed since, so changes may have been missed.
+    Gap { last_slot: u64 },
+}
+
+/// Reads the records of a recording file.
+pub fn read_records(path: impl AsRef<std::path::Path>) -> Result<Vec<Record>> {
+    let contents = fs::read_to_string(path)?;
+    contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+/// Records the books and event heaps of `RecorderConfig::markets`.
+pub struct Recorder {
+    rpc_client: Rpc,
+    config: RecorderConfig,
+    markets: Vec<MarketRecording>,
+}
+
+struct MarketRecording {
+    market: Pubkey,
+    bids: Pubkey,
+    asks: Pubkey,
+    event_heap: Pubkey,
+    last_slot: Option<u64>,
+    book: Option<(Vec<BookOrder>, Vec<BookOrder>)>,
+    events: EventHeapTracker,
+    writer: Option<RotatingWriter>,
+}
+
+impl Recorder {
+    /// Fetches the markets of `config` and prepares their recordings.
+    pub async fn new(
+        rpc_url: String,
+        commitment: CommitmentConfig,
+        config: RecorderConfig,
+    ) -> Result<Self> {
+        let rpc_client = Rpc::new(RpcClient::new_with_commitment(rpc_url, commitment));
+        fs::create_dir_all(&config.dir)
+            .with_context(|| format!("Failed to create {}", config.dir.display()))?;
+
+        let mut markets = vec![];
+        for market in &config.markets {
+            let info = rpc_client.fetch_anchor_account::<Market>(market).await?;
+            markets.push(MarketRecording {
+                market: *market,
+                bids: info.bids,
+                asks: info.asks,
+                event_heap: info.event_heap,
+                last_slot: None,
+                book: None,
+                events: EventHeapTracker::default(),
+                writer: None,
+            });
+        }
+        Ok(Self {
+            rpc_client,
+            config,
+            markets,
+        })
+    }
+
+    /// Polls every `poll_interval` until `shutdown` completes.
+    ///
+    /// Failed polls are logged and retried, and show up as gaps once they succeed again.
+    pub async fn run(&mut self, shutdown: impl Future<Output = ()>) -> Result<()> {
+        tokio::pin!(shutdown);
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = interval.tick() => {
+                    if let Err(err) = self.poll().await {
+                        tracing::warn!("Recorder poll failed: {err:#}");
+                    }
+                }
+            }
+        }
+        self.flush()
+    }
+
+    /// Reads the book sides and event heap of each market at one slot and records their changes.
+    ///
+    /// Markets are read in batches of one `getMultipleAccounts` request, so different batches
+    /// may be read at different slots.
+    pub async fn poll(&mut self) -> Result<()> {
+        let rpc = self.rpc_client.inner();
+        for markets in self.markets.chunks_mut(MARKETS_PER_REQUEST) {
+            let addresses: Vec<Pubkey> = markets
+                .iter()
+                .flat_map(|market| [market.bids, market.asks, market.event_heap])
+                .collect();
+            let response = rpc
+                .get_multiple_accounts_with_commitment(&addresses, rpc.commitment())
+                .await?;
+            let slot = response.context.slot;
+            let wall_clock_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+
+            let mut accounts = response.value.into_iter().zip(addresses);
+            let mut next = || -> Result<Vec<u8>> {
+                let (account, address) = accounts.next().expect("an account per address");
+                Ok(account
+                    .ok_or_else(|| anyhow!("Account {address} not found"))?
+                    .data)
+            };
+            for market in markets {
+                let bids = snapshot::book_side(market.bids, slot, next()?, wall_clock_ms / 1000)?;
+                let asks = snapshot::book_side(market.asks, slot, next()?, wall_clock_ms / 1000)?;
+                let event_heap = snapshot::event_heap(market.event_heap, slot, next()?)?;
+                market.record(
+                    &self.config,
+                    slot,
+                    wall_clock_ms,
+                    bids.state,
+                    asks.state,
+                    &event_heap,
+                )?;
+            }
+        }
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        for writer in self.markets.iter_mut().filter_map(|m| m.writer.as_mut()) {
+            writer.file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl MarketRecording {
+    fn record(
+        &mut self,
+        config: &RecorderConfig,
+        slot: u64,
+        wall_clock_ms: u64,
+        bids: Vec<BookOrder>,
+        asks: Vec<BookOrder>,
+        event_heap: &EventHeapSnapshot,
+    ) -> Result<()> {
+        if self.last_slot.is_some_and(|last| slot <= last) {
+            return Ok(());
+        }
+
+        // A new file starts with the book, even before a gap, so it can be replayed on its own.
+        let mut records = vec![];
+        let rotated = match &self.writer {
+            Some(writer) => writer.is_full(config),
+            None => true,
+        };
+        if rotated {
+            self.writer = Some(RotatingWriter::create(config, &self.market, wall_clock_ms)?);
+            records.push(Change::Book {
+                bids: bids.clone(),
+                asks: asks.clone(),
+            });
+        }
+        let new_events = self.events.update(event_heap)?;
+        if let Some(last_slot) = self.last_slot {
+            if slot - last_slot > config.max_slot_gap || new_events.missed > 0 {
+                records.push(Change::Gap { last_slot });
+            }
+        }
+
+        if let Some((old_bids, old_asks)) = self.book.as_ref().filter(|_| !rotated) {
+            let bids = book::diff_orders(old_bids, &bids);
+            let asks = book::diff_orders(old_asks, &asks);
+            if !bids.is_empty() || !asks.is_empty() {
+                records.push(Change::BookUpdate { bids, asks });
+            }
+        }
+
+        if !new_events.events.is_empty() {
+            records.push(Change::Events {
+                events: new_events.events,
+            });
+        }
+
+        let writer = self.writer.as_mut().expect("writer created above");
+        for change in records {
+            writer.write(&Record {
+                market: self.market,
+                slot,
+                wall_clock_ms,
+                change,
+            })?;
+        }
+        self.last_slot = Some(slot);
+        self.book = Some((bids, asks));
+        Ok(())
+    }
+}
+
+struct RotatingWriter {
+    file: BufWriter<File>,
+    bytes: u64,
+    created: Instant,
+}
+
+impl RotatingWriter {
+    /// Creates `<dir>/<market>-<wall_clock_ms>.jsonl`.
+    fn create(config: &RecorderConfig, market: &Pubkey, wall_clock_ms: u64) -> Result<Self> {
+        let path = config.dir.join(format!("{market}-{wall_clock_ms}.jsonl"));
+        let file =
+            File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self {
+            file: BufWriter::new(file),
+            bytes: 0,
+            created: Instant::now(),
+        })
+    }
+
+    fn is_full(&self, config: &RecorderConfig) -> bool {
+        self.bytes >= config.max_file_bytes || self.created.elapsed() >= config.max_file_age
+    }
+
+    fn write(&mut self, record: &Record) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.bytes += line.len() as u64;
+        Ok(())
+    }
+}
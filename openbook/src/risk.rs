@@ -0,0 +1,308 @@
+//! Pre-trade risk checks applied by `OBClient` before building order transactions.
+//!
+//! The limits of a market are part of its `MarketSettings`, e.g. in the configuration file:
+//!
+//! ```toml
+//! [profiles.mainnet.markets.gQN1TNHiqj5x82ZQd7JZ8rm8WD4xwWtXxd4onReWZNK.risk]
+//! max_order_notional = 5000.0
+//! max_position = 100.0
+//! price_band_bps = 200.0
+//! price_band_reference = "oracle"
+//! max_open_orders = 20
+//! max_orders_per_minute = 120
+//! ```
+//!
+//! Orders breaking a limit are rejected with a `RiskViolation`, which callers can recover from
+//! the `anyhow::Error` with `downcast_ref`.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use openbook_v2::state::Side;
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::Signature;
+
+use crate::context::{MarketContext, OrderLots};
+
+/// Window of `RiskLimits::max_orders_per_minute`.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// The price the price band is measured from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceReference {
+    /// The best opposite price of the book: the best ask for bids and the best bid for asks,
+    /// falling back to the oracle price when that side is empty.
+    #[default]
+    Book,
+
+    /// The oracle price of the market.
+    Oracle,
+}
+
+/// Limits of the orders placed on a market. Unset limits are not checked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RiskLimits {
+    /// Maximum value of an order, in quote tokens.
+    pub max_order_notional: Option<f64>,
+
+    /// Maximum base held once all resting and new bids fill, in base tokens.
+    pub max_position: Option<f64>,
+
+    /// Maximum distance of a bid above, or of an ask below, the reference price, in basis points.
+    pub price_band_bps: Option<f64>,
+
+    #[serde(default)]
+    pub price_band_reference: PriceReference,
+
+    /// Maximum number of open orders, including the new ones.
+    pub max_open_orders: Option<usize>,
+
+    /// Maximum number of orders placed in any minute.
+    pub max_orders_per_minute: Option<usize>,
+}
+
+impl RiskLimits {
+    /// Whether the limits need the state of the market and account to be checked.
+    pub fn needs_state(&self) -> bool {
+        self.max_position.is_some()
+            || self.price_band_bps.is_some()
+            || self.max_open_orders.is_some()
+    }
+}
+
+/// The state of a market and open orders account that orders are checked against.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RiskState {
+    /// The best bid, in quote lots per base lot.
+    pub best_bid: Option<i64>,
+
+    /// The best ask, in quote lots per base lot.
+    pub best_ask: Option<i64>,
+
+    /// The oracle price, in quote tokens per base token.
+    pub oracle_price: Option<f64>,
+
+    /// Base tokens held, in native units.
+    pub base_inventory: i64,
+
+    /// Base lots of the resting bids.
+    pub resting_bid_lots: i64,
+
+    pub open_orders: usize,
+}
+
+/// The reason an order was rejected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RiskViolation {
+    /// The order is worth `notional` quote tokens, above `max`.
+    OrderNotional { notional: f64, max: f64 },
+
+    /// Filling the bids would bring the position to `position` base tokens, above `max`.
+    Position { position: f64, max: f64 },
+
+    /// The order price is more than `max_bps` away from the `reference` price, both in quote
+    /// tokens per base token.
+    PriceBand {
+        price: f64,
+        reference: f64,
+        max_bps: f64,
+    },
+
+    /// The orders would bring the open orders to `open_orders`, above `max`.
+    OpenOrders { open_orders: usize, max: usize },
+
+    /// `orders` orders were already placed in the last minute, and no more than `max` may be.
+    RateLimit { orders: usize, max: usize },
+}
+
+impl fmt::Display for RiskViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OrderNotional { notional, max } => {
+                write!(f, "Order notional {notional} exceeds the maximum {max}")
+            }
+            Self::Position { position, max } => {
+                write!(f, "Position {position} would exceed the maximum {max}")
+            }
+            Self::PriceBand {
+                price,
+                reference,
+                max_bps,
+            } => write!(
+                f,
+                "Order price {price} is more than {max_bps} bps away from the reference price \
+                 {reference}"
+            ),
+            Self::OpenOrders { open_orders, max } => {
+                write!(
+                    f,
+                    "{open_orders} open orders would exceed the maximum {max}"
+                )
+            }
+            Self::RateLimit { orders, max } => write!(
+                f,
+                "{orders} orders placed in the last minute, the maximum is {max}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RiskViolation {}
+
+/// Checks `orders`, placed together, against `limits`, except the rate limit.
+pub fn check_orders(
+    limits: &RiskLimits,
+    market: &MarketContext,
+    state: &RiskState,
+    orders: &[(Side, OrderLots)],
+) -> Result<(), RiskViolation> {
+    let quote_factor = 10f64.powi(market.market.quote_decimals as i32);
+    let quote_lot_size = market.market.quote_lot_size as f64;
+    let lots_per_ui_price = 1.0 / market.price_lots_to_ui(1);
+
+    for (side, lots) in orders {
+        let base_value = lots.max_base_lots as i128 * lots.price_lots as i128;
+        let notional_lots = match side {
+            Side::Bid => base_value.min(lots.max_quote_lots as i128),
+            Side::Ask => base_value,
+        };
+        let notional = notional_lots as f64 * quote_lot_size / quote_factor;
+        if let Some(max) = limits.max_order_notional {
+            if notional > max {
+                return Err(RiskViolation::OrderNotional { notional, max });
+            }
+        }
+
+        if let Some(max_bps) = limits.price_band_bps {
+            let oracle = state.oracle_price.map(|price| price * lots_per_ui_price);
+            let book = match side {
+                Side::Bid => state.best_ask,
+                Side::Ask => state.best_bid,
+            };
+            let reference = match limits.price_band_reference {
+                PriceReference::Book => book.map(|price| price as f64).or(oracle),
+                PriceReference::Oracle => oracle,
+            };
+            if let Some(reference) = reference {
+                let price = lots.price_lots as f64;
+                let band = reference * max_bps / 10_000.0;
+                let outside = match side {
+                    Side::Bid => price > reference + band,
+                    Side::Ask => price < reference - band,
+                };
+                if outside {
+                    return Err(RiskViolation::PriceBand {
+                        price: market.price_lots_to_ui(lots.price_lots),
+                        reference: reference / lots_per_ui_price,
+                        max_bps,
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(max) = limits.max_position {
+        let bid_lots: i64 = orders
+            .iter()
+            .filter(|(side, _)| *side == Side::Bid)
+            .map(|(_, lots)| lots.max_base_lots as i64)
+            .sum();
+        let position_native = state.base_inventory
+            + (state.resting_bid_lots + bid_lots) * market.market.base_lot_size;
+        let position = market.base_native_to_ui(position_native);
+        if bid_lots > 0 && position > max {
+            return Err(RiskViolation::Position { position, max });
+        }
+    }
+
+    if let Some(max) = limits.max_open_orders {
+        let open_orders = state.open_orders + orders.len();
+        if open_orders > max {
+            return Err(RiskViolation::OpenOrders { open_orders, max });
+        }
+    }
+    Ok(())
+}
+
+/// Counts the orders placed in the last minute, shared by the clones of a client.
+///
+/// Builders `check` the limit and `reserve` the orders under the signature of their transaction,
+/// and `settle` turns the reservation into placed orders when it was sent, or releases it when
+/// it failed. Reservations count towards the limit like placed orders until they are settled or
+/// a minute passed, when their blockhash has expired, so transactions built but sent elsewhere
+/// can not bypass the limit.
+#[derive(Clone, Debug, Default)]
+pub struct OrderRateLimiter {
+    state: Arc<Mutex<RateLimiterState>>,
+}
+
+#[derive(Debug, Default)]
+struct RateLimiterState {
+    placed: VecDeque<Instant>,
+    reserved: HashMap<Signature, (Instant, usize)>,
+}
+
+impl OrderRateLimiter {
+    /// Checks that placing `orders` orders now keeps the orders placed or reserved in the last
+    /// minute within `max`.
+    pub fn check(&self, max: usize, orders: usize) -> Result<(), RiskViolation> {
+        let state = self.lock(Instant::now());
+        let reserved: usize = state.reserved.values().map(|(_, orders)| orders).sum();
+        let placed = state.placed.len() + reserved;
+        if placed + orders > max {
+            return Err(RiskViolation::RateLimit {
+                orders: placed,
+                max,
+            });
+        }
+        Ok(())
+    }
+
+    /// Counts `orders` orders placed now.
+    pub fn record(&self, orders: usize) {
+        let now = Instant::now();
+        self.lock(now)
+            .placed
+            .extend(std::iter::repeat(now).take(orders));
+    }
+
+    /// Reserves `orders` orders of the transaction `signature`, counted until it is settled or
+    /// expires.
+    pub fn reserve(&self, signature: Signature, orders: usize) {
+        let now = Instant::now();
+        self.lock(now).reserved.insert(signature, (now, orders));
+    }
+
+    /// Counts the orders reserved by the transaction `signature` as placed if it was `sent`, or
+    /// releases them otherwise. Transactions without a reservation are ignored.
+    pub fn settle(&self, signature: &Signature, sent: bool) {
+        let now = Instant::now();
+        let mut state = self.lock(now);
+        if let Some((_, orders)) = state.reserved.remove(signature) {
+            if sent {
+                state.placed.extend(std::iter::repeat(now).take(orders));
+            }
+        }
+    }
+
+    /// Locks the state, dropping the orders and reservations older than the window.
+    fn lock(&self, now: Instant) -> MutexGuard<'_, RateLimiterState> {
+        let mut state = self.state.lock().expect("rate limiter lock poisoned");
+        while state
+            .placed
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= RATE_LIMIT_WINDOW)
+        {
+            state.placed.pop_front();
+        }
+        state
+            .reserved
+            .retain(|_, (time, _)| now.duration_since(*time) < RATE_LIMIT_WINDOW);
+        state
+    }
+}
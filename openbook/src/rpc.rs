@@ -3,11 +3,16 @@
 use std::sync::Arc;
 
 use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcAccountInfoConfig};
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{address_lookup_table_account::AddressLookupTableAccount, pubkey::Pubkey};
 
 use anchor_lang::{AccountDeserialize, Discriminator};
 
-use openbook_v2::state::OpenOrdersAccount;
+use openbook_v2::state::{OpenOrdersAccount, OpenOrdersIndexer};
+
+use crate::{
+    instructions, lookup_table,
+    token::{self, MintInfo},
+};
 
 use solana_client::{
     rpc_config::RpcProgramAccountsConfig,
@@ -16,6 +21,9 @@ use solana_client::{
 
 use solana_account_decoder::UiAccountEncoding;
 
+/// Maximum number of accounts of a `getMultipleAccounts` request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
 /// Wrapper type for RpcClient providing additional functionality and enabling Debug trait implementation.
 ///
 /// This struct holds an `Arc` of `RpcClient` to ensure thread safety and efficient resource sharing.
@@ -66,6 +74,12 @@ impl Rpc {
         Ok(T::try_deserialize(&mut (&account.data as &[u8]))?)
     }
 
+    /// Scans `program` for the open orders accounts of `owner` with `getProgramAccounts`.
+    #[deprecated(
+        note = "`getProgramAccounts` is disabled by most RPC providers, read the accounts of the \
+                open orders indexer with `fetch_open_orders_indexer` and \
+                `fetch_open_orders_accounts` instead"
+    )]
     pub async fn fetch_openbook_accounts(
         &self,
         program: Pubkey,
@@ -97,4 +111,74 @@ impl Rpc {
             })
             .collect()
     }
+
+    /// Fetches the open orders indexer of `owner`, `None` if the owner never created an open
+    /// orders account.
+    pub async fn fetch_open_orders_indexer(
+        &self,
+        owner: &Pubkey,
+    ) -> anyhow::Result<Option<OpenOrdersIndexer>> {
+        let address = instructions::open_orders_indexer(owner);
+        let account = self
+            .inner()
+            .get_account_with_commitment(&address, self.inner().commitment())
+            .await?
+            .value;
+        account
+            .map(|account| OpenOrdersIndexer::try_deserialize(&mut (&account.data as &[u8])))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Fetches the open orders accounts at `addresses`, skipping the closed ones.
+    pub async fn fetch_open_orders_accounts(
+        &self,
+        addresses: &[Pubkey],
+    ) -> anyhow::Result<Vec<(Pubkey, OpenOrdersAccount)>> {
+        let mut accounts = vec![];
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let fetched = self.inner().get_multiple_accounts(chunk).await?;
+            for (address, account) in chunk.iter().zip(fetched) {
+                if let Some(account) = account {
+                    let account =
+                        OpenOrdersAccount::try_deserialize(&mut (&account.data as &[u8]))?;
+                    accounts.push((*address, account));
+                }
+            }
+        }
+        Ok(accounts)
+    }
+
+    /// Fetches the token program and current transfer fee of each of `mints`.
+    pub async fn fetch_mint_infos(&self, mints: &[Pubkey]) -> anyhow::Result<Vec<MintInfo>> {
+        let epoch = self.inner().get_epoch_info().await?.epoch;
+        self.inner()
+            .get_multiple_accounts(mints)
+            .await?
+            .into_iter()
+            .zip(mints)
+            .map(|(account, mint)| {
+                let account = account.ok_or_else(|| anyhow::anyhow!("Mint {mint} not found"))?;
+                token::decode_mint(&account.owner, &account.data, epoch)
+            })
+            .collect()
+    }
+
+    /// Fetches the address lookup tables at `addresses`.
+    pub async fn fetch_lookup_tables(
+        &self,
+        addresses: &[Pubkey],
+    ) -> anyhow::Result<Vec<AddressLookupTableAccount>> {
+        self.inner()
+            .get_multiple_accounts(addresses)
+            .await?
+            .into_iter()
+            .zip(addresses)
+            .map(|(account, address)| {
+                let account =
+                    account.ok_or_else(|| anyhow::anyhow!("Lookup table {address} not found"))?;
+                lookup_table::decode(*address, &account.data)
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,117 @@
+//! Serde representations shared by the serializable types of the crate.
+//!
+//! Human-readable formats such as JSON get base58 public keys and base64 bytes, while binary
+//! formats keep the raw bytes. Sides are always serialized by name.
+
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use openbook_v2::state::Side;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use solana_sdk::pubkey::Pubkey;
+
+/// Serializes a `Pubkey` as base58 in human-readable formats.
+pub mod pubkey {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(key: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&key.to_string())
+        } else {
+            key.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        if deserializer.is_human_readable() {
+            let key = String::deserialize(deserializer)?;
+            Pubkey::from_str(&key).map_err(D::Error::custom)
+        } else {
+            Pubkey::deserialize(deserializer)
+        }
+    }
+}
+
+/// Serializes an `Option<Pubkey>` as an optional base58 string in human-readable formats.
+pub mod option_pubkey {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super::pubkey")] Pubkey);
+
+    pub fn serialize<S: Serializer>(
+        key: &Option<Pubkey>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        key.map(Wrapper).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Pubkey>, D::Error> {
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(key)| key))
+    }
+}
+
+/// Serializes a `Vec<Pubkey>` as base58 strings in human-readable formats.
+pub mod pubkeys {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super::pubkey")] Pubkey);
+
+    pub fn serialize<S: Serializer>(keys: &[Pubkey], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(keys.iter().map(|key| Wrapper(*key)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Pubkey>, D::Error> {
+        Ok(Vec::<Wrapper>::deserialize(deserializer)?
+            .into_iter()
+            .map(|Wrapper(key)| key)
+            .collect())
+    }
+}
+
+/// Serializes bytes as base64 in human-readable formats.
+pub mod bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(bytes))
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            let encoded = String::deserialize(deserializer)?;
+            STANDARD.decode(encoded).map_err(D::Error::custom)
+        } else {
+            Vec::<u8>::deserialize(deserializer)
+        }
+    }
+}
+
+/// Serializes a `Side` as `"bid"` or `"ask"`.
+pub mod side {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(side: &Side, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match side {
+            Side::Bid => "bid",
+            Side::Ask => "ask",
+        })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Side, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "bid" => Ok(Side::Bid),
+            "ask" => Ok(Side::Ask),
+            other => Err(D::Error::custom(format!("invalid side {other}"))),
+        }
+    }
+}
@@ -0,0 +1,430 @@
+//! Serializable snapshots of the market state seen by `OBClient`.
+//!
+//! Every account snapshot keeps the raw account bytes and the slot they were read at next to the
+//! decoded state, so archived snapshots can be decoded again, replayed in tests and compared
+//! across slots. Snapshots are encoded as JSON, or as compact binary with `bincode`.
+
+use anchor_lang::AccountDeserialize;
+use anyhow::Result;
+use openbook_v2::state::{BookSide, EventHeap, Market, OpenOrdersAccount};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    book::{self, BookOrder, OrderDiff},
+    context::MarketContext,
+    events::{self, MarketEvent},
+    serde_util,
+    token::MintInfo,
+};
+
+/// The raw bytes of an account at a slot, with their decoded state.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountSnapshot<T> {
+    #[serde(with = "serde_util::pubkey")]
+    pub address: Pubkey,
+
+    /// The slot the account was read at.
+    pub slot: u64,
+
+    /// The raw account data, including the anchor discriminator.
+    #[serde(with = "serde_util::bytes")]
+    pub data: Vec<u8>,
+
+    pub state: T,
+}
+
+impl<T> AccountSnapshot<T> {
+    /// Decodes the raw account data again, e.g. to replay it through the program's types.
+    pub fn account<A: AccountDeserialize>(&self) -> Result<A> {
+        Ok(A::try_deserialize(&mut self.data.as_slice())?)
+    }
+}
+
+pub type MarketContextSnapshot = AccountSnapshot<MarketState>;
+pub type BookSideSnapshot = AccountSnapshot<Vec<BookOrder>>;
+pub type EventHeapSnapshot = AccountSnapshot<Vec<EventRecord>>;
+pub type OpenOrdersSnapshot = AccountSnapshot<OpenOrdersState>;
+
+/// The parameters and totals of a market, with the token programs and transfer fees of its mints.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketState {
+    pub name: String,
+    #[serde(with = "serde_util::pubkey")]
+    pub base_mint: Pubkey,
+    #[serde(with = "serde_util::pubkey")]
+    pub quote_mint: Pubkey,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    pub base_lot_size: i64,
+    pub quote_lot_size: i64,
+    pub maker_fee: i64,
+    pub taker_fee: i64,
+    pub time_expiry: i64,
+    #[serde(with = "serde_util::pubkey")]
+    pub bids: Pubkey,
+    #[serde(with = "serde_util::pubkey")]
+    pub asks: Pubkey,
+    #[serde(with = "serde_util::pubkey")]
+    pub event_heap: Pubkey,
+    #[serde(with = "serde_util::pubkey")]
+    pub market_base_vault: Pubkey,
+    #[serde(with = "serde_util::pubkey")]
+    pub market_quote_vault: Pubkey,
+    #[serde(with = "serde_util::option_pubkey")]
+    pub oracle_a: Option<Pubkey>,
+    #[serde(with = "serde_util::option_pubkey")]
+    pub oracle_b: Option<Pubkey>,
+
+    /// The sequence number of the next order placed on the market.
+    pub seq_num: u64,
+
+    pub base_deposit_total: u64,
+    pub quote_deposit_total: u64,
+    pub base_mint_info: MintInfo,
+    pub quote_mint_info: MintInfo,
+}
+
+/// A decoded event of the event heap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventRecord {
+    Fill {
+        /// The slot of the event in the event heap.
+        heap_slot: usize,
+        /// 0 for a bid, 1 for an ask.
+        taker_side: u8,
+        maker_out: bool,
+        maker_slot: u8,
+        timestamp: u64,
+        market_seq_num: u64,
+        #[serde(with = "serde_util::pubkey")]
+        maker: Pubkey,
+        #[serde(with = "serde_util::pubkey")]
+        taker: Pubkey,
+        taker_client_order_id: u64,
+        maker_client_order_id: u64,
+        price: i64,
+        quantity: i64,
+    },
+    Out {
+        heap_slot: usize,
+        /// 0 for a bid, 1 for an ask.
+        side: u8,
+        owner_slot: u8,
+        timestamp: u64,
+        seq_num: u64,
+        #[serde(with = "serde_util::pubkey")]
+        owner: Pubkey,
+        quantity: i64,
+    },
+}
+
+impl EventRecord {
+    pub fn new(heap_slot: usize, event: &MarketEvent) -> Self {
+        match event {
+            MarketEvent::Fill(fill) => Self::Fill {
+                heap_slot,
+                taker_side: fill.taker_side,
+                maker_out: fill.maker_out != 0,
+                maker_slot: fill.maker_slot,
+                timestamp: fill.timestamp,
+                market_seq_num: fill.market_seq_num,
+                maker: fill.maker,
+                taker: fill.taker,
+                taker_client_order_id: fill.taker_client_order_id,
+                maker_client_order_id: fill.maker_client_order_id,
+                price: fill.price,
+                quantity: fill.quantity,
+            },
+            MarketEvent::Out(out) => Self::Out {
+                heap_slot,
+                side: out.side,
+                owner_slot: out.owner_slot,
+                timestamp: out.timestamp,
+                seq_num: out.seq_num,
+                owner: out.owner,
+                quantity: out.quantity,
+            },
+        }
+    }
+}
+
+/// The balances and orders of an open orders account.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenOrdersState {
+    pub name: String,
+    #[serde(with = "serde_util::pubkey")]
+    pub owner: Pubkey,
+    #[serde(with = "serde_util::pubkey")]
+    pub market: Pubkey,
+    #[serde(with = "serde_util::option_pubkey")]
+    pub delegate: Option<Pubkey>,
+    pub account_num: u32,
+    pub bids_base_lots: i64,
+    /// The quote locked by the bids, in quote lots, excluding the maker fees.
+    pub bids_quote_lots: i64,
+    pub asks_base_lots: i64,
+    pub base_free_native: u64,
+    pub quote_free_native: u64,
+    pub locked_maker_fees: u64,
+    pub referrer_rebates_available: u64,
+    pub orders: Vec<OpenOrderState>,
+}
+
+/// An order of an open orders account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenOrderState {
+    pub order_id: u128,
+    pub client_order_id: u64,
+    /// 0 for a bid, 1 for an ask.
+    pub side: u8,
+    /// The price the order locks funds at, in quote lots per base lot.
+    pub locked_price: i64,
+}
+
+/// A consistent view of a market at one slot: its parameters, book sides, event heap and the
+/// state of some open orders accounts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketSnapshot {
+    pub slot: u64,
+
+    /// The unix timestamp used to skip expired orders when decoding the book sides.
+    pub timestamp: u64,
+
+    pub market: MarketContextSnapshot,
+    pub bids: BookSideSnapshot,
+    pub asks: BookSideSnapshot,
+    pub event_heap: EventHeapSnapshot,
+    pub open_orders: Vec<OpenOrdersSnapshot>,
+}
+
+/// The changes of a market between two snapshots.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub from_slot: u64,
+    pub to_slot: u64,
+    pub bids: OrderDiff,
+    pub asks: OrderDiff,
+
+    /// Events in the event heap of the new snapshot but not of the old one.
+    pub new_events: Vec<EventRecord>,
+
+    /// Open orders accounts whose state changed, or which are only in the new snapshot.
+    #[serde(with = "serde_util::pubkeys")]
+    pub open_orders_changed: Vec<Pubkey>,
+}
+
+impl MarketSnapshot {
+    /// Rebuilds the `MarketContext` the snapshot was taken with.
+    pub fn context(&self) -> Result<MarketContext> {
+        Ok(MarketContext {
+            address: self.market.address,
+            market: self.market.account::<Market>()?,
+            base_mint_info: self.market.state.base_mint_info,
+            quote_mint_info: self.market.state.quote_mint_info,
+        })
+    }
+
+    /// Compares this snapshot with a later `other` one.
+    pub fn diff(&self, other: &MarketSnapshot) -> SnapshotDiff {
+        SnapshotDiff {
+            from_slot: self.slot,
+            to_slot: other.slot,
+            bids: book::diff_orders(&self.bids.state, &other.bids.state),
+            asks: book::diff_orders(&self.asks.state, &other.asks.state),
+            new_events: other
+                .event_heap
+                .state
+                .iter()
+                .filter(|event| !self.event_heap.state.contains(event))
+                .copied()
+                .collect(),
+            open_orders_changed: other
+                .open_orders
+                .iter()
+                .filter(|account| {
+                    !self
+                        .open_orders
+                        .iter()
+                        .any(|old| old.address == account.address && old.state == account.state)
+                })
+                .map(|account| account.address)
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        to_json(self)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        from_json(json)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        to_bytes(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        from_bytes(bytes)
+    }
+}
+
+/// Snapshots the market account `data` of `context`.
+pub fn market(context: &MarketContext, slot: u64, data: Vec<u8>) -> MarketContextSnapshot {
+    let market = &context.market;
+    AccountSnapshot {
+        address: context.address,
+        slot,
+        data,
+        state: MarketState {
+            name: market.name().to_string(),
+            base_mint: market.base_mint,
+            quote_mint: market.quote_mint,
+            base_decimals: market.base_decimals,
+            quote_decimals: market.quote_decimals,
+            base_lot_size: market.base_lot_size,
+            quote_lot_size: market.quote_lot_size,
+            maker_fee: market.maker_fee,
+            taker_fee: market.taker_fee,
+            time_expiry: market.time_expiry,
+            bids: market.bids,
+            asks: market.asks,
+            event_heap: market.event_heap,
+            market_base_vault: market.market_base_vault,
+            market_quote_vault: market.market_quote_vault,
+            oracle_a: market.oracle_a.into(),
+            oracle_b: market.oracle_b.into(),
+            seq_num: market.seq_num,
+            base_deposit_total: market.base_deposit_total,
+            quote_deposit_total: market.quote_deposit_total,
+            base_mint_info: context.base_mint_info,
+            quote_mint_info: context.quote_mint_info,
+        },
+    }
+}
+
+/// Snapshots the book side account `data`, skipping the orders expired at `now_ts`.
+pub fn book_side(
+    address: Pubkey,
+    slot: u64,
+    data: Vec<u8>,
+    now_ts: u64,
+) -> Result<BookSideSnapshot> {
+    let book_side = BookSide::try_deserialize(&mut data.as_slice())?;
+    Ok(AccountSnapshot {
+        address,
+        slot,
+        state: book::book_side_orders(&book_side, now_ts, None),
+        data,
+    })
+}
+
+/// Snapshots the event heap account `data`.
+pub fn event_heap(address: Pubkey, slot: u64, data: Vec<u8>) -> Result<EventHeapSnapshot> {
+    let event_heap = EventHeap::try_deserialize(&mut data.as_slice())?;
+    Ok(AccountSnapshot {
+        address,
+        slot,
+        state: events::pending_events(&event_heap)
+            .iter()
+            .map(|(heap_slot, event)| EventRecord::new(*heap_slot, event))
+            .collect(),
+        data,
+    })
+}
+
+/// Finds the events added between successive snapshots of an event heap.
+///
+/// The event heap numbers the events pushed to it with its `seq_num`, so events pushed and
+/// consumed between two snapshots, never seen, are still counted as missed.
+#[derive(Clone, Debug, Default)]
+pub struct EventHeapTracker {
+    seq_num: Option<u64>,
+    events: Vec<EventRecord>,
+}
+
+/// The events added to an event heap since the previous snapshot, see `EventHeapTracker`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NewEvents {
+    /// The pending events not in the previous snapshot, or all of them in the first one.
+    pub events: Vec<EventRecord>,
+
+    /// Number of events pushed and consumed since the previous snapshot, which fills may have
+    /// been among.
+    pub missed: u64,
+}
+
+impl EventHeapTracker {
+    /// Compares `event_heap` with the previous snapshot.
+    pub fn update(&mut self, event_heap: &EventHeapSnapshot) -> Result<NewEvents> {
+        let seq_num = event_heap.account::<EventHeap>()?.header.seq_num;
+        let events: Vec<EventRecord> = event_heap
+            .state
+            .iter()
+            .filter(|event| !self.events.contains(event))
+            .copied()
+            .collect();
+        let missed = self.seq_num.map_or(0, |last| {
+            seq_num
+                .saturating_sub(last)
+                .saturating_sub(events.len() as u64)
+        });
+        self.seq_num = Some(seq_num);
+        self.events = event_heap.state.clone();
+        Ok(NewEvents { events, missed })
+    }
+}
+
+/// Snapshots the open orders account `data`.
+pub fn open_orders(address: Pubkey, slot: u64, data: Vec<u8>) -> Result<OpenOrdersSnapshot> {
+    let account = OpenOrdersAccount::try_deserialize(&mut data.as_slice())?;
+    let position = &account.position;
+    Ok(AccountSnapshot {
+        address,
+        slot,
+        state: OpenOrdersState {
+            name: account.name().to_string(),
+            owner: account.owner,
+            market: account.market,
+            delegate: account.delegate.into(),
+            account_num: account.account_num,
+            bids_base_lots: position.bids_base_lots,
+            bids_quote_lots: position.bids_quote_lots,
+            asks_base_lots: position.asks_base_lots,
+            base_free_native: position.base_free_native,
+            quote_free_native: position.quote_free_native,
+            locked_maker_fees: position.locked_maker_fees,
+            referrer_rebates_available: position.referrer_rebates_available,
+            orders: account
+                .all_orders_in_use()
+                .map(|order| OpenOrderState {
+                    order_id: order.id,
+                    client_order_id: order.client_id,
+                    side: order.side_and_tree().side() as u8,
+                    locked_price: order.locked_price,
+                })
+                .collect(),
+        },
+        data,
+    })
+}
+
+/// Encodes `value` as JSON.
+pub fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string(value)?)
+}
+
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Encodes `value` as compact binary.
+pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    Ok(bincode::serialize(value)?)
+}
+
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    Ok(bincode::deserialize(bytes)?)
+}
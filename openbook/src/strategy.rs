@@ -0,0 +1,437 @@
+//! A `Strategy` trait for automated trading, and a runtime running strategies against a market.
+//!
+//! Strategies react to the book, their fills and a timer by returning `OrderIntent`s, which the
+//! `StrategyRuntime` executes through `OBClient`. The same strategies can be evaluated offline
+//! with `backtest::IntentStrategy`.
+//!
+//! The runtime polls a snapshot of the market and the open orders account at one slot, and
+//! reconciles the orders it placed with the orders actually open: orders it did not place are
+//! cancelled, and placed orders no longer open (filled, expired or dropped as post-only) are
+//! forgotten. On shutdown, all orders are cancelled.
+//!
+//! Fills are found in the event heap, so fills consumed by a crank between two polls are never
+//! seen. The event heap sequence number still tells they may have happened: they are logged and
+//! counted in `StrategyRuntime::missed_events`.
+
+use std::{future::Future, time::Duration};
+
+use anyhow::Result;
+use openbook_v2::state::Side;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    book::BookOrder,
+    config::OrderType,
+    context::{MarketContext, OrderLots},
+    ob_client::OBClient,
+    serde_util,
+    snapshot::{EventHeapTracker, EventRecord, MarketSnapshot, OpenOrderState},
+};
+
+/// A limit order of `quote_size` quote tokens at `price_lots`, in quote lots per base lot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quote {
+    pub price_lots: i64,
+    pub quote_size: u64,
+}
+
+/// The orders `OBClient::cancel_all_and_place_orders` places for `bids` and `asks`, with the
+/// client order ids the program assigns them by position: `0..bids.len()` for the bids, and the
+/// following ids for the asks.
+pub fn replace_orders<'a>(
+    bids: &'a [Quote],
+    asks: &'a [Quote],
+) -> impl Iterator<Item = (u64, Side, &'a Quote)> {
+    let bids = bids.iter().map(|quote| (Side::Bid, quote));
+    let asks = asks.iter().map(|quote| (Side::Ask, quote));
+    bids.chain(asks)
+        .enumerate()
+        .map(|(client_order_id, (side, quote))| (client_order_id as u64, side, quote))
+}
+
+/// An order action requested by a strategy.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OrderIntent {
+    /// Places a limit order, like `OBClient::place_limit_order`.
+    Place {
+        side: Side,
+        limit_price: f64,
+        quote_size: u64,
+    },
+
+    /// Places an order with explicit lots and order type, like `OBClient::place_order`.
+    PlaceLots {
+        side: Side,
+        lots: OrderLots,
+        order_type: OrderType,
+    },
+
+    /// Cancels an open order by its order id.
+    Cancel { order_id: u128 },
+
+    /// Cancels all open orders.
+    CancelAll,
+
+    /// Cancels all open orders and places `bids` and `asks` in one transaction, like
+    /// `OBClient::cancel_all_and_place_orders`, with the client order ids of `replace_orders`.
+    Replace { bids: Vec<Quote>, asks: Vec<Quote> },
+}
+
+/// A fill of one of the strategy's orders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fill {
+    pub slot: u64,
+    pub client_order_id: u64,
+    #[serde(with = "serde_util::side")]
+    pub side: Side,
+    pub price_lots: i64,
+
+    /// The filled quantity, in base lots.
+    pub quantity: i64,
+
+    /// Whether the order was resting on the book, rather than taking liquidity.
+    pub maker: bool,
+}
+
+impl Fill {
+    /// The fill of `open_orders_account` in the event heap record `event`, if any.
+    pub fn from_event(
+        slot: u64,
+        event: &EventRecord,
+        open_orders_account: &Pubkey,
+    ) -> Option<Self> {
+        let EventRecord::Fill {
+            taker_side,
+            maker,
+            taker,
+            taker_client_order_id,
+            maker_client_order_id,
+            price,
+            quantity,
+            ..
+        } = *event
+        else {
+            return None;
+        };
+        let taker_side = if taker_side == 0 {
+            Side::Bid
+        } else {
+            Side::Ask
+        };
+        let (client_order_id, side, maker) = if maker == *open_orders_account {
+            (maker_client_order_id, taker_side.invert_side(), true)
+        } else if taker == *open_orders_account {
+            (taker_client_order_id, taker_side, false)
+        } else {
+            return None;
+        };
+        Some(Self {
+            slot,
+            client_order_id,
+            side,
+            price_lots: price,
+            quantity,
+            maker,
+        })
+    }
+}
+
+/// The market state passed to a strategy.
+pub struct StrategyContext<'a> {
+    pub market: &'a MarketContext,
+    pub slot: u64,
+
+    /// The unix timestamp of the state, in seconds.
+    pub timestamp: u64,
+
+    /// The bids, from best to worst price.
+    pub bids: &'a [BookOrder],
+
+    /// The asks, from best to worst price.
+    pub asks: &'a [BookOrder],
+
+    /// The strategy's open orders.
+    pub open_orders: &'a [OpenOrderState],
+
+    /// Base tokens held, in native units: the owner's wallet balance and the free and locked base
+    /// of the open orders account. In a backtest, the simulated position.
+    pub base_inventory: i64,
+
+    /// The oracle price of the market, in quote tokens per base token, if it has an oracle and
+    /// the price is usable, see `oracle::OraclePrice::usable`.
+    pub oracle_price: Option<f64>,
+}
+
+/// Automated trading logic.
+pub trait Strategy {
+    /// Called when the book changed.
+    fn on_book(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent>;
+
+    /// Called for every fill of one of the strategy's orders.
+    fn on_fill(&mut self, _ctx: &StrategyContext, _fill: &Fill) -> Vec<OrderIntent> {
+        vec![]
+    }
+
+    /// Called every `RuntimeConfig::timer_interval`.
+    fn on_timer(&mut self, _ctx: &StrategyContext) -> Vec<OrderIntent> {
+        vec![]
+    }
+
+    /// Whether the strategy finished, stopping `StrategyRuntime::run`.
+    fn is_done(&self) -> bool {
+        false
+    }
+}
+
+/// Settings of a `StrategyRuntime`.
+#[derive(Clone, Copy, Debug)]
+pub struct RuntimeConfig {
+    /// Interval between two snapshots of the market.
+    pub poll_interval: Duration,
+
+    /// Interval between two calls of `Strategy::on_timer`.
+    pub timer_interval: Duration,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            timer_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Runs a `Strategy` against the market of an `OBClient`.
+pub struct StrategyRuntime<S> {
+    pub client: OBClient,
+    pub strategy: S,
+    config: RuntimeConfig,
+
+    /// Client order ids of the orders placed by the runtime and believed to be open.
+    intended: Vec<u64>,
+    snapshot: Option<MarketSnapshot>,
+    events: EventHeapTracker,
+    missed_events: u64,
+    base_inventory: i64,
+    oracle_price: Option<f64>,
+}
+
+impl<S: Strategy> StrategyRuntime<S> {
+    pub fn new(client: OBClient, strategy: S, config: RuntimeConfig) -> Self {
+        Self {
+            client,
+            strategy,
+            config,
+            intended: vec![],
+            snapshot: None,
+            events: EventHeapTracker::default(),
+            missed_events: 0,
+            base_inventory: 0,
+            oracle_price: None,
+        }
+    }
+
+    /// Runs the strategy until `shutdown` completes or the strategy is done, then cancels all
+    /// orders.
+    ///
+    /// Failed polls and intents are logged and retried on the next poll.
+    pub async fn run(&mut self, shutdown: impl Future<Output = ()>) -> Result<()> {
+        tokio::pin!(shutdown);
+        let mut poll = tokio::time::interval(self.config.poll_interval);
+        let mut timer = tokio::time::interval(self.config.timer_interval);
+        while !self.strategy.is_done() {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = poll.tick() => {
+                    if let Err(err) = self.poll().await {
+                        tracing::warn!("Strategy poll failed: {err:#}");
+                    }
+                }
+                _ = timer.tick() => {
+                    if let Err(err) = self.timer().await {
+                        tracing::warn!("Strategy timer failed: {err:#}");
+                    }
+                }
+            }
+        }
+        self.shutdown().await
+    }
+
+    /// Snapshots the market, reconciles the open orders, and calls the strategy with the new
+    /// fills and, if it changed, the book. Failed cancels of unexpected orders are logged and
+    /// retried on the next poll.
+    pub async fn poll(&mut self) -> Result<()> {
+        let snapshot = self.client.snapshot().await?;
+        let (balances, oracle_price) = self.client.balances_and_oracle_price(&snapshot).await?;
+        self.base_inventory = balances.base() as i64;
+        self.oracle_price = oracle_price;
+        let open_orders = open_orders(&snapshot);
+
+        let unexpected: Vec<u128> = open_orders
+            .iter()
+            .filter(|order| !self.intended.contains(&order.client_order_id))
+            .map(|order| order.order_id)
+            .collect();
+        self.intended.retain(|client_order_id| {
+            open_orders
+                .iter()
+                .any(|order| order.client_order_id == *client_order_id)
+        });
+        for order_id in unexpected {
+            tracing::info!("Cancelling order {order_id} not placed by the strategy");
+            if let Err(err) = self.execute(OrderIntent::Cancel { order_id }).await {
+                tracing::warn!("Failed to cancel order {order_id}: {err:#}");
+            }
+        }
+
+        let new_events = self.events.update(&snapshot.event_heap)?;
+        if new_events.missed > 0 {
+            tracing::warn!(
+                "{} events were consumed since the previous poll, fills may have been missed",
+                new_events.missed
+            );
+            self.missed_events += new_events.missed;
+        }
+        let fills: Vec<Fill> = new_events
+            .events
+            .iter()
+            .filter_map(|event| {
+                Fill::from_event(snapshot.slot, event, &self.client.open_orders_account)
+            })
+            .collect();
+
+        let book_changed = self.snapshot.as_ref().map_or(true, |previous| {
+            previous.bids.state != snapshot.bids.state || previous.asks.state != snapshot.asks.state
+        });
+        self.snapshot = Some(snapshot);
+
+        let mut intents = vec![];
+        for fill in &fills {
+            intents.extend(self.with_context(|strategy, ctx| strategy.on_fill(ctx, fill)));
+        }
+        if book_changed {
+            intents.extend(self.with_context(|strategy, ctx| strategy.on_book(ctx)));
+        }
+        self.execute_all(intents).await
+    }
+
+    /// Calls `Strategy::on_timer` with the latest snapshot.
+    pub async fn timer(&mut self) -> Result<()> {
+        let intents = self.with_context(|strategy, ctx| strategy.on_timer(ctx));
+        self.execute_all(intents).await
+    }
+
+    /// Cancels all orders of the open orders account.
+    pub async fn shutdown(&mut self) -> Result<()> {
+        self.execute(OrderIntent::CancelAll).await
+    }
+
+    /// The client order ids of the orders placed by the runtime and believed to be open.
+    pub fn intended_orders(&self) -> &[u64] {
+        &self.intended
+    }
+
+    /// Number of events consumed between two polls before being seen, which fills of the
+    /// strategy may have been among.
+    pub fn missed_events(&self) -> u64 {
+        self.missed_events
+    }
+
+    fn with_context(
+        &mut self,
+        call: impl FnOnce(&mut S, &StrategyContext) -> Vec<OrderIntent>,
+    ) -> Vec<OrderIntent> {
+        let Some(snapshot) = &self.snapshot else {
+            return vec![];
+        };
+        let ctx = StrategyContext {
+            market: &self.client.context,
+            slot: snapshot.slot,
+            timestamp: snapshot.timestamp,
+            bids: &snapshot.bids.state,
+            asks: &snapshot.asks.state,
+            open_orders: open_orders(snapshot),
+            base_inventory: self.base_inventory,
+            oracle_price: self.oracle_price,
+        };
+        call(&mut self.strategy, &ctx)
+    }
+
+    /// Executes every intent, even after a failure, logging each failed intent and returning an
+    /// error counting them.
+    async fn execute_all(&mut self, intents: Vec<OrderIntent>) -> Result<()> {
+        let total = intents.len();
+        let mut failed = 0;
+        for intent in intents {
+            let description = format!("{intent:?}");
+            if let Err(err) = self.execute(intent).await {
+                tracing::warn!("Strategy intent {description} failed: {err:#}");
+                failed += 1;
+            }
+        }
+        anyhow::ensure!(failed == 0, "{failed} of {total} strategy intents failed");
+        Ok(())
+    }
+
+    async fn execute(&mut self, intent: OrderIntent) -> Result<()> {
+        let trx = match intent {
+            OrderIntent::Place {
+                side,
+                limit_price,
+                quote_size,
+            } => {
+                let client_order_id = rand::random();
+                let trx = self
+                    .client
+                    .place_limit_order_with_client_id(
+                        limit_price,
+                        quote_size,
+                        side,
+                        client_order_id,
+                    )
+                    .await?;
+                self.intended.push(client_order_id);
+                trx
+            }
+            OrderIntent::PlaceLots {
+                side,
+                lots,
+                order_type,
+            } => {
+                let client_order_id = rand::random();
+                let trx = self
+                    .client
+                    .place_order(side, lots, order_type.into(), client_order_id)
+                    .await?;
+                self.intended.push(client_order_id);
+                trx
+            }
+            OrderIntent::Cancel { order_id } => self.client.cancel_limit_order(order_id).await?,
+            OrderIntent::CancelAll => {
+                self.intended.clear();
+                self.client.cancel_all().await?
+            }
+            OrderIntent::Replace { bids, asks } => {
+                let trx = self
+                    .client
+                    .cancel_all_and_place_orders(&bids, &asks)
+                    .await?;
+                self.intended = replace_orders(&bids, &asks)
+                    .map(|(client_order_id, ..)| client_order_id)
+                    .collect();
+                trx
+            }
+        };
+        self.client.send_trx(&trx).await?;
+        Ok(())
+    }
+}
+
+fn open_orders(snapshot: &MarketSnapshot) -> &[OpenOrderState] {
+    snapshot
+        .open_orders
+        .first()
+        .map_or(&[], |account| account.state.orders.as_slice())
+}
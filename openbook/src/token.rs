@@ -0,0 +1,162 @@
+//! Builders for the token account instructions accompanying OpenBook instructions: creating
+//! associated token accounts, and wrapping and unwrapping native SOL.
+
+use anchor_spl::{
+    token::spl_token,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{
+            transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+        },
+    },
+};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    instruction::Instruction, program_pack::Pack, pubkey::Pubkey, system_instruction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+
+use crate::serde_util;
+
+const MAX_FEE_BASIS_POINTS: u128 = 10_000;
+
+/// The token program and transfer fee of a mint.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MintInfo {
+    /// The program owning the mint, either the Token or the Token-2022 program.
+    #[serde(with = "serde_util::pubkey")]
+    pub token_program: Pubkey,
+
+    /// The transfer fee of the current epoch, for Token-2022 mints with the extension.
+    pub transfer_fee: Option<TransferFee>,
+}
+
+/// A Token-2022 transfer fee, withheld from the amount received by the destination account.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferFee {
+    pub basis_points: u16,
+
+    /// The maximum fee of a transfer, in native units.
+    pub maximum_fee: u64,
+}
+
+impl TransferFee {
+    /// The fee withheld when transferring `amount`, rounded up as the Token-2022 program does.
+    pub fn fee(&self, amount: u64) -> u64 {
+        let basis_points = self.basis_points as u128;
+        if basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * basis_points).div_ceil(MAX_FEE_BASIS_POINTS);
+        fee.min(self.maximum_fee as u128) as u64
+    }
+
+    /// The amount received when transferring `amount`.
+    pub fn amount_after_fee(&self, amount: u64) -> u64 {
+        amount - self.fee(amount)
+    }
+
+    /// The amount to transfer so that `received` is received after the fee.
+    pub fn amount_before_fee(&self, received: u64) -> u64 {
+        let basis_points = self.basis_points as u128;
+        if basis_points == 0 || received == 0 {
+            return received;
+        }
+        if basis_points == MAX_FEE_BASIS_POINTS {
+            return received.saturating_add(self.maximum_fee);
+        }
+        let raw =
+            (received as u128 * MAX_FEE_BASIS_POINTS).div_ceil(MAX_FEE_BASIS_POINTS - basis_points);
+        if raw - received as u128 >= self.maximum_fee as u128 {
+            received.saturating_add(self.maximum_fee)
+        } else {
+            raw as u64
+        }
+    }
+}
+
+/// Decodes the mint account `data` owned by `owner`, with the transfer fee of `epoch`.
+pub fn decode_mint(owner: &Pubkey, data: &[u8], epoch: u64) -> Result<MintInfo> {
+    if *owner == spl_token::id() {
+        return Ok(MintInfo {
+            token_program: *owner,
+            transfer_fee: None,
+        });
+    }
+    if *owner != spl_token_2022::id() {
+        bail!("Mint is owned by {owner}, which is not a token program");
+    }
+
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(data)?;
+    let transfer_fee = mint
+        .get_extension::<TransferFeeConfig>()
+        .ok()
+        .map(|config| {
+            let fee = config.get_epoch_fee(epoch);
+            TransferFee {
+                basis_points: u16::from(fee.transfer_fee_basis_points),
+                maximum_fee: u64::from(fee.maximum_fee),
+            }
+        })
+        .filter(|fee| fee.basis_points > 0);
+
+    Ok(MintInfo {
+        token_program: *owner,
+        transfer_fee,
+    })
+}
+
+/// Returns whether `mint` is the wrapped SOL mint.
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::id()
+}
+
+/// Creates the associated token account of `owner` for `mint`, succeeding if it already exists.
+pub fn create_ata_idempotent(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    create_associated_token_account_idempotent(payer, owner, mint, token_program)
+}
+
+/// Wraps `lamports` of `owner` into its existing wrapped SOL associated token account.
+pub fn wrap_sol(owner: &Pubkey, lamports: u64, token_program: &Pubkey) -> Result<Vec<Instruction>> {
+    let ata = get_associated_token_address_with_program_id(
+        owner,
+        &spl_token::native_mint::id(),
+        token_program,
+    );
+    Ok(vec![
+        system_instruction::transfer(owner, &ata, lamports),
+        spl_token::instruction::sync_native(token_program, &ata)?,
+    ])
+}
+
+/// Unwraps the wrapped SOL of `owner` by closing its associated token account.
+pub fn unwrap_sol(owner: &Pubkey, token_program: &Pubkey) -> Result<Instruction> {
+    let ata = get_associated_token_address_with_program_id(
+        owner,
+        &spl_token::native_mint::id(),
+        token_program,
+    );
+    Ok(spl_token::instruction::close_account(
+        token_program,
+        &ata,
+        owner,
+        owner,
+        &[],
+    )?)
+}
+
+/// Reads the amount held by a packed token account, or `None` if `data` is not one.
+pub fn token_account_amount(data: &[u8]) -> Option<u64> {
+    data.get(..spl_token::state::Account::LEN)
+        .and_then(|data| spl_token::state::Account::unpack(data).ok())
+        .map(|account| account.amount)
+}
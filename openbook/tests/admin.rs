@@ -0,0 +1,154 @@
+//! Tests of the admin checks of the market admin instructions.
+
+mod synthetic;
+
+use openbook::admin::{self, AdminRole, Cosignable};
+use openbook::context::MarketContext;
+use openbook_v2::state::{OpenOrdersAccount, StubOracle};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    message::{Message, VersionedMessage},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::VersionedTransaction,
+};
+
+fn context(close_market_admin: Option<Pubkey>, collect_fee_admin: Pubkey) -> MarketContext {
+    let mut market = synthetic::market(0, 0);
+    market.close_market_admin = close_market_admin.into();
+    market.collect_fee_admin = collect_fee_admin;
+    market.bids = Pubkey::new_unique();
+    market.asks = Pubkey::new_unique();
+    market.event_heap = Pubkey::new_unique();
+    MarketContext {
+        address: Pubkey::new_unique(),
+        market,
+        base_mint_info: Default::default(),
+        quote_mint_info: Default::default(),
+    }
+}
+
+#[test]
+fn test_close_market_admin() {
+    let admin = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let market = context(Some(admin), other);
+
+    let ix = admin::set_market_expired(&market, &admin).unwrap();
+    assert_eq!(ix.program_id, openbook_v2::id());
+    assert_eq!(ix.accounts[0].pubkey, admin);
+    assert!(ix.accounts[0].is_signer);
+    assert_eq!(ix.accounts[1].pubkey, market.address);
+
+    let ix = admin::prune_orders(&market, &admin, Pubkey::new_unique(), 10).unwrap();
+    assert_eq!(ix.accounts[3].pubkey, market.market.bids);
+    assert!(admin::close_market(&market, &admin, admin).is_ok());
+
+    let err = admin::set_market_expired(&market, &other).unwrap_err();
+    assert!(err.to_string().contains("is not the close market admin"));
+    assert!(admin::prune_orders(&market, &other, Pubkey::new_unique(), 10).is_err());
+    assert!(admin::close_market(&market, &other, other).is_err());
+    // The collect fee admin can not close the market.
+    assert!(admin::sweep_fees(&market, &other, Pubkey::new_unique()).is_ok());
+    assert!(admin::sweep_fees(&market, &admin, Pubkey::new_unique()).is_err());
+
+    let without_admin = context(None, other);
+    let err = admin::set_market_expired(&without_admin, &other).unwrap_err();
+    assert!(err.to_string().contains("has no close market admin"));
+    assert_eq!(AdminRole::CloseMarket.admin(&without_admin.market), None);
+    assert!(admin::check_admin(&without_admin, AdminRole::ConsumeEvents, &other).is_err());
+}
+
+#[test]
+fn test_consume_events_admin() {
+    let admin = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let mut market = context(None, other);
+    market.market.consume_events_admin = Some(admin).into();
+    let open_orders = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let ix = admin::consume_events(&market, &admin, &open_orders, 8).unwrap();
+    assert_eq!(ix.accounts[0].pubkey, admin);
+    assert!(ix.accounts[0].is_signer);
+    assert_eq!(ix.accounts[2].pubkey, market.market.event_heap);
+    assert_eq!(ix.accounts[3].pubkey, open_orders[0]);
+
+    let ix = admin::consume_given_events(&market, &admin, &open_orders, vec![0, 3]).unwrap();
+    assert_eq!(ix.accounts[0].pubkey, admin);
+    assert!(ix.accounts[0].is_signer);
+    assert_eq!(ix.accounts.len(), 5);
+    assert!(ix.accounts[4].is_writable);
+
+    let err = admin::consume_given_events(&market, &other, &open_orders, vec![0]).unwrap_err();
+    assert!(err.to_string().contains("is not the consume events admin"));
+    assert!(admin::consume_events(&market, &other, &open_orders, 8).is_err());
+}
+
+#[test]
+fn test_settle_funds_expired() {
+    let admin = Pubkey::new_unique();
+    let market = context(Some(admin), admin);
+    let mut account: OpenOrdersAccount = bytemuck::Zeroable::zeroed();
+    account.owner = Pubkey::new_unique();
+    account.market = market.address;
+
+    let address = Pubkey::new_unique();
+    let ix = admin::settle_funds_expired(&market, &admin, admin, address, &account).unwrap();
+    assert_eq!(ix.accounts[1].pubkey, account.owner);
+    assert_eq!(ix.accounts[3].pubkey, address);
+
+    account.market = Pubkey::new_unique();
+    assert!(admin::settle_funds_expired(&market, &admin, admin, address, &account).is_err());
+}
+
+#[test]
+fn test_stub_oracle() {
+    let owner = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let address = admin::stub_oracle(&owner, &mint);
+    assert_ne!(address, admin::stub_oracle(&mint, &owner));
+
+    let ix = admin::stub_oracle_create(owner, owner, mint, 1.5);
+    assert_eq!(ix.accounts[2].pubkey, address);
+
+    let mut oracle: StubOracle = bytemuck::Zeroable::zeroed();
+    oracle.owner = owner;
+    assert!(admin::stub_oracle_set(address, &oracle, &owner, 2.0).is_ok());
+    assert!(admin::stub_oracle_close(address, &oracle, &owner, owner).is_ok());
+    let other = Pubkey::new_unique();
+    assert!(admin::stub_oracle_set(address, &oracle, &other, 2.0).is_err());
+    assert!(admin::stub_oracle_close(address, &oracle, &other, other).is_err());
+}
+
+#[test]
+fn test_sign_open_orders_versioned() {
+    let admin = Keypair::new();
+    let payer = Keypair::new();
+    let mut market = context(None, payer.pubkey());
+    market.market.open_orders_admin = Some(admin.pubkey()).into();
+
+    let ix = Instruction::new_with_bytes(
+        openbook_v2::id(),
+        &[],
+        vec![
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(admin.pubkey(), true),
+        ],
+    );
+    let message = Message::new(&[ix], Some(&payer.pubkey()));
+    let mut trx = VersionedTransaction {
+        signatures: vec![Signature::default(); 2],
+        message: VersionedMessage::Legacy(message),
+    };
+    assert_eq!(trx.missing_signers(), vec![payer.pubkey(), admin.pubkey()]);
+
+    assert!(admin::sign_open_orders(&market, &mut trx, &payer).is_err());
+    admin::sign_open_orders(&market, &mut trx, &admin).unwrap();
+    assert_eq!(trx.missing_signers(), vec![payer.pubkey()]);
+    // Signing twice is rejected.
+    assert!(admin::sign_open_orders(&market, &mut trx, &admin).is_err());
+    trx.cosign(&payer).unwrap();
+    assert!(trx.missing_signers().is_empty());
+    assert!(trx.verify_with_results().iter().all(|valid| *valid));
+}
@@ -0,0 +1,271 @@
+//! Tests of the backtester on synthetic recordings.
+
+mod synthetic;
+
+use anyhow::Result;
+use openbook::backtest::{
+    BacktestStrategy, Backtester, IntentStrategy, SimFill, SimulatedExchange,
+};
+use openbook::book::{BookOrder, OrderDiff};
+use openbook::config::MarketSettings;
+use openbook::context::OrderLots;
+use openbook::recorder::Change;
+use openbook::snapshot::EventRecord;
+use openbook::strategy::{OrderIntent, Strategy, StrategyContext};
+use openbook_v2::state::{PlaceOrderType, Side};
+use solana_sdk::pubkey::Pubkey;
+
+use synthetic::{order, record};
+
+/// Quotes a post-only bid at 2.0 once, and tries to place a crossing one.
+#[derive(Default)]
+struct Quoter {
+    resting: Option<u128>,
+    crossing: Option<Option<u128>>,
+    fills: Vec<SimFill>,
+}
+
+impl BacktestStrategy for Quoter {
+    fn on_book(&mut self, exchange: &mut SimulatedExchange) -> Result<()> {
+        if self.resting.is_none() {
+            self.resting = exchange.place_limit_order(2.0, 10, Side::Bid)?;
+            self.crossing = Some(exchange.place_limit_order(2.2, 10, Side::Bid)?);
+        }
+        Ok(())
+    }
+
+    fn on_fill(&mut self, fill: &SimFill, _exchange: &mut SimulatedExchange) -> Result<()> {
+        self.fills.push(*fill);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_post_only_quote_filled_by_trade() -> Result<()> {
+    let context = synthetic::context(-100, 400);
+    let market = context.address;
+    let records = vec![
+        record(
+            market,
+            1,
+            Change::Book {
+                bids: vec![order(1, 1_900, 1_000)],
+                asks: vec![order(2, 2_100, 1_000)],
+            },
+        ),
+        record(
+            market,
+            2,
+            Change::Events {
+                events: vec![EventRecord::Fill {
+                    heap_slot: 0,
+                    taker_side: 1,
+                    maker_out: false,
+                    maker_slot: 0,
+                    timestamp: 0,
+                    market_seq_num: 1,
+                    maker: Pubkey::new_unique(),
+                    taker: Pubkey::new_unique(),
+                    taker_client_order_id: 0,
+                    maker_client_order_id: 0,
+                    price: 1_950,
+                    quantity: 3_000,
+                }],
+            },
+        ),
+        record(Pubkey::new_unique(), 3, Change::Gap { last_slot: 0 }),
+    ];
+
+    let mut strategy = Quoter::default();
+    let backtester = Backtester::new(context, MarketSettings::default(), Default::default());
+    let report = backtester.run(records, &mut strategy)?;
+
+    assert!(strategy.resting.is_some());
+    assert_eq!(strategy.crossing, Some(None));
+    assert_eq!(strategy.fills, report.fills);
+    assert_eq!(report.fills.len(), 1);
+    let fill = report.fills[0];
+    assert!(fill.maker);
+    assert_eq!(Some(fill.order_id), strategy.resting);
+    // Client order ids count up, so that runs are reproducible.
+    assert_eq!(fill.client_order_id, 1);
+    assert_eq!(
+        (fill.price_lots, fill.quantity, fill.fee),
+        (2_000, 3_000, -600)
+    );
+
+    assert_eq!(report.gaps, 0);
+    assert_eq!(report.pnl.base_position, 3_000_000_000);
+    assert_eq!(report.pnl.quote_position, -5_999_400);
+    assert_eq!(report.pnl.mark_price_lots, Some(2_000));
+    Ok(())
+}
+
+#[test]
+fn test_taking_orders_and_self_trade() -> Result<()> {
+    let context = synthetic::context(-100, 400);
+    let market = context.address;
+    let mut backtester = Backtester::new(context, MarketSettings::default(), Default::default());
+    backtester.step(
+        record(
+            market,
+            1,
+            Change::Book {
+                bids: vec![],
+                asks: vec![order(1, 2_100, 1_000), order(2, 2_200, 1_000)],
+            },
+        ),
+        &mut Quoter::default(),
+    )?;
+    let exchange = &mut backtester.exchange;
+    exchange.cancel_all()?;
+
+    let lots = |price_lots, max_base_lots| OrderLots {
+        price_lots,
+        max_base_lots,
+        max_quote_lots: u64::MAX / 4,
+    };
+    let fok = exchange.place_order(
+        Side::Bid,
+        lots(2_100, 1_500),
+        PlaceOrderType::FillOrKill,
+        0,
+        0,
+    );
+    assert!(fok.is_err());
+    assert!(exchange.fills().is_empty());
+
+    let rest = exchange.place_order(Side::Bid, lots(2_150, 1_500), PlaceOrderType::Limit, 0, 0)?;
+    assert!(rest.is_some());
+    assert_eq!(exchange.open_orders()[0].quantity, 500);
+    assert_eq!(exchange.fills().len(), 1);
+    assert_eq!(exchange.fills()[0].fee, 840);
+    assert_eq!(exchange.best_recorded_price(Side::Ask), Some(2_200));
+
+    let self_trade = exchange.place_order(
+        Side::Ask,
+        lots(2_100, 100),
+        PlaceOrderType::ImmediateOrCancel,
+        0,
+        0,
+    );
+    assert!(self_trade.is_err());
+    assert_eq!(exchange.open_orders()[0].quantity, 500);
+
+    Ok(())
+}
+
+/// Places no orders.
+struct Idle;
+
+impl BacktestStrategy for Idle {
+    fn on_book(&mut self, _exchange: &mut SimulatedExchange) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_taker_fees_and_expired_orders() -> Result<()> {
+    let context = synthetic::context(0, 400);
+    let market = context.address;
+    let mut backtester = Backtester::new(context, MarketSettings::default(), Default::default());
+    let expired = BookOrder {
+        time_in_force: 1,
+        ..order(1, 900, 10)
+    };
+    let asks = vec![
+        expired,
+        order(2, 1_000, 1),
+        order(3, 1_000, 1),
+        order(4, 1_000, 1),
+    ];
+    backtester.step(
+        record(market, 1, Change::Book { bids: vec![], asks }),
+        &mut Idle,
+    )?;
+    let exchange = &mut backtester.exchange;
+    assert_eq!(exchange.best_recorded_price(Side::Ask), Some(1_000));
+
+    // 3_000 quote lots only leave 2_998 to match once the taker fee is reserved.
+    let lots = |max_quote_lots| OrderLots {
+        price_lots: 1_000,
+        max_base_lots: 3,
+        max_quote_lots,
+    };
+    exchange.place_order(
+        Side::Bid,
+        lots(3_000),
+        PlaceOrderType::ImmediateOrCancel,
+        0,
+        0,
+    )?;
+    let quantities: Vec<i64> = exchange.fills().iter().map(|fill| fill.quantity).collect();
+    assert_eq!(quantities, [1, 1]);
+    // The fee is rounded up once on the 2_000 quote lots matched, not on every fill.
+    let fees: Vec<i64> = exchange.fills().iter().map(|fill| fill.fee).collect();
+    assert_eq!(fees, [1, 0]);
+
+    exchange.place_order(
+        Side::Bid,
+        lots(10_000),
+        PlaceOrderType::ImmediateOrCancel,
+        0,
+        0,
+    )?;
+    assert_eq!(exchange.fills().len(), 3);
+    assert_eq!(exchange.best_recorded_price(Side::Ask), None);
+    Ok(())
+}
+
+/// Bids 500 price lots above the best bid whenever it has no open order.
+struct Joiner;
+
+impl Strategy for Joiner {
+    fn on_book(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        match ctx.bids.first() {
+            Some(best) if ctx.open_orders.is_empty() => vec![OrderIntent::Place {
+                side: Side::Bid,
+                limit_price: (best.price_lots + 500) as f64 / 1_000.0,
+                quote_size: 10,
+            }],
+            _ => vec![],
+        }
+    }
+}
+
+#[test]
+fn test_intent_strategy() -> Result<()> {
+    let context = synthetic::context(-100, 400);
+    let market = context.address;
+    let records = vec![
+        record(
+            market,
+            1,
+            Change::Book {
+                bids: vec![order(1, 1_500, 1_000)],
+                asks: vec![order(2, 2_100, 1_000)],
+            },
+        ),
+        record(
+            market,
+            2,
+            Change::BookUpdate {
+                bids: Default::default(),
+                asks: OrderDiff {
+                    added: vec![order(3, 1_800, 10_000)],
+                    ..Default::default()
+                },
+            },
+        ),
+    ];
+
+    let backtester = Backtester::new(context, MarketSettings::default(), Default::default());
+    let report = backtester.run(records, &mut IntentStrategy(Joiner))?;
+
+    // The bid at 2.0 is filled by the ask posted at 1.8, and its replacement would cross it.
+    assert_eq!(report.fills.len(), 1);
+    assert_eq!(report.fills[0].price_lots, 2_000);
+    assert_eq!(report.fills[0].quantity, 5_000);
+    assert_eq!(report.pnl.maker_fills, 1);
+    Ok(())
+}
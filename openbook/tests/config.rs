@@ -0,0 +1,148 @@
+//! Tests of configuration file parsing and profile resolution.
+
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::Result;
+use openbook::config::{Config, OrderType, CONFIG_PATH_ENV, PROFILE_ENV};
+use openbook_v2::state::PlaceOrderType;
+use solana_sdk::{commitment_config::CommitmentLevel, pubkey::Pubkey};
+use tempfile::TempDir;
+
+const MARKET: &str = "gQN1TNHiqj5x82ZQd7JZ8rm8WD4xwWtXxd4onReWZNK";
+const REFERRER: &str = "7azgmy1pFXHikv36q1zZASvFq5vFa39TT9NweVugKKTU";
+
+/// The environment variables overriding the configuration.
+const ENV_VARS: [&str; 8] = [
+    CONFIG_PATH_ENV,
+    PROFILE_ENV,
+    "OPENBOOK_RPC_URL",
+    "OPENBOOK_KEYPAIR",
+    "OPENBOOK_COMMITMENT",
+    "OPENBOOK_MARKET",
+    "OPENBOOK_OPEN_ORDERS_ACCOUNT",
+    "OPENBOOK_REFERRER",
+];
+
+/// Serializes the tests, which share the process environment.
+static ENV: Mutex<()> = Mutex::new(());
+
+/// Locks the environment and clears the overrides, so that the tests do not depend on the
+/// environment they run in.
+fn clear_env() -> MutexGuard<'static, ()> {
+    let guard = ENV.lock().unwrap_or_else(|err| err.into_inner());
+    for var in ENV_VARS {
+        std::env::remove_var(var);
+    }
+    guard
+}
+
+/// Writes `contents` to `name` in a new temporary directory, removed when dropped.
+fn write_config(name: &str, contents: &str) -> Result<(TempDir, std::path::PathBuf)> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join(name);
+    std::fs::write(&path, contents)?;
+    Ok((dir, path))
+}
+
+#[test]
+fn test_toml_profile_with_market_settings() -> Result<()> {
+    let _env = clear_env();
+    let (_dir, path) = write_config(
+        "openbook-config-test.toml",
+        &format!(
+            r#"
+default_profile = "trading"
+
+[profiles.trading]
+rpc_url = "http://localhost:8899"
+keypair_path = "/tmp/id.json"
+commitment = "finalized"
+market = "{MARKET}"
+referrer = "{REFERRER}"
+
+[profiles.trading.markets.{MARKET}]
+order_type = "limit"
+ttl_secs = 60
+max_size = 500
+"#
+        ),
+    )?;
+
+    let config = Config::load(&path)?.resolve(None)?;
+    assert_eq!(config.rpc_url, "http://localhost:8899");
+    assert_eq!(config.keypair_path, "/tmp/id.json");
+    assert_eq!(config.commitment.commitment, CommitmentLevel::Finalized);
+    assert_eq!(config.market, Some(MARKET.parse::<Pubkey>()?));
+    assert_eq!(config.referrer, Some(REFERRER.parse::<Pubkey>()?));
+
+    let settings = config.market_settings();
+    assert_eq!(settings.order_type, Some(OrderType::Limit));
+    assert!(matches!(settings.order_type(), PlaceOrderType::Limit));
+    assert_eq!(settings.ttl_secs(), 60);
+    assert_eq!(settings.max_size, Some(500));
+    Ok(())
+}
+
+#[test]
+fn test_json_config_and_builtin_profiles() -> Result<()> {
+    let _env = clear_env();
+    let (_dir, path) = write_config(
+        "openbook-config-test.json",
+        r#"{ "profiles": { "mainnet": { "keypair_path": "/tmp/main.json" } } }"#,
+    )?;
+    let config = Config::load(&path)?;
+
+    let mainnet = config.resolve(Some("mainnet"))?;
+    assert_eq!(mainnet.rpc_url, "https://api.mainnet-beta.solana.com");
+    assert_eq!(mainnet.keypair_path, "/tmp/main.json");
+    assert_eq!(mainnet.commitment.commitment, CommitmentLevel::Confirmed);
+    assert!(mainnet.market.is_none());
+    assert!(mainnet.referrer.is_none());
+    assert!(!matches!(
+        mainnet.market_settings().order_type(),
+        PlaceOrderType::Limit
+    ));
+
+    let localnet = config.resolve(Some("localnet"))?;
+    assert_eq!(localnet.rpc_url, "http://127.0.0.1:8899");
+    assert!(config.resolve(Some("unknown")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_environment_overrides() -> Result<()> {
+    let _env = clear_env();
+    let (_dir, path) = write_config(
+        "openbook-config-test.toml",
+        r#"
+[profiles.trading]
+rpc_url = "http://localhost:8899"
+keypair_path = "/tmp/id.json"
+commitment = "finalized"
+"#,
+    )?;
+    std::env::set_var(CONFIG_PATH_ENV, &path);
+    std::env::set_var(PROFILE_ENV, "trading");
+    std::env::set_var("OPENBOOK_RPC_URL", "http://override:8899");
+    std::env::set_var("OPENBOOK_COMMITMENT", "processed");
+    std::env::set_var("OPENBOOK_MARKET", MARKET);
+    std::env::set_var("OPENBOOK_REFERRER", REFERRER);
+
+    let config = Config::load_default()?.resolve(None)?;
+    assert_eq!(config.rpc_url, "http://override:8899");
+    assert_eq!(config.keypair_path, "/tmp/id.json");
+    assert_eq!(config.commitment.commitment, CommitmentLevel::Processed);
+    assert_eq!(config.market, Some(MARKET.parse::<Pubkey>()?));
+    assert_eq!(config.referrer, Some(REFERRER.parse::<Pubkey>()?));
+
+    // An explicit profile takes precedence over `OPENBOOK_PROFILE`.
+    let devnet = Config::load_default()?.resolve(Some("devnet"))?;
+    assert_eq!(devnet.rpc_url, "http://override:8899");
+
+    std::env::set_var("OPENBOOK_COMMITMENT", "eventually");
+    assert!(Config::load_default()?.resolve(None).is_err());
+    std::env::set_var("OPENBOOK_COMMITMENT", "confirmed");
+    std::env::set_var("OPENBOOK_MARKET", "not a pubkey");
+    assert!(Config::load_default()?.resolve(None).is_err());
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! Tests of the execution algorithms on synthetic recordings.
+
+mod synthetic;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use openbook::backtest::{Backtester, IntentStrategy};
+use openbook::book::OrderDiff;
+use openbook::config::MarketSettings;
+use openbook::execution::{Iceberg, IcebergConfig, Progress, Twap, TwapConfig};
+use openbook::recorder::{Change, Record};
+use openbook::snapshot::EventRecord;
+use openbook::strategy::Strategy;
+use openbook_v2::state::Side;
+use solana_sdk::pubkey::Pubkey;
+
+use synthetic::{order, record};
+
+fn tick(market: Pubkey, slot: u64) -> Record {
+    record(
+        market,
+        slot,
+        Change::BookUpdate {
+            bids: Default::default(),
+            asks: Default::default(),
+        },
+    )
+}
+
+#[test]
+fn test_twap() -> Result<()> {
+    let context = synthetic::context(0, 400);
+    let market = context.address;
+    let records = vec![
+        record(
+            market,
+            0,
+            Change::Book {
+                bids: vec![order(1, 1_900, 1_000)],
+                asks: vec![order(2, 2_000, 3_000), order(3, 2_200, 10_000)],
+            },
+        ),
+        tick(market, 2),
+        tick(market, 10),
+        tick(market, 12),
+        tick(market, 20),
+        tick(market, 22),
+    ];
+
+    let reports: Arc<Mutex<Vec<Progress>>> = Default::default();
+    let twap = Twap::new(TwapConfig {
+        side: Side::Bid,
+        base_size: 4.0,
+        limit_price: 2.1,
+        duration: Duration::from_secs(10),
+        slices: 2,
+        jitter: 0.0,
+    })
+    .with_progress({
+        let reports = reports.clone();
+        move |progress| reports.lock().unwrap().push(*progress)
+    });
+    let mut strategy = IntentStrategy(twap);
+    let backtester = Backtester::new(context, MarketSettings::default(), Default::default());
+    let report = backtester.run(records, &mut strategy)?;
+
+    // The first slice takes 2000 lots, the second only the 1000 left below the limit price.
+    let quantities: Vec<i64> = report.fills.iter().map(|fill| fill.quantity).collect();
+    assert_eq!(quantities, [2_000, 1_000]);
+    assert!(report.fills.iter().all(|fill| fill.price_lots == 2_000));
+
+    let progress = strategy.0.progress();
+    assert_eq!(progress.filled_base_lots, 3_000);
+    assert_eq!(progress.target_base_lots, 4_000);
+    assert_eq!(progress.orders, 2);
+    assert!(progress.done && strategy.0.is_done());
+    assert_eq!(progress.fraction(), 0.75);
+    assert_eq!(reports.lock().unwrap().last(), Some(&progress));
+    Ok(())
+}
+
+#[test]
+fn test_twap_converts_prices_with_the_lot_sizes() -> Result<()> {
+    // A quote lot of 10 native units makes 2.1 quote tokens per base token 210 price lots.
+    let mut context = synthetic::context(0, 400);
+    context.market.quote_lot_size = 10;
+    let market = context.address;
+    let records = vec![
+        record(
+            market,
+            0,
+            Change::Book {
+                bids: vec![],
+                asks: vec![order(1, 200, 500), order(2, 220, 10_000)],
+            },
+        ),
+        tick(market, 2),
+    ];
+
+    let twap = Twap::new(TwapConfig {
+        side: Side::Bid,
+        base_size: 1.0,
+        limit_price: 2.1,
+        duration: Duration::from_secs(10),
+        slices: 1,
+        jitter: 0.0,
+    });
+    let mut strategy = IntentStrategy(twap);
+    let backtester = Backtester::new(context, MarketSettings::default(), Default::default());
+    let report = backtester.run(records, &mut strategy)?;
+
+    assert_eq!(report.fills.len(), 1);
+    assert_eq!(
+        (report.fills[0].price_lots, report.fills[0].quantity),
+        (200, 500)
+    );
+    assert_eq!(strategy.0.progress().target_base_lots, 1_000);
+    Ok(())
+}
+
+#[test]
+fn test_iceberg() -> Result<()> {
+    let context = synthetic::context(0, 400);
+    let market = context.address;
+    let trade = |quantity| EventRecord::Fill {
+        heap_slot: 0,
+        taker_side: 0,
+        maker_out: false,
+        maker_slot: 0,
+        timestamp: 0,
+        market_seq_num: 1,
+        maker: Pubkey::new_unique(),
+        taker: Pubkey::new_unique(),
+        taker_client_order_id: 0,
+        maker_client_order_id: 0,
+        price: 2_250,
+        quantity,
+    };
+    let records = vec![
+        record(
+            market,
+            1,
+            Change::Book {
+                bids: vec![order(1, 1_900, 1_000)],
+                asks: vec![order(2, 2_300, 1_000)],
+            },
+        ),
+        // A bid crossing the clip fills it.
+        record(
+            market,
+            2,
+            Change::BookUpdate {
+                bids: OrderDiff {
+                    added: vec![order(3, 2_250, 1_000)],
+                    ..Default::default()
+                },
+                asks: Default::default(),
+            },
+        ),
+        // A trade fills part of the next clip, which keeps resting.
+        record(
+            market,
+            3,
+            Change::Events {
+                events: vec![trade(400)],
+            },
+        ),
+        tick(market, 4),
+        record(
+            market,
+            5,
+            Change::Events {
+                events: vec![trade(5_000)],
+            },
+        ),
+        tick(market, 6),
+    ];
+
+    let iceberg = Iceberg::new(IcebergConfig {
+        side: Side::Ask,
+        base_size: 2.5,
+        clip_size: 1.0,
+        limit_price: 2.2,
+    });
+    let mut strategy = IntentStrategy(iceberg);
+    let mut backtester = Backtester::new(context, MarketSettings::default(), Default::default());
+    for record in records {
+        backtester.step(record, &mut strategy)?;
+    }
+
+    let quantities: Vec<i64> = backtester
+        .exchange
+        .fills()
+        .iter()
+        .map(|fill| fill.quantity)
+        .collect();
+    assert_eq!(quantities, [1_000, 400, 600]);
+    assert!(backtester.exchange.fills().iter().all(|fill| fill.maker));
+
+    // The last clip holds the 500 lots left.
+    let progress = strategy.0.progress();
+    assert_eq!(progress.filled_base_lots, 2_000);
+    assert_eq!(progress.orders, 3);
+    assert!(!progress.done);
+    let open_orders = backtester.exchange.open_orders();
+    assert_eq!(open_orders.len(), 1);
+    assert_eq!(open_orders[0].quantity, 500);
+    assert_eq!(open_orders[0].price_lots, 2_200);
+    Ok(())
+}
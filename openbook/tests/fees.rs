@@ -0,0 +1,96 @@
+//! Tests of the fee calculations.
+
+mod synthetic;
+
+use openbook::fees::{self, FeeBreakdown};
+use openbook_v2::state::{Market, Side};
+
+fn market(maker_fee: i64, taker_fee: i64, quote_lot_size: i64) -> Market {
+    let mut market = synthetic::market(maker_fee, taker_fee);
+    market.quote_lot_size = quote_lot_size;
+    market
+}
+
+#[test]
+fn test_fill_fees() {
+    let rebates = market(-100, 400, 1);
+    assert_eq!(fees::taker_fee_ceil(&rebates, 1_000_001), 401);
+    assert_eq!(fees::maker_rebate_floor(&rebates, 1_000_001), 100);
+    assert_eq!(fees::maker_fee_ceil(&rebates, 1_000_001), 0);
+    assert_eq!(fees::fill_fee(&rebates, 1_000_001, false), 401);
+    assert_eq!(fees::fill_fee(&rebates, 1_000_001, true), -100);
+    assert_eq!(fees::referrer_rebate(&rebates, 1_000_001), 301);
+
+    let maker_fees = market(200, 400, 1);
+    assert_eq!(fees::maker_fee_ceil(&maker_fees, 1_000_001), 201);
+    assert_eq!(fees::maker_rebate_floor(&maker_fees, 1_000_001), 0);
+    assert_eq!(fees::fill_fee(&maker_fees, 1_000_001, true), 201);
+    assert_eq!(fees::referrer_rebate(&maker_fees, 1_000_000), 400);
+}
+
+#[test]
+fn test_fee_inclusive_lots() {
+    let market = market(200, 400, 10);
+    assert_eq!(fees::subtract_taker_fees(&market, 1_000_400), 1_000_000);
+    assert_eq!(
+        fees::quote_lots_including_taker_fees(&market, 1_000_000),
+        1_000_400
+    );
+    for quote_lots in 1..3_000 {
+        let including = fees::quote_lots_including_taker_fees(&market, quote_lots);
+        assert!(fees::subtract_taker_fees(&market, including) >= quote_lots);
+        assert!(fees::subtract_taker_fees(&market, including - 1) < quote_lots);
+    }
+
+    // 2 native units of maker fee round up to a lot of 10.
+    assert_eq!(fees::quote_lots_including_maker_fees(&market, 1_000), 1_001);
+    assert_eq!(
+        fees::quote_lots_including_maker_fees(&market, 100_000),
+        100_020
+    );
+    assert_eq!(
+        fees::quote_lots_including_fees(&market, Side::Bid, 100_000, true),
+        100_020
+    );
+    assert_eq!(
+        fees::quote_lots_including_fees(&market, Side::Bid, 1_000_000, false),
+        1_000_400
+    );
+    assert_eq!(
+        fees::quote_lots_including_fees(&market, Side::Ask, 500, false),
+        500
+    );
+
+    let context = synthetic::context(200, 400);
+    assert_eq!(
+        context.max_quote_lots_including_maker_fees(1_000_000),
+        1_000_200
+    );
+}
+
+#[test]
+fn test_fee_breakdown() {
+    let rebates = market(-100, 400, 1);
+    let bid = FeeBreakdown::new(&rebates, Side::Bid, 1_000_001, true);
+    assert_eq!(
+        bid,
+        FeeBreakdown {
+            quote_native: 1_000_001,
+            taker_fee_native: 401,
+            maker_rebate_native: 100,
+            maker_fee_native: 0,
+            referrer_rebate_native: 301,
+            market_fee_native: 0,
+            taker_net_quote_native: 1_000_402,
+        }
+    );
+    let ask = FeeBreakdown::new(&rebates, Side::Ask, 1_000_001, false);
+    assert_eq!(ask.referrer_rebate_native, 0);
+    assert_eq!(ask.market_fee_native, 301);
+    assert_eq!(ask.taker_net_quote_native, 999_600);
+
+    let maker_fees = market(200, 400, 1);
+    let bid = FeeBreakdown::new(&maker_fees, Side::Bid, 1_000_000, false);
+    assert_eq!(bid.maker_fee_native, 200);
+    assert_eq!(bid.market_fee_native, 600);
+}
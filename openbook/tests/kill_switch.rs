@@ -0,0 +1,108 @@
+//! Tests of the kill switch flattening all the open orders accounts of an owner.
+
+mod program_test;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use openbook::kill_switch::{self, KillSwitchConfig};
+use openbook::ob_client::OBClient;
+use openbook_v2::state::Side;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use program_test::*;
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_kill_switch() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market_a = ctx.create_market(base_mint, quote_mint).await?;
+    let market_b = ctx.create_market(base_mint, quote_mint).await?;
+
+    let owner = ctx.create_funded_wallet(&[base_mint, quote_mint]).await?;
+    let mut client_a = ctx.client_for(owner.clone(), &market_a).await?;
+    let account_b = ctx
+        .create_open_orders_account(owner.as_ref(), market_b.market)
+        .await?;
+    let mut client_b = OBClient::new(
+        ctx.rpc_url(),
+        owner.clone(),
+        Some(account_b),
+        CommitmentConfig::confirmed(),
+        market_b.market,
+    )
+    .await?;
+
+    for (price, side) in [(2.0, Side::Bid), (1.9, Side::Bid)] {
+        ctx.send_transaction(&client_a.place_limit_order(price, 10, side).await?)
+            .await?;
+    }
+    ctx.send_transaction(&client_b.place_limit_order(3.0, 10, Side::Ask).await?)
+        .await?;
+
+    // A fill of the best bid, pending in the event heap until cranked.
+    let taker = ctx.create_client(&market_a).await?;
+    ctx.place_taker_order(&taker, Side::Ask, 2_000, 2_000)
+        .await?;
+
+    let config = KillSwitchConfig {
+        retry_delay: Duration::from_millis(500),
+        ..Default::default()
+    };
+    let report = client_a.kill_switch(config).await?;
+    assert_eq!(report.accounts.len(), 2);
+    let open_orders = |address| {
+        let account = report
+            .accounts
+            .iter()
+            .find(|account| account.address == address);
+        account.map(|account| account.open_orders)
+    };
+    assert_eq!(open_orders(client_a.open_orders_account), Some(2));
+    assert_eq!(open_orders(account_b), Some(1));
+    for address in [client_a.open_orders_account, account_b] {
+        let account = ctx.open_orders_account(&address).await?;
+        assert!(kill_switch::is_flat(&account));
+    }
+
+    let report = client_a
+        .kill_switch(KillSwitchConfig {
+            close_accounts: true,
+            ..config
+        })
+        .await?;
+    assert!(report.accounts.iter().all(|account| account.closed));
+    assert!(ctx.rpc.get_account(&account_b).await.is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_kill_switch_mixed_token_programs() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx
+        .create_mint_with_transfer_fee(QUOTE_DECIMALS, 0, 0)
+        .await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let mut client = ctx.create_client(&market).await?;
+    ctx.send_transaction(&client.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+
+    // The bid is cancelled, but `SettleFunds` can not move both tokens.
+    let report = client
+        .kill_switch(KillSwitchConfig {
+            retry_delay: Duration::from_millis(500),
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(report.accounts.len(), 1);
+    assert!(report.accounts[0].unsettled);
+    let account = ctx.open_orders_account(&client.open_orders_account).await?;
+    assert!(account.all_orders_in_use().next().is_none());
+    assert!(account.position.quote_free_native > 0);
+    assert!(!kill_switch::is_flat(&account));
+    Ok(())
+}
@@ -0,0 +1,160 @@
+//! Tests of the reference market maker.
+
+mod program_test;
+mod synthetic;
+
+use anyhow::Result;
+use openbook::book::BookOrder;
+use openbook::context::MarketContext;
+use openbook::market_maker::{MarketMaker, MarketMakerConfig};
+use openbook::snapshot::OpenOrderState;
+use openbook::strategy::{
+    OrderIntent, Quote, RuntimeConfig, Strategy, StrategyContext, StrategyRuntime,
+};
+use openbook_v2::state::Side;
+
+use program_test::*;
+
+fn order(order_id: u128, price_lots: i64) -> BookOrder {
+    synthetic::order(order_id, price_lots, 1_000)
+}
+
+fn open_order(order_id: u128) -> OpenOrderState {
+    OpenOrderState {
+        order_id,
+        client_order_id: 0,
+        side: 0,
+        locked_price: 0,
+    }
+}
+
+fn strategy_context<'a>(
+    market: &'a MarketContext,
+    bids: &'a [BookOrder],
+    asks: &'a [BookOrder],
+    open_orders: &'a [OpenOrderState],
+    base_inventory: i64,
+) -> StrategyContext<'a> {
+    StrategyContext {
+        market,
+        slot: 1,
+        timestamp: 0,
+        bids,
+        asks,
+        open_orders,
+        base_inventory,
+        oracle_price: None,
+    }
+}
+
+fn prices(quotes: &[Quote]) -> Vec<i64> {
+    quotes.iter().map(|quote| quote.price_lots).collect()
+}
+
+#[test]
+fn test_ladder() {
+    let market = synthetic::context(0, 400);
+    let bids = [order(1, 1_900)];
+    let asks = [order(2, 2_100)];
+    let ctx = strategy_context(&market, &bids, &asks, &[], 0);
+
+    let mut config = MarketMakerConfig {
+        levels: 2,
+        spread_bps: 20.0,
+        level_spacing_bps: 10.0,
+        ..Default::default()
+    };
+    let ladder = MarketMaker::new(config).ladder(&ctx).unwrap();
+    assert_eq!(prices(&ladder.bids), [1_996, 1_994]);
+    assert_eq!(prices(&ladder.asks), [2_004, 2_006]);
+
+    // The first level stays 4 bps of taker fee and 1 bp of edge away from the mid price.
+    config.spread_bps = 1.0;
+    let ladder = MarketMaker::new(config).ladder(&ctx).unwrap();
+    assert_eq!(prices(&ladder.bids), [1_999, 1_997]);
+    assert_eq!(prices(&ladder.asks), [2_001, 2_003]);
+
+    // Holding 2 base tokens over the target moves the ladder down by 20 bps.
+    config.spread_bps = 20.0;
+    config.inventory_skew_bps = 10.0;
+    let ctx = strategy_context(&market, &bids, &asks, &[], 2_000_000_000);
+    let ladder = MarketMaker::new(config).ladder(&ctx).unwrap();
+    assert_eq!(prices(&ladder.bids), [1_992, 1_990]);
+    assert_eq!(prices(&ladder.asks), [2_001, 2_002]);
+
+    let empty = strategy_context(&market, &bids, &[], &[], 0);
+    assert!(MarketMaker::new(config).ladder(&empty).is_none());
+}
+
+#[test]
+fn test_refresh() {
+    let market = synthetic::context(0, 400);
+    let bids = [order(10, 1_990), order(1, 1_900)];
+    let asks = [order(2, 2_100)];
+    let mut maker = MarketMaker::new(MarketMakerConfig {
+        levels: 2,
+        ..Default::default()
+    });
+
+    // The maker's own bid at 1990 is ignored by the mid price.
+    let resting = [10, 11, 12, 13].map(open_order);
+    let intents = maker.on_book(&strategy_context(&market, &bids, &asks, &resting, 0));
+    assert!(matches!(&intents[..], [OrderIntent::Replace { bids, asks }]
+        if bids.len() == 2 && asks.len() == 2));
+    assert!(maker
+        .on_book(&strategy_context(&market, &bids, &asks, &resting, 0))
+        .is_empty());
+
+    // A filled order refreshes the ladder.
+    let intents = maker.on_timer(&strategy_context(&market, &bids, &asks, &resting[..3], 0));
+    assert_eq!(intents.len(), 1);
+    assert!(maker
+        .on_book(&strategy_context(&market, &bids, &asks, &resting, 0))
+        .is_empty());
+
+    // A move of the mid price by more than 5 bps refreshes it too.
+    let asks = [order(2, 2_200)];
+    let intents = maker.on_book(&strategy_context(&market, &bids, &asks, &resting, 0));
+    assert_eq!(intents.len(), 1);
+
+    // Without a reference price, the orders are cancelled.
+    let intents = maker.on_book(&strategy_context(&market, &bids, &[], &resting, 0));
+    assert_eq!(intents, [OrderIntent::CancelAll]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_market_maker_runtime() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let mut other = ctx.create_client(&market).await?;
+    ctx.send_transaction(&other.place_limit_order(1.0, 10, Side::Bid).await?)
+        .await?;
+    ctx.send_transaction(&other.place_limit_order(3.0, 10, Side::Ask).await?)
+        .await?;
+
+    let client = ctx.create_client(&market).await?;
+    let open_orders_account = client.open_orders_account;
+    let maker = MarketMaker::new(MarketMakerConfig {
+        levels: 2,
+        ..Default::default()
+    });
+    let mut runtime = StrategyRuntime::new(client, maker, RuntimeConfig::default());
+    runtime.poll().await?;
+
+    let account = ctx.open_orders_account(&open_orders_account).await?;
+    let mut orders: Vec<(i64, u64)> = account
+        .all_orders_in_use()
+        .map(|order| (order.locked_price, order.client_id))
+        .collect();
+    orders.sort();
+    assert_eq!(orders, [(1_994, 1), (1_996, 0), (2_004, 2), (2_006, 3)]);
+    assert_eq!(runtime.intended_orders(), &[0, 1, 2, 3]);
+
+    runtime.shutdown().await?;
+    let account = ctx.open_orders_account(&open_orders_account).await?;
+    assert_eq!(account.all_orders_in_use().count(), 0);
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! Tests of the local preview of taking orders.
+
+mod synthetic;
+
+use openbook::book::BookOrder;
+use openbook::matching::{self, TakerOrder};
+use openbook_v2::state::Side;
+
+fn order(order_id: u128, price_lots: i64, quantity: i64, time_in_force: u16) -> BookOrder {
+    BookOrder {
+        time_in_force,
+        ..synthetic::order(order_id, price_lots, quantity)
+    }
+}
+
+fn asks() -> Vec<BookOrder> {
+    vec![
+        order(1, 100, 5, 0),
+        order(2, 101, 10, 10),
+        order(3, 102, 5, 0),
+        order(4, 105, 10, 0),
+    ]
+}
+
+fn bid(price_lots: i64, max_base_lots: i64, max_quote_lots: i64, limit: u8) -> TakerOrder {
+    TakerOrder {
+        side: Side::Bid,
+        price_lots,
+        max_base_lots,
+        max_quote_lots_including_fees: max_quote_lots,
+        limit,
+    }
+}
+
+#[test]
+fn test_preview_bid() {
+    let context = synthetic::context(0, 400);
+    let asks = asks();
+
+    let preview = matching::preview_match(&context, &[], &asks, &bid(104, 8, 1_000_000, 12), 1_000);
+    let fills: Vec<(u128, i64, i64)> = preview
+        .fills
+        .iter()
+        .map(|fill| (fill.order_id, fill.price_lots, fill.quantity))
+        .collect();
+    assert_eq!(fills, vec![(1, 100, 5), (3, 102, 3)]);
+    assert_eq!(preview.fills[0].maker, asks[0].owner);
+    assert_eq!(preview.base_lots, 8);
+    assert_eq!(preview.quote_lots, 806);
+    assert_eq!(preview.average_price_lots(), Some(100.75));
+    assert!((preview.average_price(&context).unwrap() - 0.10075).abs() < 1e-12);
+    assert_eq!(preview.taker_fee_native, 1);
+    assert_eq!(preview.expired_orders, 1);
+    assert!(preview.is_filled());
+    assert_eq!(preview.remaining_quote_lots, 999_600 - 806);
+    assert!(!preview.limit_reached);
+
+    // Orders are not expired before `timestamp + time_in_force`.
+    let preview = matching::preview_match(&context, &[], &asks, &bid(104, 8, 1_000_000, 12), 5);
+    assert_eq!(preview.fills[1].order_id, 2);
+    assert_eq!(preview.expired_orders, 0);
+}
+
+#[test]
+fn test_preview_limits() {
+    let context = synthetic::context(0, 400);
+    let asks = asks();
+
+    let preview =
+        matching::preview_match(&context, &[], &asks, &bid(101, 20, 1_000_000, 12), 1_000);
+    assert_eq!(preview.base_lots, 5);
+    assert_eq!(preview.remaining_base_lots, 15);
+    assert!(!preview.is_filled());
+    assert!(!preview.limit_reached);
+
+    let preview = matching::preview_match(&context, &[], &asks, &bid(110, 20, 1_000_000, 2), 1_000);
+    assert_eq!(preview.base_lots, 10);
+    assert_eq!(preview.remaining_base_lots, 10);
+    assert!(preview.limit_reached);
+
+    // 1000 quote lots leave 999 after reserving the taker fee.
+    let preview = matching::preview_match(&context, &[], &asks, &bid(200, 100, 1_000, 12), 1_000);
+    assert_eq!(preview.base_lots, 9);
+    assert_eq!(preview.quote_lots, 908);
+    assert_eq!(preview.remaining_quote_lots, 91);
+    assert_eq!(preview.remaining_base_lots, 91);
+
+    let preview = matching::preview_match(&context, &[], &[], &bid(200, 100, 1_000, 12), 1_000);
+    assert_eq!(preview.average_price_lots(), None);
+    assert_eq!(preview.taker_fee_native, 0);
+}
+
+#[test]
+fn test_preview_ask() {
+    let context = synthetic::context(0, 400);
+    let bids = vec![order(1, 99, 5, 0), order(2, 98, 5, 0), order(3, 97, 5, 0)];
+    let ask = TakerOrder {
+        side: Side::Ask,
+        price_lots: 98,
+        max_base_lots: 12,
+        max_quote_lots_including_fees: i64::MAX,
+        limit: 12,
+    };
+    let preview = matching::preview_match(&context, &bids, &[], &ask, 1_000);
+    assert_eq!(preview.base_lots, 10);
+    assert_eq!(preview.quote_lots, 5 * 99 + 5 * 98);
+    assert_eq!(preview.remaining_base_lots, 2);
+}
@@ -0,0 +1,640 @@
+//! End-to-end tests of the `OBClient` instruction builders against a local validator.
+
+mod program_test;
+
+use std::sync::Arc;
+
+use anchor_spl::token_2022::spl_token_2022;
+use anyhow::Result;
+use openbook::ob_client::{self, OBClient};
+use openbook::snapshot::MarketSnapshot;
+use openbook::token::TransferFee;
+use openbook::{admin, instructions};
+use openbook_v2::state::Side;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Keypair, signer::Signer,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+use program_test::*;
+
+async fn setup() -> Result<(TestContext, TestMarket)> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    Ok((ctx, market))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_create_market() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let client = ctx.create_client(&market).await?;
+
+    let (market_keypair, new_market) = ctx
+        .allocate_market(market.base_mint, market.quote_mint)
+        .await?;
+    let mut trx = client
+        .create_market(
+            new_market.market,
+            new_market.market_authority,
+            new_market.bids,
+            new_market.asks,
+            new_market.event_heap,
+            new_market.base_mint,
+            new_market.quote_mint,
+            None,
+            None,
+            client.owner(),
+            None,
+            None,
+            None,
+            instructions::event_authority(),
+            "CLIENT-MARKET".to_string(),
+            default_oracle_config(),
+            BASE_LOT_SIZE,
+            QUOTE_LOT_SIZE,
+            0,
+            0,
+            0,
+        )
+        .await?;
+    trx.partial_sign(&[&market_keypair], trx.message.recent_blockhash);
+    ctx.send_transaction(&trx).await?;
+
+    let created = ctx.market(&new_market.market).await?;
+    assert_eq!(created.base_mint, market.base_mint);
+    assert_eq!(created.quote_mint, market.quote_mint);
+    assert_eq!(created.market_base_vault, new_market.market_base_vault);
+    assert_eq!(created.market_quote_vault, new_market.market_quote_vault);
+    assert_eq!(created.collect_fee_admin, client.owner());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_create_open_orders_account() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let client = ctx.create_client(&market).await?;
+
+    let delegate = Keypair::new();
+    let (address, trx) = client
+        .create_open_orders_account("second", Some(delegate.pubkey()))
+        .await?;
+    ctx.send_transaction(&trx).await?;
+
+    // The harness created account 1 with the indexer.
+    assert_eq!(
+        address,
+        instructions::open_orders_account(&client.owner(), 2)
+    );
+    let account = ctx.open_orders_account(&address).await?;
+    assert_eq!(account.owner, client.owner());
+    assert_eq!(account.market, market.market);
+    assert_eq!(account.account_num, 2);
+    assert_eq!(account.name(), "second");
+    assert_eq!(
+        Option::<Pubkey>::from(account.delegate),
+        Some(delegate.pubkey())
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_load_open_orders_accounts() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let client = ctx.create_client(&market).await?;
+    let owner = client.owner();
+    assert!(ctx
+        .open_orders_indexer(&Pubkey::new_unique())
+        .await?
+        .is_none());
+    assert_eq!(ob_client::next_account_num(None), 1);
+    assert_eq!(client.next_account_num().await?, 2);
+
+    let (address, trx) = client.create_open_orders_account("other", None).await?;
+    ctx.send_transaction(&trx).await?;
+    let accounts = client.load_open_orders_accounts().await?;
+    let addresses: Vec<Pubkey> = accounts.iter().map(|(address, _)| *address).collect();
+    assert_eq!(
+        addresses,
+        vec![instructions::open_orders_account(&owner, 1), address]
+    );
+    assert_eq!(accounts[1].1.name(), "other");
+    assert_eq!(client.next_account_num().await?, 3);
+
+    // The "random" account is created once, then found by name and market.
+    let found = client.find_or_create_account().await?;
+    assert_eq!(found, instructions::open_orders_account(&owner, 3));
+    assert_eq!(client.find_or_create_account().await?, found);
+    assert_eq!(client.load_open_orders_accounts().await?.len(), 3);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_deposit() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let client = ctx.create_client(&market).await?;
+
+    let trx = client
+        .deposit(
+            market.market,
+            5_000,
+            7_000,
+            client.base_ata,
+            client.quote_ata,
+            market.market_base_vault,
+            market.market_quote_vault,
+        )
+        .await?;
+    ctx.send_transaction(&trx).await?;
+
+    let account = ctx.open_orders_account(&client.open_orders_account).await?;
+    assert_eq!(account.position.base_free_native, 5_000);
+    assert_eq!(account.position.quote_free_native, 7_000);
+    assert_eq!(ctx.token_balance(&market.market_base_vault).await?, 5_000);
+    assert_eq!(ctx.token_balance(&market.market_quote_vault).await?, 7_000);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_place_limit_order() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let mut client = ctx.create_client(&market).await?;
+
+    let trx = client.place_limit_order(2.0, 10, Side::Bid).await?;
+    ctx.send_transaction(&trx).await?;
+
+    let account = ctx.open_orders_account(&client.open_orders_account).await?;
+    assert!(account.position.bids_base_lots > 0);
+    assert_eq!(account.position.asks_base_lots, 0);
+    assert_eq!(
+        ctx.token_balance(&market.market_quote_vault).await?,
+        10_000_000
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_place_market_order() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let mut client = ctx.create_client(&market).await?;
+
+    let trx = client.place_market_order(2.5, 10, Side::Ask).await?;
+    ctx.send_transaction(&trx).await?;
+
+    let account = ctx.open_orders_account(&client.open_orders_account).await?;
+    assert!(account.position.asks_base_lots > 0);
+    assert_eq!(account.position.bids_base_lots, 0);
+
+    // Market orders are capped like limit orders.
+    client.settings.max_size = Some(5);
+    assert!(client.place_market_order(2.5, 10, Side::Ask).await.is_err());
+    assert!(client.place_limit_order(2.5, 10, Side::Ask).await.is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_cancel_limit_order() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let mut client = ctx.create_client(&market).await?;
+
+    ctx.send_transaction(&client.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+    let account = ctx.open_orders_account(&client.open_orders_account).await?;
+    let order_id = account
+        .open_orders
+        .iter()
+        .find(|order| order.is_free == 0)
+        .expect("order was not placed")
+        .id;
+
+    ctx.send_transaction(&client.cancel_limit_order(order_id).await?)
+        .await?;
+
+    let account = ctx.open_orders_account(&client.open_orders_account).await?;
+    assert_eq!(account.position.bids_base_lots, 0);
+    assert_eq!(account.position.quote_free_native, 10_000_000);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_cancel_all() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let mut client = ctx.create_client(&market).await?;
+
+    ctx.send_transaction(&client.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+    ctx.send_transaction(&client.place_limit_order(2.5, 10, Side::Ask).await?)
+        .await?;
+
+    ctx.send_transaction(&client.cancel_all().await?).await?;
+
+    let account = ctx.open_orders_account(&client.open_orders_account).await?;
+    assert_eq!(account.position.bids_base_lots, 0);
+    assert_eq!(account.position.asks_base_lots, 0);
+    assert!(account.open_orders.iter().all(|order| order.is_free != 0));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_place_match_consume_settle() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let mut maker = ctx.create_client(&market).await?;
+    let taker = ctx.create_client(&market).await?;
+
+    ctx.send_transaction(&maker.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+    let resting = ctx.open_orders_account(&maker.open_orders_account).await?;
+    let bid_lots = resting.position.bids_base_lots;
+    assert!(bid_lots > 0);
+
+    let maker_base_before = ctx.token_balance(&maker.base_ata).await?;
+
+    ctx.place_taker_order(&taker, Side::Ask, 1_000, bid_lots)
+        .await?;
+    let taker_account = ctx.open_orders_account(&taker.open_orders_account).await?;
+    assert!(taker_account.position.quote_free_native > 0);
+
+    ctx.consume_events(&market, &[maker.open_orders_account])
+        .await?;
+    let filled = ctx.open_orders_account(&maker.open_orders_account).await?;
+    assert_eq!(filled.position.bids_base_lots, 0);
+    assert_eq!(
+        filled.position.base_free_native,
+        bid_lots as u64 * BASE_LOT_SIZE as u64
+    );
+
+    ctx.settle_funds(&maker, &market).await?;
+    let settled = ctx.open_orders_account(&maker.open_orders_account).await?;
+    assert_eq!(settled.position.base_free_native, 0);
+    assert_eq!(
+        ctx.token_balance(&maker.base_ata).await?,
+        maker_base_before + bid_lots as u64 * BASE_LOT_SIZE as u64
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_client_consume_events_and_settle_funds() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let mut maker = ctx.create_client(&market).await?;
+    let taker = ctx.create_client(&market).await?;
+    assert!(maker.consume_events(10).await?.is_none());
+
+    ctx.send_transaction(&maker.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+    let bid_lots = ctx
+        .open_orders_account(&maker.open_orders_account)
+        .await?
+        .position
+        .bids_base_lots;
+    let maker_base_before = ctx.token_balance(&maker.base_ata).await?;
+    ctx.place_taker_order(&taker, Side::Ask, 1_000, bid_lots)
+        .await?;
+
+    let crank = maker.consume_events(10).await?.expect("no pending events");
+    ctx.send_transaction(&crank).await?;
+    assert!(maker.consume_events(10).await?.is_none());
+    let filled = ctx.open_orders_account(&maker.open_orders_account).await?;
+    assert_eq!(
+        filled.position.base_free_native,
+        bid_lots as u64 * BASE_LOT_SIZE as u64
+    );
+
+    ctx.send_transaction(&maker.settle_funds().await?).await?;
+    assert_eq!(
+        ctx.token_balance(&maker.base_ata).await?,
+        maker_base_before + bid_lots as u64 * BASE_LOT_SIZE as u64
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_separate_payer_and_offline_signing() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let owner_client = ctx.create_client(&market).await?;
+    let payer = ctx.create_funded_wallet(&[]).await?;
+    let client = owner_client.with_payer(payer.clone());
+
+    let trx = client.cancel_all().await?;
+    assert_eq!(trx.message.account_keys[0], payer.pubkey());
+    assert!(trx.is_signed());
+    ctx.send_transaction(&trx).await?;
+
+    let ix = instructions::cancel_all_orders(
+        openbook_v2::accounts::CancelOrder {
+            open_orders_account: client.open_orders_account,
+            signer: client.owner(),
+            market: market.market,
+            bids: market.bids,
+            asks: market.asks,
+        },
+        None,
+        255,
+    );
+    let mut unsigned = client.to_unsigned_trx(vec![ix]).await?;
+    assert!(!unsigned.is_signed());
+    let recent_blockhash = unsigned.message.recent_blockhash;
+    unsigned.try_sign(
+        &[payer.as_ref() as &dyn Signer, client.owner.as_ref()],
+        recent_blockhash,
+    )?;
+    ctx.send_transaction(&unsigned).await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_delegate_trading() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let owner_client = ctx.create_client(&market).await?;
+    let delegate = ctx
+        .create_funded_wallet(&[market.base_mint, market.quote_mint])
+        .await?;
+
+    ctx.send_transaction(&owner_client.set_delegate(Some(delegate.pubkey())).await?)
+        .await?;
+
+    let mut delegate_client = OBClient::new_delegate(
+        ctx.rpc_url(),
+        owner_client.owner(),
+        delegate.clone(),
+        owner_client.open_orders_account,
+        CommitmentConfig::confirmed(),
+        market.market,
+    )
+    .await?;
+    assert_eq!(delegate_client.signer(), delegate.pubkey());
+
+    let trx = delegate_client
+        .place_limit_order(2.0, 10, Side::Bid)
+        .await?;
+    assert!(trx.is_signed());
+    ctx.send_transaction(&trx).await?;
+    let account = ctx
+        .open_orders_account(&owner_client.open_orders_account)
+        .await?;
+    assert!(account.position.bids_base_lots > 0);
+
+    ctx.send_transaction(&delegate_client.cancel_all().await?)
+        .await?;
+    let account = ctx
+        .open_orders_account(&owner_client.open_orders_account)
+        .await?;
+    assert_eq!(account.position.bids_base_lots, 0);
+
+    ctx.send_transaction(&owner_client.set_delegate(None).await?)
+        .await?;
+    assert!(OBClient::new_delegate(
+        ctx.rpc_url(),
+        owner_client.owner(),
+        delegate,
+        owner_client.open_orders_account,
+        CommitmentConfig::confirmed(),
+        market.market,
+    )
+    .await
+    .is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_permissioned_market() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let open_orders_admin = Arc::new(Keypair::new());
+    let market = ctx
+        .create_permissioned_market(base_mint, quote_mint, open_orders_admin.pubkey())
+        .await?;
+    let mut client = ctx.create_client(&market).await?;
+    assert_eq!(
+        client.open_orders_admin_key(),
+        Some(open_orders_admin.pubkey())
+    );
+
+    // Without the co-signer, orders are left for the admin service to sign.
+    let mut trx = client.place_limit_order(2.0, 10, Side::Bid).await?;
+    assert!(!trx.is_signed());
+    let err = client.send_trx(&trx).await.unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("misses the signature of the open orders admin"));
+    let other = Keypair::new();
+    assert!(admin::sign_open_orders(&client.context, &mut trx, &other).is_err());
+    admin::sign_open_orders(&client.context, &mut trx, open_orders_admin.as_ref())?;
+    assert!(trx.is_signed());
+    ctx.send_transaction(&trx).await?;
+
+    let plain = client.clone();
+    assert!(plain
+        .clone()
+        .with_open_orders_admin(Arc::new(other))
+        .is_err());
+    let mut client = client.with_open_orders_admin(open_orders_admin.clone())?;
+    let trx = client.place_limit_order(2.5, 10, Side::Ask).await?;
+    assert!(trx.is_signed());
+    ctx.send_transaction(&trx).await?;
+
+    let account = ctx.open_orders_account(&client.open_orders_account).await?;
+    assert!(account.position.bids_base_lots > 0);
+    assert!(account.position.asks_base_lots > 0);
+
+    // Cancels do not need the open orders admin.
+    let trx = plain.cancel_all().await?;
+    assert!(trx.is_signed());
+    ctx.send_transaction(&trx).await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_token_2022_transfer_fee() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx
+        .create_mint_with_transfer_fee(BASE_DECIMALS, 100, u64::MAX)
+        .await?;
+    let quote_mint = ctx
+        .create_mint_with_transfer_fee(QUOTE_DECIMALS, 50, u64::MAX)
+        .await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let mut maker = ctx.create_client(&market).await?;
+    let taker = ctx.create_client(&market).await?;
+
+    let base_mint_info = maker.context.base_mint_info;
+    assert_eq!(base_mint_info.token_program, spl_token_2022::id());
+    assert_eq!(
+        base_mint_info.transfer_fee,
+        Some(TransferFee {
+            basis_points: 100,
+            maximum_fee: u64::MAX,
+        })
+    );
+    assert_eq!(maker.common_token_program()?, spl_token_2022::id());
+
+    // The bid only locks the quote reaching the vault after the transfer fee.
+    ctx.send_transaction(&maker.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+    let bid_lots = ctx
+        .open_orders_account(&maker.open_orders_account)
+        .await?
+        .position
+        .bids_base_lots;
+    assert!(bid_lots > 0);
+
+    let maker_base_before = ctx.token_balance(&maker.base_ata).await?;
+    ctx.place_taker_order(&taker, Side::Ask, 1_000, bid_lots)
+        .await?;
+    ctx.consume_events(&market, &[maker.open_orders_account])
+        .await?;
+    let filled_native = bid_lots as u64 * BASE_LOT_SIZE as u64;
+    let filled = ctx.open_orders_account(&maker.open_orders_account).await?;
+    assert_eq!(filled.position.base_free_native, filled_native);
+
+    // Settling withholds the base transfer fee from the tokens leaving the vault.
+    ctx.send_transaction(&maker.settle_funds().await?).await?;
+    let settled = ctx.open_orders_account(&maker.open_orders_account).await?;
+    assert_eq!(settled.position.base_free_native, 0);
+    let base_fee = base_mint_info.transfer_fee.unwrap();
+    assert_eq!(
+        ctx.token_balance(&maker.base_ata).await?,
+        maker_base_before + base_fee.amount_after_fee(filled_native)
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_settle_creates_missing_ata() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let owner = ctx.create_funded_wallet(&[market.quote_mint]).await?;
+    let mut maker = ctx.client_for(owner, &market).await?;
+    let taker = ctx.create_client(&market).await?;
+    assert!(ctx.rpc.get_account(&maker.base_ata).await.is_err());
+
+    ctx.send_transaction(&maker.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+    let bid_lots = ctx
+        .open_orders_account(&maker.open_orders_account)
+        .await?
+        .position
+        .bids_base_lots;
+    ctx.place_taker_order(&taker, Side::Ask, 1_000, bid_lots)
+        .await?;
+    let crank = maker.consume_events(10).await?.expect("no pending events");
+    ctx.send_transaction(&crank).await?;
+
+    ctx.send_transaction(&maker.settle_funds().await?).await?;
+    assert_eq!(
+        ctx.token_balance(&maker.base_ata).await?,
+        bid_lots as u64 * BASE_LOT_SIZE as u64
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_settle_pays_referrer() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx
+        .create_market_with_fees(base_mint, quote_mint, -100, 400)
+        .await?;
+    let mut maker = ctx.create_client(&market).await?;
+    let referrer = Pubkey::new_unique();
+    let taker = ctx.create_client(&market).await?.with_referrer(referrer);
+
+    ctx.send_transaction(&maker.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+    let bid_lots = ctx
+        .open_orders_account(&maker.open_orders_account)
+        .await?
+        .position
+        .bids_base_lots;
+    ctx.place_taker_order(&taker, Side::Ask, 1_000, bid_lots)
+        .await?;
+    let rebates = taker.referrer_rebates_available().await?;
+    assert!(rebates > 0);
+
+    ctx.send_transaction(&taker.settle_funds().await?).await?;
+    let referrer_account = get_associated_token_address(&referrer, &quote_mint);
+    assert_eq!(ctx.token_balance(&referrer_account).await?, rebates);
+    assert_eq!(taker.referrer_rebates_available().await?, 0);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_versioned_transaction_with_lookup_table() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let mut client = ctx.create_client(&market).await?;
+    let table = client.create_market_lookup_table().await?;
+    assert_eq!(client.lookup_tables.len(), 1);
+    assert_eq!(client.lookup_tables[0].key, table);
+    assert!(client.lookup_tables[0].addresses.contains(&market.market));
+
+    let ix = instructions::cancel_all_orders(
+        openbook_v2::accounts::CancelOrder {
+            open_orders_account: client.open_orders_account,
+            signer: client.owner(),
+            market: market.market,
+            bids: market.bids,
+            asks: market.asks,
+        },
+        None,
+        255,
+    );
+    let trx = client.to_versioned_trx(vec![ix]).await?;
+    let lookups = trx
+        .message
+        .address_table_lookups()
+        .expect("not a v0 message");
+    assert_eq!(lookups.len(), 1);
+    assert_eq!(lookups[0].account_key, table);
+    client.send_trx(&trx).await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_snapshot_round_trip_and_diff() -> Result<()> {
+    let (ctx, market) = setup().await?;
+    let mut client = ctx.create_client(&market).await?;
+    ctx.send_transaction(&client.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+
+    let before = client.snapshot().await?;
+    assert_eq!(before.bids.state.len(), 1);
+    assert_eq!(before.bids.state[0].owner, client.open_orders_account);
+    assert_eq!(before.open_orders.len(), 1);
+    assert_eq!(before.open_orders[0].state.orders.len(), 1);
+    assert_eq!(before.context()?.address, market.market);
+
+    let json = before.to_json()?;
+    assert_eq!(MarketSnapshot::from_json(&json)?, before);
+    let bytes = before.to_bytes()?;
+    assert_eq!(MarketSnapshot::from_bytes(&bytes)?, before);
+    assert!(bytes.len() < json.len());
+
+    ctx.send_transaction(&client.cancel_all().await?).await?;
+    let after = client.snapshot().await?;
+    let diff = before.diff(&after);
+    assert!(diff.bids.added.is_empty());
+    assert_eq!(diff.bids.removed, before.bids.state);
+    assert!(diff.asks.is_empty());
+    assert_eq!(diff.open_orders_changed, vec![client.open_orders_account]);
+    Ok(())
+}
@@ -0,0 +1,170 @@
+//! Tests of the decoding and combination of oracle accounts.
+
+use anchor_lang::Discriminator;
+use openbook::oracle::{self, OraclePrice, OracleState, OracleType};
+use openbook_v2::state::{Market, OracleConfig, StubOracle};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+fn config(conf_filter: f64, max_staleness_slots: i64) -> OracleConfig {
+    let mut config: OracleConfig = bytemuck::Zeroable::zeroed();
+    config.conf_filter = conf_filter;
+    config.max_staleness_slots = max_staleness_slots;
+    config
+}
+
+fn pyth(price: i64, confidence: u64, exponent: i32, last_slot: u64) -> Vec<u8> {
+    let mut data = vec![0; 3312];
+    data[0..4].copy_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+    data[20..24].copy_from_slice(&exponent.to_le_bytes());
+    data[32..40].copy_from_slice(&last_slot.to_le_bytes());
+    data[208..216].copy_from_slice(&price.to_le_bytes());
+    data[216..224].copy_from_slice(&confidence.to_le_bytes());
+    data
+}
+
+fn switchboard_decimal(data: &mut [u8], offset: usize, mantissa: i128, scale: u32) {
+    data[offset..offset + 16].copy_from_slice(&mantissa.to_le_bytes());
+    data[offset + 16..offset + 20].copy_from_slice(&scale.to_le_bytes());
+}
+
+fn stub(price: f64, deviation: f64, last_update_slot: u64) -> Vec<u8> {
+    let mut oracle: StubOracle = bytemuck::Zeroable::zeroed();
+    oracle.price = price;
+    oracle.deviation = deviation;
+    oracle.last_update_slot = last_update_slot;
+    let mut data = StubOracle::discriminator().to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&oracle));
+    data
+}
+
+fn state(price: f64, deviation: f64, last_update_slot: u64) -> OracleState {
+    OracleState {
+        oracle_type: OracleType::Stub,
+        price,
+        deviation,
+        last_update_slot,
+    }
+}
+
+#[test]
+fn test_decode() {
+    let owner = Pubkey::new_unique();
+
+    let pyth = OracleState::decode(&owner, &pyth(2_000_000_000, 1_000_000, -8, 42)).unwrap();
+    assert_eq!(pyth.oracle_type, OracleType::Pyth);
+    assert!((pyth.price - 20.0).abs() < 1e-9);
+    assert!((pyth.deviation - 0.01).abs() < 1e-9);
+    assert_eq!(pyth.last_update_slot, 42);
+
+    let mut data = vec![0; 3851];
+    data[..8].copy_from_slice(&[217, 230, 65, 101, 201, 162, 27, 125]);
+    data[350..358].copy_from_slice(&43u64.to_le_bytes());
+    switchboard_decimal(&mut data, 366, 1_500_000, 5);
+    switchboard_decimal(&mut data, 386, 25, 3);
+    let switchboard = OracleState::decode(&owner, &data).unwrap();
+    assert_eq!(switchboard.oracle_type, OracleType::SwitchboardV2);
+    assert!((switchboard.price - 15.0).abs() < 1e-9);
+    assert!((switchboard.deviation - 0.025).abs() < 1e-9);
+    assert_eq!(switchboard.last_update_slot, 43);
+
+    let switchboard_v1 = "DtmE9D2CSB4L5D6A15mraeEjrGMm6auWVzgaD8hK2tZM"
+        .parse()
+        .unwrap();
+    let mut data = vec![0; 81];
+    data[0] = 13;
+    data[41..49].copy_from_slice(&10.0f64.to_le_bytes());
+    data[49..57].copy_from_slice(&44u64.to_le_bytes());
+    data[65..73].copy_from_slice(&9.9f64.to_le_bytes());
+    data[73..81].copy_from_slice(&10.1f64.to_le_bytes());
+    let switchboard = OracleState::decode(&switchboard_v1, &data).unwrap();
+    assert_eq!(switchboard.oracle_type, OracleType::SwitchboardV1);
+    assert_eq!(switchboard.price, 10.0);
+    assert!((switchboard.deviation - 0.2).abs() < 1e-9);
+    assert_eq!(switchboard.last_update_slot, 44);
+    data[0] = 1;
+    assert!(OracleState::decode(&switchboard_v1, &data).is_err());
+
+    let stub = OracleState::decode(&owner, &stub(1.5, 0.01, 45)).unwrap();
+    assert_eq!(
+        stub,
+        OracleState {
+            oracle_type: OracleType::Stub,
+            price: 1.5,
+            deviation: 0.01,
+            last_update_slot: 45,
+        }
+    );
+
+    assert!(OracleState::decode(&owner, &[0; 100]).is_err());
+    assert!(OracleState::decode(&owner, &pyth(1, 1, 0, 0)[..100]).is_err());
+}
+
+#[test]
+fn test_staleness_and_confidence() {
+    let oracle = state(100.0, 1.0, 100);
+    assert!(!oracle.is_stale(&config(0.1, 10), 110));
+    assert!(oracle.is_stale(&config(0.1, 10), 111));
+    assert!(!oracle.is_stale(&config(0.1, -1), 1_000));
+    assert!(oracle.has_valid_confidence(&config(0.01, -1)));
+    assert!(!oracle.has_valid_confidence(&config(0.009, -1)));
+
+    let price = OraclePrice::new(&config(0.1, 10), &oracle, None, 105);
+    assert_eq!(price.price, 100.0);
+    assert_eq!(price.usable(), Some(100.0));
+    let stale = OraclePrice::new(&config(0.1, 10), &oracle, None, 200);
+    assert!(stale.stale);
+    assert_eq!(stale.usable(), None);
+    let unconfident = OraclePrice::new(&config(0.001, 10), &oracle, None, 105);
+    assert!(!unconfident.confident);
+    assert_eq!(unconfident.usable(), None);
+}
+
+#[test]
+fn test_combined_price() {
+    let oracle_a = state(150.0, 0.45, 100);
+    let oracle_b = state(1.0, 0.004, 90);
+
+    let price = OraclePrice::new(&config(0.01, 20), &oracle_a, Some(&oracle_b), 105);
+    assert!((price.price - 150.0).abs() < 1e-9);
+    // Relative deviations of 0.3% and 0.4% compound to 0.5%.
+    assert!((price.deviation - 0.75).abs() < 1e-9);
+    assert_eq!(price.last_update_slot, 90);
+    assert!(price.confident);
+    assert!(!price.stale);
+
+    assert!(!OraclePrice::new(&config(0.004, 20), &oracle_a, Some(&oracle_b), 105).confident);
+    assert!(OraclePrice::new(&config(0.01, 10), &oracle_a, Some(&oracle_b), 105).stale);
+}
+
+#[test]
+fn test_market_oracle_price() {
+    let mut market: Market = bytemuck::Zeroable::zeroed();
+    market.oracle_config = config(0.1, -1);
+    assert!(oracle::market_oracles(&market).is_empty());
+    assert_eq!(oracle::market_oracle_price(&market, &[], 0).unwrap(), None);
+
+    let [oracle_a, oracle_b] = [Pubkey::new_unique(), Pubkey::new_unique()];
+    market.oracle_b = Some(oracle_b).into();
+    assert!(oracle::market_oracles(&market).is_empty());
+    market.oracle_a = Some(oracle_a).into();
+    assert_eq!(oracle::market_oracles(&market), vec![oracle_a, oracle_b]);
+
+    let account = |data| Account {
+        lamports: 1,
+        data,
+        owner: openbook_v2::id(),
+        executable: false,
+        rent_epoch: 0,
+    };
+    let accounts = [
+        Some(account(stub(30.0, 0.0, 0))),
+        Some(account(stub(2.0, 0.0, 0))),
+    ];
+    let price = oracle::market_oracle_price(&market, &accounts, 0)
+        .unwrap()
+        .unwrap();
+    assert_eq!(price.usable(), Some(15.0));
+
+    assert!(oracle::market_oracle_price(&market, &accounts[..1], 0).is_err());
+    assert!(oracle::market_oracle_price(&market, &[accounts[0].clone(), None], 0).is_err());
+}
@@ -0,0 +1,163 @@
+//! Tests of the position and PnL accounting.
+
+mod synthetic;
+
+use openbook::portfolio::{Balances, MarketPortfolio};
+use openbook::recorder::Change;
+use openbook::snapshot::{EventRecord, OpenOrderState, OpenOrdersState};
+use solana_sdk::pubkey::Pubkey;
+
+/// A fill between `maker` and `taker` of the taker order `seq_num`.
+fn fill(
+    seq_num: u64,
+    maker: (Pubkey, u8),
+    taker: Pubkey,
+    taker_side: u8,
+    price: i64,
+    quantity: i64,
+) -> EventRecord {
+    EventRecord::Fill {
+        heap_slot: 0,
+        taker_side,
+        maker_out: false,
+        maker_slot: maker.1,
+        timestamp: 0,
+        market_seq_num: seq_num,
+        maker: maker.0,
+        taker,
+        taker_client_order_id: 0,
+        maker_client_order_id: 0,
+        price,
+        quantity,
+    }
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "{actual} is not {expected}"
+    );
+}
+
+#[test]
+fn test_position_and_pnl() {
+    let account = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let mut portfolio = MarketPortfolio::new(synthetic::context(-100, 400), account);
+
+    let events = [
+        // Buys 2 and 2 base tokens at 2.0 and 2.2 taking, then sells 3 at 2.3 as maker.
+        fill(1, (other, 0), account, 0, 2_000, 2_000),
+        fill(2, (other, 1), account, 0, 2_200, 2_000),
+        fill(3, (account, 0), other, 0, 2_300, 3_000),
+        fill(3, (other, 2), Pubkey::new_unique(), 1, 2_300, 1_000),
+    ];
+    for event in events.iter().chain(&events) {
+        portfolio.apply_event(1, event);
+    }
+    let position = portfolio.position;
+    assert_eq!(position.fills, 3);
+    assert_eq!(position.base_lots, 1_000);
+    assert_eq!(position.average_entry_price_lots, 2_100.0);
+    assert_close(position.realized_pnl_native, 600_000.0);
+    assert_eq!(position.fees_paid_native, 1_600 + 1_760);
+    assert_eq!(position.maker_rebates_native, 690);
+    assert_eq!(position.volume_native, 15_300_000);
+
+    let balances = Balances {
+        wallet_base: 1_000_000_000,
+        wallet_quote: 10_000_000,
+        ..Default::default()
+    };
+    let pnl = portfolio.pnl(&balances, Some(2.5));
+    assert_eq!(pnl.position, 1.0);
+    assert_close(pnl.average_entry_price.unwrap(), 2.1);
+    assert_close(pnl.realized_pnl, 0.6);
+    assert_close(pnl.unrealized_pnl, 0.4);
+    assert_close(pnl.fees_paid, 0.00336);
+    assert_close(pnl.maker_rebates, 0.00069);
+    assert_close(pnl.net_pnl, 0.99733);
+    assert_close(pnl.equity.unwrap(), 12.5);
+    assert_eq!(portfolio.pnl(&balances, None).unrealized_pnl, 0.0);
+
+    // Sells 2 base tokens taking from two makers, closing the position and going short.
+    let events = [
+        fill(4, (other, 0), account, 1, 2_000, 1_000),
+        fill(4, (other, 1), account, 1, 1_900, 1_000),
+    ];
+    for event in events.iter().chain(&events) {
+        portfolio.apply_event(2, event);
+    }
+    let position = portfolio.position;
+    assert_eq!(position.fills, 5);
+    assert_eq!(position.base_lots, -1_000);
+    assert_eq!(position.average_entry_price_lots, 1_900.0);
+    assert_close(position.realized_pnl_native, 500_000.0);
+    assert_close(portfolio.pnl(&balances, Some(1.8)).unrealized_pnl, 0.1);
+}
+
+#[test]
+fn test_balances() {
+    let market = synthetic::context(-100, 400);
+    let account = OpenOrdersState {
+        name: "test".to_string(),
+        owner: Pubkey::new_unique(),
+        market: market.address,
+        delegate: None,
+        account_num: 0,
+        bids_base_lots: 500,
+        bids_quote_lots: 1_000_000,
+        asks_base_lots: 300,
+        base_free_native: 7,
+        quote_free_native: 9,
+        locked_maker_fees: 100,
+        referrer_rebates_available: 0,
+        orders: vec![
+            OpenOrderState {
+                order_id: 1,
+                client_order_id: 0,
+                side: 0,
+                locked_price: 2_000,
+            },
+            OpenOrderState {
+                order_id: 2,
+                client_order_id: 0,
+                side: 1,
+                locked_price: 2_400,
+            },
+        ],
+    };
+    let balances = Balances::new(&market, Some(&account), 1_000, 2_000);
+    assert_eq!(
+        balances,
+        Balances {
+            wallet_base: 1_000,
+            wallet_quote: 2_000,
+            free_base: 7,
+            free_quote: 9,
+            locked_base: 300_000_000,
+            locked_quote: 1_000_100,
+        }
+    );
+    assert_eq!(balances.base(), 300_001_007);
+    assert_eq!(balances.quote(), 1_002_109);
+    assert_eq!(Balances::new(&market, None, 1_000, 2_000).base(), 1_000);
+}
+
+#[test]
+fn test_records_and_gaps() {
+    let account = Pubkey::new_unique();
+    let context = synthetic::context(0, 0);
+    let market = context.address;
+    let mut portfolio = MarketPortfolio::new(context, account);
+
+    let other = Pubkey::new_unique();
+    let events = vec![fill(1, (other, 0), account, 0, 2_000, 1_000)];
+    portfolio.apply_records(&[
+        synthetic::record(market, 1, Change::Events { events }),
+        synthetic::record(market, 9, Change::Gap { last_slot: 1 }),
+        synthetic::record(Pubkey::new_unique(), 9, Change::Gap { last_slot: 1 }),
+    ]);
+    assert_eq!(portfolio.position.fills, 1);
+    assert_eq!(portfolio.gaps, 1);
+}
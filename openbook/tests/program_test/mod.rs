@@ -0,0 +1,560 @@
+//! In-process test harness for driving `OBClient` against a local OpenBook V2 program.
+//!
+//! The harness boots a `solana-test-validator` with the `openbook_v2` program loaded, and
+//! provides helpers to create mints, funded wallets, markets and open orders accounts.
+//!
+//! The program binary is not part of the repository. Dump it into `tests/fixtures` (or point
+//! `SBF_OUT_DIR` at a directory containing it) before running the tests:
+//!
+//! ```sh
+//! solana program dump -u m opnb2LAfJYbRMAHHvqjCwQxanZn7ReEHp1k81EohpZb tests/fixtures/openbook_v2.so
+//! ```
+//!
+//! The tests using the harness are ignored by default so that `cargo test` passes without the
+//! binary. Run them with `cargo test -- --ignored` once it is in place.
+
+#![allow(dead_code)]
+
+use std::mem::size_of;
+use std::sync::Arc;
+
+use anchor_lang::{prelude::System, Id};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{spl_token, Token},
+    token_2022::spl_token_2022::{
+        self,
+        extension::{transfer_fee, ExtensionType},
+    },
+};
+use anyhow::Result;
+use openbook::{
+    instructions,
+    ob_client::{self, OBClient},
+};
+use openbook_v2::state::{
+    BookSide, EventHeap, Market, OpenOrdersAccount, OpenOrdersIndexer, OracleConfigParams,
+    PlaceOrderType, SelfTradeBehavior, Side,
+};
+use openbook_v2::PlaceOrderArgs;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    system_instruction,
+    transaction::Transaction,
+};
+use solana_test_validator::{TestValidator, TestValidatorGenesis};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account,
+};
+
+/// Lamports given to every wallet created by the harness.
+pub const WALLET_LAMPORTS: u64 = 100_000_000_000;
+
+/// Native amount of each mint given to every wallet created by the harness.
+pub const WALLET_TOKENS: u64 = 1_000_000_000_000_000;
+
+pub const BASE_DECIMALS: u8 = 9;
+pub const QUOTE_DECIMALS: u8 = 6;
+pub const BASE_LOT_SIZE: i64 = 1_000_000;
+pub const QUOTE_LOT_SIZE: i64 = 1;
+
+/// Accounts of a market created through the harness.
+#[derive(Clone, Debug)]
+pub struct TestMarket {
+    pub market: Pubkey,
+    pub market_authority: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub event_heap: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub market_base_vault: Pubkey,
+    pub market_quote_vault: Pubkey,
+}
+
+/// A local validator with the OpenBook V2 program and a funded mint authority.
+pub struct TestContext {
+    pub validator: TestValidator,
+    pub payer: Arc<Keypair>,
+    pub rpc: RpcClient,
+}
+
+impl TestContext {
+    /// Starts a fresh validator with the `openbook_v2` program deployed.
+    pub async fn new() -> Self {
+        let (validator, payer) = TestValidatorGenesis::default()
+            .add_program("openbook_v2", openbook_v2::id())
+            .start_async()
+            .await;
+        let rpc =
+            RpcClient::new_with_commitment(validator.rpc_url(), CommitmentConfig::confirmed());
+
+        Self {
+            validator,
+            payer: Arc::new(payer),
+            rpc,
+        }
+    }
+
+    pub fn rpc_url(&self) -> String {
+        self.validator.rpc_url()
+    }
+
+    /// Signs `instructions` with the harness payer and `signers`, and sends them.
+    pub async fn send(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&dyn Signer],
+    ) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        let mut all_signers: Vec<&dyn Signer> = vec![self.payer.as_ref()];
+        all_signers.extend_from_slice(signers);
+        let trx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.send_transaction(&trx).await
+    }
+
+    pub async fn send_transaction(&self, trx: &Transaction) -> Result<Signature> {
+        Ok(self.rpc.send_and_confirm_transaction(trx).await?)
+    }
+
+    pub async fn create_mint(&self, decimals: u8) -> Result<Pubkey> {
+        let mint = Keypair::new();
+        let lamports = self
+            .rpc
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)
+            .await?;
+        let instructions = [
+            system_instruction::create_account(
+                &self.payer.pubkey(),
+                &mint.pubkey(),
+                lamports,
+                spl_token::state::Mint::LEN as u64,
+                &Token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &Token::id(),
+                &mint.pubkey(),
+                &self.payer.pubkey(),
+                None,
+                decimals,
+            )?,
+        ];
+        self.send(&instructions, &[&mint]).await?;
+        Ok(mint.pubkey())
+    }
+
+    /// Creates a Token-2022 mint withholding `basis_points` of every transfer, up to
+    /// `maximum_fee` native units.
+    pub async fn create_mint_with_transfer_fee(
+        &self,
+        decimals: u8,
+        basis_points: u16,
+        maximum_fee: u64,
+    ) -> Result<Pubkey> {
+        let mint = Keypair::new();
+        let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+            ExtensionType::TransferFeeConfig,
+        ])?;
+        let lamports = self
+            .rpc
+            .get_minimum_balance_for_rent_exemption(space)
+            .await?;
+        let instructions = [
+            system_instruction::create_account(
+                &self.payer.pubkey(),
+                &mint.pubkey(),
+                lamports,
+                space as u64,
+                &spl_token_2022::id(),
+            ),
+            transfer_fee::instruction::initialize_transfer_fee_config(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                Some(&self.payer.pubkey()),
+                Some(&self.payer.pubkey()),
+                basis_points,
+                maximum_fee,
+            )?,
+            spl_token_2022::instruction::initialize_mint(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                &self.payer.pubkey(),
+                None,
+                decimals,
+            )?,
+        ];
+        self.send(&instructions, &[&mint]).await?;
+        Ok(mint.pubkey())
+    }
+
+    /// The program owning `mint`, either the Token or the Token-2022 program.
+    pub async fn token_program(&self, mint: &Pubkey) -> Result<Pubkey> {
+        Ok(self.rpc.get_account(mint).await?.owner)
+    }
+
+    /// The associated token account of `owner` for `mint`, with the program owning the mint.
+    pub async fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Result<Pubkey> {
+        let token_program = self.token_program(mint).await?;
+        Ok(get_associated_token_address_with_program_id(
+            owner,
+            mint,
+            &token_program,
+        ))
+    }
+
+    /// Creates a wallet holding SOL and an ATA with `WALLET_TOKENS` of each mint.
+    pub async fn create_funded_wallet(&self, mints: &[Pubkey]) -> Result<Arc<Keypair>> {
+        let wallet = Keypair::new();
+        let mut instructions = vec![system_instruction::transfer(
+            &self.payer.pubkey(),
+            &wallet.pubkey(),
+            WALLET_LAMPORTS,
+        )];
+        for mint in mints {
+            let token_program = self.token_program(mint).await?;
+            instructions.push(create_associated_token_account(
+                &self.payer.pubkey(),
+                &wallet.pubkey(),
+                mint,
+                &token_program,
+            ));
+            instructions.push(spl_token_2022::instruction::mint_to(
+                &token_program,
+                mint,
+                &get_associated_token_address_with_program_id(
+                    &wallet.pubkey(),
+                    mint,
+                    &token_program,
+                ),
+                &self.payer.pubkey(),
+                &[],
+                WALLET_TOKENS,
+            )?);
+        }
+        self.send(&instructions, &[]).await?;
+        Ok(Arc::new(wallet))
+    }
+
+    /// Creates an account owned by the OpenBook program, sized for the zero-copy type `T`.
+    async fn create_program_account<T>(&self) -> Result<Keypair> {
+        let account = Keypair::new();
+        let space = 8 + size_of::<T>();
+        let lamports = self
+            .rpc
+            .get_minimum_balance_for_rent_exemption(space)
+            .await?;
+        self.send(
+            &[system_instruction::create_account(
+                &self.payer.pubkey(),
+                &account.pubkey(),
+                lamports,
+                space as u64,
+                &openbook_v2::id(),
+            )],
+            &[&account],
+        )
+        .await?;
+        Ok(account)
+    }
+
+    /// Allocates the accounts needed by `CreateMarket` and returns them with the market keypair.
+    pub async fn allocate_market(
+        &self,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+    ) -> Result<(Keypair, TestMarket)> {
+        let market = Keypair::new();
+        let bids = self.create_program_account::<BookSide>().await?;
+        let asks = self.create_program_account::<BookSide>().await?;
+        let event_heap = self.create_program_account::<EventHeap>().await?;
+        let market_authority = instructions::market_authority(&market.pubkey());
+
+        let test_market = TestMarket {
+            market: market.pubkey(),
+            market_authority,
+            bids: bids.pubkey(),
+            asks: asks.pubkey(),
+            event_heap: event_heap.pubkey(),
+            base_mint,
+            quote_mint,
+            market_base_vault: self.ata(&market_authority, &base_mint).await?,
+            market_quote_vault: self.ata(&market_authority, &quote_mint).await?,
+        };
+        Ok((market, test_market))
+    }
+
+    /// Creates a permissionless market without oracles or fees, administered by the harness
+    /// payer.
+    pub async fn create_market(&self, base_mint: Pubkey, quote_mint: Pubkey) -> Result<TestMarket> {
+        self.create_market_with_fees(base_mint, quote_mint, 0, 0)
+            .await
+    }
+
+    /// Creates a market like `create_market`, with `maker_fee` and `taker_fee` in millionths.
+    pub async fn create_market_with_fees(
+        &self,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        maker_fee: i64,
+        taker_fee: i64,
+    ) -> Result<TestMarket> {
+        self.create_market_with(base_mint, quote_mint, maker_fee, taker_fee, None)
+            .await
+    }
+
+    /// Creates a market like `create_market`, whose orders are co-signed by `open_orders_admin`.
+    pub async fn create_permissioned_market(
+        &self,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        open_orders_admin: Pubkey,
+    ) -> Result<TestMarket> {
+        self.create_market_with(base_mint, quote_mint, 0, 0, Some(open_orders_admin))
+            .await
+    }
+
+    async fn create_market_with(
+        &self,
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        maker_fee: i64,
+        taker_fee: i64,
+        open_orders_admin: Option<Pubkey>,
+    ) -> Result<TestMarket> {
+        let (market_keypair, market) = self.allocate_market(base_mint, quote_mint).await?;
+        let token_program = self.token_program(&base_mint).await?;
+
+        let ix = instructions::create_market(
+            openbook_v2::accounts::CreateMarket {
+                market: market.market,
+                market_authority: market.market_authority,
+                bids: market.bids,
+                asks: market.asks,
+                event_heap: market.event_heap,
+                payer: self.payer.pubkey(),
+                market_base_vault: market.market_base_vault,
+                market_quote_vault: market.market_quote_vault,
+                base_mint,
+                quote_mint,
+                system_program: System::id(),
+                oracle_a: None,
+                oracle_b: None,
+                collect_fee_admin: self.payer.pubkey(),
+                open_orders_admin,
+                consume_events_admin: None,
+                close_market_admin: None,
+                event_authority: instructions::event_authority(),
+                program: openbook_v2::id(),
+                token_program,
+                associated_token_program: AssociatedToken::id(),
+            },
+            "TEST-MARKET".to_string(),
+            default_oracle_config(),
+            QUOTE_LOT_SIZE,
+            BASE_LOT_SIZE,
+            maker_fee,
+            taker_fee,
+            0,
+        );
+        self.send(&[ix], &[&market_keypair]).await?;
+        Ok(market)
+    }
+
+    /// Creates the open orders indexer (if needed) and the next open orders account of `owner`.
+    pub async fn create_open_orders_account(
+        &self,
+        owner: &dyn Signer,
+        market: Pubkey,
+    ) -> Result<Pubkey> {
+        let open_orders_indexer = instructions::open_orders_indexer(&owner.pubkey());
+        let indexer = self.open_orders_indexer(&owner.pubkey()).await?;
+        let account_num = ob_client::next_account_num(indexer.as_ref());
+        let open_orders_account = instructions::open_orders_account(&owner.pubkey(), account_num);
+
+        let mut ixs = vec![];
+        if indexer.is_none() {
+            ixs.push(instructions::create_open_orders_indexer(
+                openbook_v2::accounts::CreateOpenOrdersIndexer {
+                    payer: self.payer.pubkey(),
+                    owner: owner.pubkey(),
+                    open_orders_indexer,
+                    system_program: System::id(),
+                },
+            ));
+        }
+        ixs.push(instructions::create_open_orders_account(
+            openbook_v2::accounts::CreateOpenOrdersAccount {
+                owner: owner.pubkey(),
+                open_orders_indexer,
+                open_orders_account,
+                payer: self.payer.pubkey(),
+                delegate_account: None,
+                market,
+                system_program: System::id(),
+            },
+            format!("test-{account_num}"),
+        ));
+        self.send(&ixs, &[owner]).await?;
+        Ok(open_orders_account)
+    }
+
+    /// Creates a funded wallet with an open orders account on `market` and an `OBClient` for it.
+    pub async fn create_client(&self, market: &TestMarket) -> Result<OBClient> {
+        let owner = self
+            .create_funded_wallet(&[market.base_mint, market.quote_mint])
+            .await?;
+        self.client_for(owner, market).await
+    }
+
+    /// Creates an open orders account of `owner` on `market` and an `OBClient` for it.
+    pub async fn client_for(&self, owner: Arc<Keypair>, market: &TestMarket) -> Result<OBClient> {
+        let open_orders_account = self
+            .create_open_orders_account(owner.as_ref(), market.market)
+            .await?;
+        OBClient::new(
+            self.rpc_url(),
+            owner,
+            Some(open_orders_account),
+            CommitmentConfig::confirmed(),
+            market.market,
+        )
+        .await
+    }
+
+    /// Places a crossing limit order as `taker`, matching against resting orders.
+    pub async fn place_taker_order(
+        &self,
+        taker: &OBClient,
+        side: Side,
+        price_lots: i64,
+        max_base_lots: i64,
+    ) -> Result<Signature> {
+        let (user_token_account, market_vault) = match side {
+            Side::Bid => (taker.quote_ata, taker.market_info.market_quote_vault),
+            Side::Ask => (taker.base_ata, taker.market_info.market_base_vault),
+        };
+        let ix = instructions::place_order(
+            openbook_v2::accounts::PlaceOrder {
+                open_orders_account: taker.open_orders_account,
+                open_orders_admin: None,
+                signer: taker.owner(),
+                market: taker.market_id,
+                bids: taker.market_info.bids,
+                asks: taker.market_info.asks,
+                event_heap: taker.market_info.event_heap,
+                oracle_a: None,
+                oracle_b: None,
+                user_token_account,
+                market_vault,
+                token_program: taker.side_token_program(side),
+            },
+            PlaceOrderArgs {
+                side,
+                price_lots,
+                max_base_lots,
+                max_quote_lots_including_fees: i64::MAX,
+                client_order_id: 0,
+                order_type: PlaceOrderType::ImmediateOrCancel,
+                expiry_timestamp: 0,
+                self_trade_behavior: SelfTradeBehavior::AbortTransaction,
+                limit: 12,
+            },
+        );
+        self.send(&[ix], &[taker.owner.as_ref()]).await
+    }
+
+    /// Processes pending fill and out events for the given open orders accounts.
+    pub async fn consume_events(
+        &self,
+        market: &TestMarket,
+        open_orders_accounts: &[Pubkey],
+    ) -> Result<Signature> {
+        let ix = instructions::consume_events(
+            openbook_v2::accounts::ConsumeEvents {
+                consume_events_admin: None,
+                market: market.market,
+                event_heap: market.event_heap,
+            },
+            open_orders_accounts,
+            10,
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    /// Settles the free balances of `client`'s open orders account back to its ATAs.
+    pub async fn settle_funds(&self, client: &OBClient, market: &TestMarket) -> Result<Signature> {
+        let ix = instructions::settle_funds(openbook_v2::accounts::SettleFunds {
+            owner: client.owner(),
+            penalty_payer: self.payer.pubkey(),
+            open_orders_account: client.open_orders_account,
+            market: market.market,
+            market_authority: market.market_authority,
+            market_base_vault: market.market_base_vault,
+            market_quote_vault: market.market_quote_vault,
+            user_base_account: client.base_ata,
+            user_quote_account: client.quote_ata,
+            referrer_account: None,
+            token_program: client.common_token_program()?,
+            system_program: System::id(),
+        });
+        self.send(&[ix], &[client.owner.as_ref()]).await
+    }
+
+    pub async fn open_orders_account(&self, address: &Pubkey) -> Result<OpenOrdersAccount> {
+        let account = self.rpc.get_account(address).await?;
+        Ok(anchor_lang::AccountDeserialize::try_deserialize(
+            &mut (&account.data as &[u8]),
+        )?)
+    }
+
+    pub async fn open_orders_indexer(&self, owner: &Pubkey) -> Result<Option<OpenOrdersIndexer>> {
+        let account = self
+            .rpc
+            .get_account_with_commitment(
+                &instructions::open_orders_indexer(owner),
+                CommitmentConfig::confirmed(),
+            )
+            .await?
+            .value;
+        account
+            .map(|account| {
+                anchor_lang::AccountDeserialize::try_deserialize(&mut (&account.data as &[u8]))
+            })
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    pub async fn market(&self, address: &Pubkey) -> Result<Market> {
+        let account = self.rpc.get_account(address).await?;
+        Ok(anchor_lang::AccountDeserialize::try_deserialize(
+            &mut (&account.data as &[u8]),
+        )?)
+    }
+
+    pub async fn token_balance(&self, address: &Pubkey) -> Result<u64> {
+        Ok(self
+            .rpc
+            .get_token_account_balance(address)
+            .await?
+            .amount
+            .parse()?)
+    }
+}
+
+pub fn default_oracle_config() -> OracleConfigParams {
+    OracleConfigParams {
+        conf_filter: 0.1,
+        max_staleness_slots: None,
+    }
+}
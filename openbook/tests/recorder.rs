@@ -0,0 +1,153 @@
+//! Tests of the market data recorder against a local validator.
+
+mod program_test;
+
+use anyhow::Result;
+use openbook::recorder::{read_records, Change, Recorder, RecorderConfig};
+use openbook_v2::state::Side;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use program_test::*;
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_record_book_changes_and_gaps() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let mut client = ctx.create_client(&market).await?;
+
+    let dir = std::env::temp_dir().join(format!("openbook-recorder-{}", market.market));
+    let config = RecorderConfig {
+        max_slot_gap: 0,
+        ..RecorderConfig::new(vec![market.market], &dir)
+    };
+    let mut recorder = Recorder::new(ctx.rpc_url(), CommitmentConfig::confirmed(), config).await?;
+
+    recorder.poll().await?;
+    ctx.send_transaction(&client.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+    recorder.poll().await?;
+
+    let files: Vec<_> = std::fs::read_dir(&dir)?.collect::<Result<_, _>>()?;
+    assert_eq!(files.len(), 1);
+    let records = read_records(files[0].path())?;
+    assert_eq!(records.len(), 3);
+    assert!(records.iter().all(|record| record.market == market.market));
+    assert_eq!(
+        records[0].change,
+        Change::Book {
+            bids: vec![],
+            asks: vec![]
+        }
+    );
+    assert_eq!(
+        records[1].change,
+        Change::Gap {
+            last_slot: records[0].slot
+        }
+    );
+    match &records[2].change {
+        Change::BookUpdate { bids, asks } => {
+            assert_eq!(bids.added.len(), 1);
+            assert_eq!(bids.added[0].owner, client.open_orders_account);
+            assert!(asks.is_empty());
+        }
+        change => panic!("unexpected change {change:?}"),
+    }
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_rotated_file_starts_with_the_book() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let mut client = ctx.create_client(&market).await?;
+
+    let dir = std::env::temp_dir().join(format!("openbook-recorder-{}", market.market));
+    let config = RecorderConfig {
+        max_slot_gap: 0,
+        max_file_bytes: 0,
+        ..RecorderConfig::new(vec![market.market], &dir)
+    };
+    let mut recorder = Recorder::new(ctx.rpc_url(), CommitmentConfig::confirmed(), config).await?;
+
+    recorder.poll().await?;
+    ctx.send_transaction(&client.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+    recorder.poll().await?;
+
+    let mut files: Vec<_> = std::fs::read_dir(&dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    files.sort();
+    assert_eq!(files.len(), 2);
+    let records = read_records(&files[1])?;
+    assert_eq!(records.len(), 2);
+    match &records[0].change {
+        Change::Book { bids, asks } => {
+            assert_eq!(bids.len(), 1);
+            assert!(asks.is_empty());
+        }
+        change => panic!("unexpected change {change:?}"),
+    }
+    assert!(matches!(records[1].change, Change::Gap { .. }));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_consumed_events_are_recorded_as_gaps() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let mut maker = ctx.create_client(&market).await?;
+    let taker = ctx.create_client(&market).await?;
+    ctx.send_transaction(&maker.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+
+    let dir = std::env::temp_dir().join(format!("openbook-recorder-{}", market.market));
+    let config = RecorderConfig {
+        max_slot_gap: u64::MAX,
+        ..RecorderConfig::new(vec![market.market], &dir)
+    };
+    let mut recorder = Recorder::new(ctx.rpc_url(), CommitmentConfig::confirmed(), config).await?;
+    recorder.poll().await?;
+
+    // The fill is pushed and consumed between two polls.
+    let bid_lots = ctx
+        .open_orders_account(&maker.open_orders_account)
+        .await?
+        .position
+        .bids_base_lots;
+    ctx.place_taker_order(&taker, Side::Ask, 1_000, bid_lots)
+        .await?;
+    ctx.consume_events(&market, &[maker.open_orders_account])
+        .await?;
+    recorder.poll().await?;
+
+    let files: Vec<_> = std::fs::read_dir(&dir)?.collect::<Result<_, _>>()?;
+    let records = read_records(files[0].path())?;
+    assert!(!records
+        .iter()
+        .any(|record| matches!(record.change, Change::Events { .. })));
+    assert_eq!(
+        records
+            .iter()
+            .filter(|record| matches!(record.change, Change::Gap { .. }))
+            .count(),
+        1
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
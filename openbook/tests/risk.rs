@@ -0,0 +1,205 @@
+//! Tests of the pre-trade risk checks.
+
+mod program_test;
+mod synthetic;
+
+use anyhow::Result;
+use openbook::context::OrderLots;
+use openbook::risk::{
+    self, OrderRateLimiter, PriceReference, RiskLimits, RiskState, RiskViolation,
+};
+use openbook_v2::state::Side;
+use solana_sdk::signature::Signature;
+
+use program_test::*;
+
+fn lots(price_lots: i64, max_base_lots: u64) -> OrderLots {
+    OrderLots {
+        price_lots,
+        max_base_lots,
+        max_quote_lots: price_lots as u64 * max_base_lots,
+    }
+}
+
+#[test]
+fn test_check_orders() {
+    let market = synthetic::context(0, 0);
+    let state = RiskState {
+        best_bid: Some(1_900),
+        best_ask: Some(2_100),
+        oracle_price: Some(2.0),
+        base_inventory: 5_000_000_000,
+        resting_bid_lots: 4_000,
+        open_orders: 2,
+    };
+    let check = |limits: RiskLimits, orders: &[(Side, OrderLots)]| {
+        risk::check_orders(&limits, &market, &state, orders)
+    };
+
+    let limits = RiskLimits {
+        max_order_notional: Some(10.0),
+        ..Default::default()
+    };
+    assert_eq!(
+        check(limits, &[(Side::Bid, lots(2_000, 10_000))]),
+        Err(RiskViolation::OrderNotional {
+            notional: 20.0,
+            max: 10.0
+        })
+    );
+    assert!(check(limits, &[(Side::Ask, lots(2_000, 5_000))]).is_ok());
+
+    let mut limits = RiskLimits {
+        price_band_bps: Some(100.0),
+        ..Default::default()
+    };
+    assert_eq!(
+        check(limits, &[(Side::Bid, lots(2_200, 1))]),
+        Err(RiskViolation::PriceBand {
+            price: 2.2,
+            reference: 2.1,
+            max_bps: 100.0
+        })
+    );
+    assert!(check(limits, &[(Side::Ask, lots(1_850, 1))]).is_err());
+    assert!(check(
+        limits,
+        &[(Side::Ask, lots(1_890, 1)), (Side::Bid, lots(1_000, 1))]
+    )
+    .is_ok());
+    limits.price_band_reference = PriceReference::Oracle;
+    assert!(check(limits, &[(Side::Bid, lots(2_030, 1))]).is_err());
+
+    // 5 tokens held, 4 in resting bids and 10 in the new bid.
+    let limits = RiskLimits {
+        max_position: Some(15.0),
+        ..Default::default()
+    };
+    assert_eq!(
+        check(limits, &[(Side::Bid, lots(2_000, 10_000))]),
+        Err(RiskViolation::Position {
+            position: 19.0,
+            max: 15.0
+        })
+    );
+    assert!(check(limits, &[(Side::Ask, lots(2_000, 10_000))]).is_ok());
+
+    let limits = RiskLimits {
+        max_open_orders: Some(3),
+        ..Default::default()
+    };
+    assert!(check(limits, &[(Side::Bid, lots(2_000, 1))]).is_ok());
+    assert_eq!(
+        check(
+            limits,
+            &[(Side::Bid, lots(2_000, 1)), (Side::Ask, lots(2_200, 1))]
+        ),
+        Err(RiskViolation::OpenOrders {
+            open_orders: 4,
+            max: 3
+        })
+    );
+}
+
+#[test]
+fn test_rate_limiter() {
+    let limiter = OrderRateLimiter::default();
+    assert!(limiter.check(3, 2).is_ok());
+    limiter.record(2);
+    assert_eq!(
+        limiter.check(3, 2),
+        Err(RiskViolation::RateLimit { orders: 2, max: 3 })
+    );
+    assert!(limiter.check(3, 1).is_ok());
+
+    // Reserved orders count until their transaction failed.
+    let [sent, failed] = [Signature::new_unique(), Signature::new_unique()];
+    limiter.reserve(sent, 1);
+    assert!(limiter.check(3, 1).is_err());
+    limiter.settle(&sent, true);
+    limiter.reserve(failed, 1);
+    assert_eq!(
+        limiter.check(4, 1),
+        Err(RiskViolation::RateLimit { orders: 4, max: 4 })
+    );
+    limiter.settle(&failed, false);
+    assert!(limiter.clone().check(4, 1).is_ok());
+    // Settling again does not count the orders twice.
+    limiter.settle(&sent, true);
+    assert!(limiter.check(4, 1).is_ok());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_client_rate_limit_counts_reserved_orders() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let mut client = ctx.create_client(&market).await?;
+    client.settings.risk = Some(RiskLimits {
+        max_orders_per_minute: Some(1),
+        ..Default::default()
+    });
+
+    // A transaction built but not sent yet holds its orders against the limit.
+    let trx = client.place_limit_order(2.0, 10, Side::Bid).await?;
+    let err = client
+        .place_limit_order(1.9, 10, Side::Bid)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RiskViolation>(),
+        Some(&RiskViolation::RateLimit { orders: 1, max: 1 })
+    );
+
+    client.send_trx(&trx).await?;
+    let err = client
+        .place_limit_order(1.9, 10, Side::Bid)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RiskViolation>(),
+        Some(&RiskViolation::RateLimit { orders: 1, max: 1 })
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_client_rejects_orders() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let mut client = ctx.create_client(&market).await?;
+    client.settings.risk = Some(RiskLimits {
+        max_order_notional: Some(50.0),
+        max_open_orders: Some(1),
+        ..Default::default()
+    });
+
+    let err = client
+        .place_limit_order(2.0, 100, Side::Bid)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<RiskViolation>(),
+        Some(RiskViolation::OrderNotional { .. })
+    ));
+
+    ctx.send_transaction(&client.place_limit_order(2.0, 10, Side::Bid).await?)
+        .await?;
+    let err = client
+        .place_limit_order(1.9, 10, Side::Bid)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RiskViolation>(),
+        Some(&RiskViolation::OpenOrders {
+            open_orders: 2,
+            max: 1
+        })
+    );
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! Tests of the strategy runtime, against a local validator, and of its order ids.
+
+mod program_test;
+
+use anyhow::Result;
+use openbook::strategy::{
+    self, OrderIntent, Quote, RuntimeConfig, Strategy, StrategyContext, StrategyRuntime,
+};
+use openbook_v2::state::Side;
+
+use program_test::*;
+
+#[test]
+fn test_replace_orders_ids() {
+    let quote = |price_lots| Quote {
+        price_lots,
+        quote_size: 10,
+    };
+    let bids = [quote(1_900), quote(1_800)];
+    let asks = [quote(2_100)];
+    let orders: Vec<_> = strategy::replace_orders(&bids, &asks)
+        .map(|(client_order_id, side, quote)| (client_order_id, side, quote.price_lots))
+        .collect();
+    assert_eq!(
+        orders,
+        [
+            (0, Side::Bid, 1_900),
+            (1, Side::Bid, 1_800),
+            (2, Side::Ask, 2_100)
+        ]
+    );
+}
+
+/// Quotes a single bid at 2.0.
+#[derive(Default)]
+struct SingleBid {
+    books: usize,
+}
+
+impl Strategy for SingleBid {
+    fn on_book(&mut self, ctx: &StrategyContext) -> Vec<OrderIntent> {
+        self.books += 1;
+        if self.books > 1 {
+            return vec![];
+        }
+        assert!(ctx.bids.iter().all(|order| order.price_lots != 2_000));
+        vec![OrderIntent::Place {
+            side: Side::Bid,
+            limit_price: 2.0,
+            quote_size: 10,
+        }]
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_runtime_reconciles_and_cancels_on_shutdown() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let mut client = ctx.create_client(&market).await?;
+
+    // An order left over from a previous run.
+    ctx.send_transaction(&client.place_limit_order(1.0, 10, Side::Bid).await?)
+        .await?;
+    let open_orders_account = client.open_orders_account;
+
+    let mut runtime = StrategyRuntime::new(client, SingleBid::default(), RuntimeConfig::default());
+    runtime.poll().await?;
+    assert_eq!(runtime.strategy.books, 1);
+
+    let account = ctx.open_orders_account(&open_orders_account).await?;
+    let orders: Vec<_> = account.all_orders_in_use().collect();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(runtime.intended_orders(), &[orders[0].client_id]);
+
+    runtime.poll().await?;
+    assert_eq!(runtime.intended_orders().len(), 1);
+
+    runtime.shutdown().await?;
+    let account = ctx.open_orders_account(&open_orders_account).await?;
+    assert_eq!(account.all_orders_in_use().count(), 0);
+    assert!(runtime.intended_orders().is_empty());
+    Ok(())
+}
+
+/// Quotes an oversized bid followed by a valid one.
+struct OversizedFirst;
+
+impl Strategy for OversizedFirst {
+    fn on_book(&mut self, _ctx: &StrategyContext) -> Vec<OrderIntent> {
+        [100, 10]
+            .into_iter()
+            .map(|quote_size| OrderIntent::Place {
+                side: Side::Bid,
+                limit_price: 2.0,
+                quote_size,
+            })
+            .collect()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_runtime_executes_intents_after_a_failure() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let mut client = ctx.create_client(&market).await?;
+    client.settings.max_size = Some(50);
+    let open_orders_account = client.open_orders_account;
+
+    let mut runtime = StrategyRuntime::new(client, OversizedFirst, RuntimeConfig::default());
+    let err = runtime.poll().await.unwrap_err();
+    assert!(err.to_string().contains("1 of 2 strategy intents failed"));
+
+    let account = ctx.open_orders_account(&open_orders_account).await?;
+    assert_eq!(account.all_orders_in_use().count(), 1);
+    assert_eq!(runtime.intended_orders().len(), 1);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs tests/fixtures/openbook_v2.so"]
+async fn test_runtime_counts_fills_consumed_between_polls() -> Result<()> {
+    let ctx = TestContext::new().await;
+    let base_mint = ctx.create_mint(BASE_DECIMALS).await?;
+    let quote_mint = ctx.create_mint(QUOTE_DECIMALS).await?;
+    let market = ctx.create_market(base_mint, quote_mint).await?;
+    let client = ctx.create_client(&market).await?;
+    let taker = ctx.create_client(&market).await?;
+    let open_orders_account = client.open_orders_account;
+
+    let mut runtime = StrategyRuntime::new(client, SingleBid::default(), RuntimeConfig::default());
+    runtime.poll().await?;
+    let bid_lots = ctx
+        .open_orders_account(&open_orders_account)
+        .await?
+        .position
+        .bids_base_lots;
+    ctx.place_taker_order(&taker, Side::Ask, 1_000, bid_lots)
+        .await?;
+    ctx.consume_events(&market, &[open_orders_account]).await?;
+
+    runtime.poll().await?;
+    assert_eq!(runtime.missed_events(), 1);
+    assert!(runtime.intended_orders().is_empty());
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Synthetic markets, book orders and recordings shared by the tests which do not need a
+//! validator.
+//!
+//! Markets have 9 base decimals, 6 quote decimals, a base lot of 1_000_000 and a quote lot of 1,
+//! so a price of 1 quote lot per base lot is 0.001 quote tokens per base token.
+
+#![allow(dead_code)]
+
+use openbook::book::BookOrder;
+use openbook::context::MarketContext;
+use openbook::recorder::{Change, Record};
+use openbook_v2::state::Market;
+use solana_sdk::pubkey::Pubkey;
+
+/// A market with `maker_fee` and `taker_fee` in millionths.
+pub fn market(maker_fee: i64, taker_fee: i64) -> Market {
+    let mut market: Market = bytemuck::Zeroable::zeroed();
+    market.base_decimals = 9;
+    market.quote_decimals = 6;
+    market.base_lot_size = 1_000_000;
+    market.quote_lot_size = 1;
+    market.maker_fee = maker_fee;
+    market.taker_fee = taker_fee;
+    market
+}
+
+/// The context of `market(maker_fee, taker_fee)` at a new address, with classic SPL mints.
+pub fn context(maker_fee: i64, taker_fee: i64) -> MarketContext {
+    MarketContext {
+        address: Pubkey::new_unique(),
+        market: market(maker_fee, taker_fee),
+        base_mint_info: Default::default(),
+        quote_mint_info: Default::default(),
+    }
+}
+
+/// A resting order of a new owner, without expiry.
+pub fn order(order_id: u128, price_lots: i64, quantity: i64) -> BookOrder {
+    BookOrder {
+        order_id,
+        owner: Pubkey::new_unique(),
+        owner_slot: 0,
+        client_order_id: 0,
+        price_lots,
+        quantity,
+        timestamp: 0,
+        time_in_force: 0,
+    }
+}
+
+/// A record at `1_700_000_000 + slot / 2` seconds.
+pub fn record(market: Pubkey, slot: u64, change: Change) -> Record {
+    Record {
+        market,
+        slot,
+        wall_clock_ms: 1_700_000_000_000 + slot * 500,
+        change,
+    }
+}
@@ -0,0 +1,60 @@
+//! Tests of the Token-2022 transfer fees and of the order sizes they leave.
+
+mod synthetic;
+
+use openbook::token::TransferFee;
+use openbook_v2::state::Side;
+
+#[test]
+fn test_transfer_fee_rounding_and_cap() {
+    let fee = TransferFee {
+        basis_points: 50,
+        maximum_fee: 5_000,
+    };
+    assert_eq!(fee.fee(0), 0);
+    assert_eq!(fee.fee(1), 1);
+    assert_eq!(fee.fee(10_000), 50);
+    assert_eq!(fee.fee(10_000_000), 5_000);
+
+    for received in [1, 999, 10_000, 123_456, 10_000_000] {
+        let sent = fee.amount_before_fee(received);
+        assert_eq!(fee.amount_after_fee(sent), received);
+    }
+}
+
+const ONE_PERCENT: TransferFee = TransferFee {
+    basis_points: 100,
+    maximum_fee: u64::MAX,
+};
+
+#[test]
+fn test_base_transfer_fee_only_shrinks_asks() {
+    let plain = synthetic::context(0, 0);
+    let mut context = synthetic::context(0, 0);
+    context.base_mint_info.transfer_fee = Some(ONE_PERCENT);
+
+    assert_eq!(
+        context.order_lots(Side::Bid, 1.5, 100),
+        plain.order_lots(Side::Bid, 1.5, 100)
+    );
+    let ask = context.order_lots(Side::Ask, 1.5, 100);
+    let plain_ask = plain.order_lots(Side::Ask, 1.5, 100);
+    assert!(ask.max_base_lots < plain_ask.max_base_lots);
+    assert_eq!(ask.max_quote_lots, plain_ask.max_quote_lots);
+}
+
+#[test]
+fn test_quote_transfer_fee_only_shrinks_bids() {
+    let plain = synthetic::context(0, 0);
+    let mut context = synthetic::context(0, 0);
+    context.quote_mint_info.transfer_fee = Some(ONE_PERCENT);
+
+    assert_eq!(
+        context.order_lots(Side::Ask, 1.5, 100),
+        plain.order_lots(Side::Ask, 1.5, 100)
+    );
+    let bid = context.order_lots(Side::Bid, 1.5, 100);
+    let plain_bid = plain.order_lots(Side::Bid, 1.5, 100);
+    assert!(bid.max_quote_lots < plain_bid.max_quote_lots);
+    assert_eq!(bid.max_base_lots, plain_bid.max_base_lots);
+}